@@ -1,59 +1,599 @@
 use crate::assembler::{
     assembly_error::{AssemblyError, AssemblyErrorVariant},
-    grammar::construct_instruction::{construct_instruction, find_matching_pattern},
+    diagnostic::{Diagnostic, Severity},
+    directives,
+    includes::expand_includes,
+    labels::{label_names, resolve_labels},
+    macros::{expand_macros, locate},
+    position::{Position, SourceMap},
+    pseudo::{self, is_pseudo},
+    relaxation,
+    relocation::{self, Relocation, RelocationKind},
+    grammar::expression::{group_operands, Expression, Symbols},
+    grammar::construct_instruction::{construct_instruction, explain_mismatch, find_matching_pattern},
     grammar::token_pattern::AmbiguousToken,
     tokenization::tokenize::Tokenizer,
-    tokenization::raw_token::RawToken,
-    tokenization::token::{Token, TokenVariant::*},
+    tokenization::token::{Token, TokenVariant},
     tokenization::tokenization_error::TokenizationError,
+    types::directive::Directive,
+    types::opcode::Opcode,
+    types::pseudo::Pseudo,
+    types::section::Section,
+    suggest::closest,
 };
-use std::collections::HashMap;
+use crate::cpu::MEMORY_SIZE;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
 
+/// Assembles a program into the words of its image. A partial last word is padded with zeros.
+#[cfg(test)]
 pub fn assemble(src: String) -> Result<Vec<u32>, AssemblyError> {
-    let mut instructions = Vec::new();
+    let image = assemble_image(src)?;
 
-    let mut tokenizer = Tokenizer::new(src);
-    let raw_tokens = tokenizer.tokenize()?;
-    let token_stream = make_raw_tokens_normal(raw_tokens)?;
-    let token_lines = collect_into_lines(token_stream);
+    Ok(image.chunks(4).map(|chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        u32::from_be_bytes(word)
+    }).collect())
+}
+
+/// Assembles a program into a memory image starting at address 0. Only the first error is returned.
+#[cfg(test)]
+pub fn assemble_image(src: String) -> Result<Vec<u8>, AssemblyError> {
+    match assemble_source(src, None, &Options::default()) {
+        Ok(assembly) => Ok(assembly.image),
+        Err(diagnostics) => Err(diagnostics.into_iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+            .expect("Assembly failed without an error")
+            .error),
+    }
+}
+
+/// A program that was assembled
+pub struct Assembly {
+    /// Memory image starting at address 0
+    pub image: Vec<u8>,
+    pub warnings: Vec<Diagnostic>,
+    /// Notes about the branches that were replaced by a jump through a register
+    pub relaxed: Vec<Diagnostic>,
+    /// Every label, instruction and directive in the order of their addresses
+    pub placements: Vec<Placement>,
+    pub sources: Rc<SourceMap>,
+    /// Address of every label and the values of constants
+    pub symbols: Symbols,
+    pub linkage: Linkage,
+}
+
+/// What the linker needs to know about a program to place it elsewhere
+#[derive(Debug, Clone, Default)]
+pub struct Linkage {
+    /// Addresses each section takes up in the image, in the order of [`Section::ALL`]
+    pub sections: [Range<usize>; 3],
+    /// Section of each label
+    pub label_sections: HashMap<String, Section>,
+    /// Labels other programs can refer to, from `.global`
+    pub exports: Vec<String>,
+    /// Labels defined by other programs, from `.extern`
+    pub imports: Vec<String>,
+    /// Fields holding addresses, only for a relocatable program
+    pub relocations: Vec<Relocation>,
+}
+
+/// Where a line of the program ended up in the image
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Placement {
+    pub address: usize,
+    /// Bytes written at the address. Padding from `.org`, `.align` and `.space` isn't counted.
+    pub length: usize,
+    pub kind: PlacementKind,
+    /// The line it was written on. For a line produced by a macro it's the line in the macro's body.
+    pub position: Position,
+    /// Labels the line refers to and their addresses
+    pub targets: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PlacementKind {
+    Label(String),
+    Code,
+    Data,
+}
+
+/// How a program is assembled
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Whether branches to labels out of reach of their offset are replaced by a jump through a register.
+    /// Without it they're errors.
+    pub relax_branches: bool,
+    /// Whether the program is assembled for the linker, which places its sections and fills in the addresses of labels.
+    /// Branches aren't relaxed then and `li` and `la` of a label always take four instructions.
+    pub relocatable: bool,
+    /// Bytes the program may take, the emulator's memory by default
+    pub memory_size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self { relax_branches: true, relocatable: false, memory_size: MEMORY_SIZE }
+    }
+}
+
+/// Assembles a program read from `path`. Errors name the file and files it includes are looked up next to it.
+/// A line with an error is skipped so the errors of all lines are reported together, followed by the warnings.
+pub fn assemble_source(src: String, path: Option<&Path>, options: &Options) -> Result<Assembly, Vec<Diagnostic>> {
+    let mut image = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut sources = SourceMap::default();
+
+    let file = path.map(Rc::from);
+    sources.insert(file.clone(), src.clone());
+
+    let token_lines = collect_into_lines(tokenize(src, file.as_ref(), &mut errors));
+    let token_lines = expand_includes(token_lines, file.as_ref(), &mut sources, &mut errors);
+    let token_lines = expand_macros(token_lines, &mut errors);
+    let token_lines = resolve_labels(token_lines, &mut errors);
 
-    let (token_lines, labels) = extract_labels(token_lines);
+    let layout = layout(token_lines, options, &mut errors);
 
-    for (instruction, tokens) in token_lines.iter().enumerate() {
-        let ambiguous_tokens = make_tokens_ambiguous(tokens);
+    // Imported labels are at 0 until the program is linked
+    let mut symbols = layout.symbols.clone();
+    for name in layout.imports.iter().filter(|_| options.relocatable) {
+        symbols.labels.entry(name.to_owned()).or_insert(0);
+    }
+
+    let mut placements = Vec::with_capacity(layout.statements.len());
+    let mut relocations = Vec::new();
+
+    for statement in &layout.statements {
+        // Padding from .org, .align and .space
+        image.resize(statement.address, 0);
 
-        let pattern = match find_matching_pattern(&ambiguous_tokens) {
-            None => return Err(AssemblyError {
-                line: tokens[0].line,
-                column: None,
-                variant: AssemblyErrorVariant::UnknownTokenPattern
-            }),
-            Some(pattern) => pattern,
+        let bytes = match options.relocatable {
+            true => assemble_relocatable(statement, &layout, &symbols, &mut relocations),
+            false => assemble_statement(statement, &symbols, false),
         };
 
-        let constructed_instruction = construct_instruction(tokens, &pattern.bit_pattern, &pattern.encoding, &labels, instruction)?;
-        instructions.push(constructed_instruction);
+        match bytes {
+            Ok(bytes) => {
+                placements.push(placement(statement, bytes.len(), &layout.symbols));
+                image.extend(bytes);
+            }
+            Err(err) => errors.push(locate(err, &statement.tokens[0])),
+        }
+    }
+
+    image.resize(layout.end, 0);
+
+    let sources = Rc::new(sources);
+    let warnings = layout.warnings.into_iter().map(|warning| Diagnostic::new(Severity::Warning, warning, &sources));
+
+    if errors.is_empty() {
+        let relaxed = layout.statements.iter().filter_map(|statement| {
+            let label = relaxation::target(&statement.tokens).filter(|_| statement.size > 4)?;
+            let note = AssemblyError::spanning(&statement.tokens, AssemblyErrorVariant::BranchRelaxed { label: label.to_owned(), instructions: statement.size / 4 });
+            Some(Diagnostic::new(Severity::Note, locate(note, &statement.tokens[0]), &sources))
+        });
+
+        let relaxed = relaxed.collect();
+
+        let linkage = Linkage {
+            sections: Section::ALL.map(|section| layout.starts[section.index()]..layout.ends[section.index()]),
+            label_sections: layout.label_sections,
+            exports: layout.exports.iter().flat_map(label_names).collect(),
+            imports: layout.imports,
+            relocations,
+        };
+
+        Ok(Assembly { image, warnings: warnings.collect(), relaxed, placements, sources, symbols: layout.symbols, linkage })
+    } else {
+        Err(errors.into_iter().map(|error| Diagnostic::new(Severity::Error, error, &sources)).chain(warnings).collect())
+    }
+}
+
+/// Bytes of an instruction, pseudo-instruction or directive. A label has none.
+fn assemble_statement(statement: &Statement, symbols: &Symbols, relocatable: bool) -> Result<Vec<u8>, AssemblyError> {
+    let tokens = &statement.tokens;
+
+    match tokens[0].variant {
+        TokenVariant::Directive(directive) => return directives::emit(directive, &tokens[1..], symbols),
+        TokenVariant::Label(_) => return Ok(Vec::new()),
+        _ => {}
+    }
+
+    let relaxed = relaxation::target(tokens).filter(|_| statement.size > 4);
+
+    if is_pseudo(tokens) || relaxed.is_some() {
+        let lines = match relaxed {
+            Some(label) => relaxation::expand(tokens, statement.address, label_address(&tokens[1], label, symbols)?, statement.size),
+            None => pseudo::expand(tokens, relocatable, |token| Expression::from_token(token).expect("Only values are evaluated").evaluate(symbols, token))?,
+        };
+        assert!(lines.len() * 4 <= statement.size, "A pseudo-instruction grew after the layout settled");
+
+        let mut bytes = Vec::with_capacity(statement.size);
+        for (i, line) in lines.iter().enumerate() {
+            bytes.extend(assemble_instruction(line, symbols, statement.address + i * 4)?);
+        }
+
+        // One that got shorter than in an earlier pass keeps its size, padded with nops
+        bytes.resize(statement.size, 0);
+        return Ok(bytes);
+    }
+
+    assemble_instruction(tokens, symbols, statement.address)
+}
+
+/// Bytes of a statement of a relocatable program, adding the fields the linker fills in to `relocations`.
+/// A branch to a label of another section is assembled as a branch to itself.
+fn assemble_relocatable(statement: &Statement, layout: &Layout, symbols: &Symbols, relocations: &mut Vec<Relocation>) -> Result<Vec<u8>, AssemblyError> {
+    let found = relocation::relocations(&statement.tokens, statement.address, statement.section, &layout.label_sections, &layout.symbols)?;
+
+    let bytes = match found.first() {
+        Some(Relocation { kind: RelocationKind::Branch, symbol, .. }) => {
+            let itself = Symbols { labels: HashMap::from([(symbol.to_owned(), statement.address)]), constants: HashMap::new() };
+            assemble_statement(statement, &itself, true)?
+        }
+        _ => assemble_statement(statement, symbols, true)?,
+    };
+
+    relocations.extend(found);
+    Ok(bytes)
+}
+
+/// Where a statement that was assembled into `length` bytes ended up
+fn placement(statement: &Statement, length: usize, symbols: &Symbols) -> Placement {
+    let kind = match &statement.tokens[0].variant {
+        TokenVariant::Label(name) => PlacementKind::Label(name.to_owned()),
+        TokenVariant::Directive(_) => PlacementKind::Data,
+        _ => PlacementKind::Code,
+    };
+
+    let names = statement.tokens[1..].iter().flat_map(label_names);
+
+    let mut targets = Vec::<(String, usize)>::new();
+    for name in names {
+        if let Some(&address) = symbols.labels.get(&name) {
+            if !targets.iter().any(|(target, _)| *target == name) {
+                targets.push((name, address));
+            }
+        }
+    }
+
+    Placement { address: statement.address, length, kind, position: statement.tokens[0].position.clone(), targets }
+}
+
+/// Address of a label a relaxed branch goes to
+fn label_address(token: &Token, name: &str, symbols: &Symbols) -> Result<usize, AssemblyError> {
+    match symbols.labels.get(name) {
+        None => Err(AssemblyError::at(token, AssemblyErrorVariant::NoLabelFound { name: name.to_owned(), suggestion: symbols.closest_label(name) })),
+        Some(&address) if !address.is_multiple_of(4) => Err(AssemblyError::at(token, AssemblyErrorVariant::MisalignedLabel { name: name.to_owned(), address })),
+        Some(&address) => Ok(address),
+    }
+}
+
+/// Bytes of a single real instruction
+fn assemble_instruction(tokens: &[Token], symbols: &Symbols, address: usize) -> Result<Vec<u8>, AssemblyError> {
+    let ambiguous_tokens = make_tokens_ambiguous(tokens);
+
+    let pattern = match find_matching_pattern(&ambiguous_tokens) {
+        None => return Err(AssemblyError::spanning(tokens, explain_mismatch(&ambiguous_tokens))),
+        Some(pattern) => pattern,
+    };
+
+    let constructed_instruction = construct_instruction(tokens, pattern, symbols, address)?;
+    Ok(constructed_instruction.to_be_bytes().to_vec())
+}
+
+/// A label, instruction, pseudo-instruction or directive placed in the image
+struct Statement {
+    address: usize,
+    section: Section,
+    tokens: Vec<Token>,
+    /// Bytes it occupies
+    size: usize,
+}
+
+/// Where everything in a program is placed
+struct Layout {
+    /// Instructions and directives in the order of their addresses
+    statements: Vec<Statement>,
+    /// Address of what follows each label and the values of constants
+    symbols: Symbols,
+    /// Address after the last statement of the current section
+    end: usize,
+    /// Section lines are placed in, chosen with `.section`
+    section: Section,
+    /// Address each section starts at. A section follows the one before it, with the size it had in the previous pass.
+    starts: [usize; 3],
+    /// Address after the last statement of each section. For the current section it's only updated when switching away.
+    ends: [usize; 3],
+    /// Section of each label
+    label_sections: HashMap<String, Section>,
+    /// Operands of `.global`
+    exports: Vec<Token>,
+    /// Operands of `.extern`
+    imports: Vec<String>,
+    warnings: Vec<AssemblyError>,
+    /// Whether the last statement was an instruction execution never continues after
+    diverted: bool,
+    /// Symbols of the previous pass, for estimating pseudo-instructions that use labels further down
+    previous: Symbols,
+    /// Bytes each pseudo-instruction and branch took in the passes so far, by line. They never shrink, so the passes settle.
+    sizes: HashMap<usize, usize>,
+    /// Whether a size was estimated from the previous pass
+    estimated: bool,
+    /// Whether branches out of reach are relaxed
+    relax: bool,
+    relocatable: bool,
+    /// Bytes the program may take
+    memory_size: usize,
+}
+
+/// Where each section starts when they have the sizes of `starts` to `ends`. Text is at 0, the others follow aligned.
+fn section_starts(starts: &[usize; 3], ends: &[usize; 3]) -> [usize; 3] {
+    let mut next = [0; 3];
+
+    for i in 1..next.len() {
+        next[i] = (next[i - 1] + ends[i - 1] - starts[i - 1]).next_multiple_of(Section::ALIGNMENT);
+    }
+
+    next
+}
+
+/// Places every line. The size of a pseudo-instruction or a relaxed branch can depend on labels further down,
+/// so the lines are placed again with the labels of the last pass until none of them moves.
+/// Sections are placed one after the other, so they're also placed again until their sizes stay the same.
+fn layout(token_lines: Vec<Vec<Token>>, options: &Options, errors: &mut Vec<AssemblyError>) -> Layout {
+    let mut previous = Symbols::default();
+    let mut sizes = HashMap::new();
+    let mut starts = [0; 3];
+
+    let (mut layout, pass_errors) = loop {
+        let mut layout = Layout {
+            statements: Vec::new(),
+            symbols: Symbols::default(),
+            end: starts[0],
+            section: Section::Text,
+            starts,
+            ends: starts,
+            label_sections: HashMap::new(),
+            exports: Vec::new(),
+            imports: Vec::new(),
+            warnings: Vec::new(),
+            diverted: false,
+            previous,
+            sizes,
+            estimated: false,
+            relax: options.relax_branches && !options.relocatable,
+            relocatable: options.relocatable,
+            memory_size: options.memory_size,
+        };
+        let mut pass_errors = Vec::new();
+
+        for (i, tokens) in token_lines.iter().enumerate() {
+            if let Err(err) = layout.place(i, tokens.clone()) {
+                pass_errors.push(locate(err, &tokens[0]));
+            }
+        }
+        layout.ends[layout.section.index()] = layout.end;
+
+        let settled = !layout.estimated || layout.symbols.labels == layout.previous.labels;
+        let next = section_starts(&layout.starts, &layout.ends);
+
+        if settled && next == starts {
+            break (layout, pass_errors);
+        }
+
+        starts = next;
+        previous = std::mem::take(&mut layout.symbols);
+        sizes = layout.sizes;
+    };
+
+    errors.extend(pass_errors);
+
+    for token in &layout.exports {
+        let TokenVariant::Label(name) = &token.variant else { continue };
+
+        if !layout.symbols.labels.contains_key(name) {
+            errors.push(locate(AssemblyError::at(token, AssemblyErrorVariant::NoLabelFound { name: name.to_owned(), suggestion: layout.symbols.closest_label(name) }), token));
+        }
     }
 
-    Ok(instructions)
+    // The image ends with the last section that has anything in it
+    layout.end = Section::ALL.iter()
+        .map(|section| section.index())
+        .filter(|&i| layout.ends[i] > layout.starts[i])
+        .map(|i| layout.ends[i])
+        .max()
+        .unwrap_or(0);
+    layout.statements.sort_by_key(|statement| statement.address);
+
+    let last_instruction = layout.statements.iter().rev().find(|statement| matches!(statement.tokens[0].variant, TokenVariant::Opcode(_) | TokenVariant::Pseudo(_)));
+
+    if let Some(Statement { tokens, .. }) = last_instruction {
+        if !diverts(tokens) {
+            let warning = AssemblyError::spanning(tokens, AssemblyErrorVariant::RunsPastEnd);
+            layout.warnings.push(locate(warning, &tokens[0]));
+        }
+    }
+
+    layout
 }
 
-fn extract_labels(token_lines: Vec<Vec<Token>>) -> (Vec<Vec<Token>>, HashMap<String, usize>) {
-    let mut line_tokens = Vec::new();
-    let mut labels = HashMap::new();
+impl Layout {
+    /// Places the line with index `line` at the end of the program
+    fn place(&mut self, line: usize, tokens: Vec<Token>) -> Result<(), AssemblyError> {
+        if let [label @ Token { variant: TokenVariant::Label(name), .. }, _, ..] = &tokens[..] {
+            return Err(AssemblyError::at(label, AssemblyErrorVariant::MissingColon { name: name.to_owned() }));
+        }
+
+        let tokens = group_operands(tokens)?;
+        let address = self.end;
+        let section = self.section;
+
+        match &tokens[0].variant {
+            TokenVariant::Label(name) => {
+                if self.symbols.labels.insert(name.to_owned(), address).is_some() {
+                    return Err(AssemblyError::at(&tokens[0], AssemblyErrorVariant::LabelRedefined { name: name.to_owned() }));
+                }
+                self.label_sections.insert(name.to_owned(), section);
+                self.statements.push(Statement { address, section, tokens, size: 0 });
+                self.diverted = false;
+            }
+            TokenVariant::Directive(Directive::Equ) => define_constant(&tokens, &mut self.symbols)?,
+            TokenVariant::Directive(Directive::Section) => {
+                let section = section_argument(&tokens)?;
+                self.ends[self.section.index()] = self.end;
+                self.section = section;
+                self.end = self.ends[section.index()];
+                self.diverted = false;
+            }
+            TokenVariant::Directive(directive @ (Directive::Global | Directive::Extern)) => {
+                for operand in &tokens[1..] {
+                    match (&operand.variant, directive) {
+                        (TokenVariant::Label(_), Directive::Global) => self.exports.push(operand.clone()),
+                        (TokenVariant::Label(name), _) => self.imports.push(name.to_owned()),
+                        _ => return Err(AssemblyError::spanning(&tokens, AssemblyErrorVariant::WrongArguments { directive: *directive, expected: "labels" })),
+                    }
+                }
+                self.statements.push(Statement { address, section, tokens, size: 0 });
+            }
+            TokenVariant::Directive(directive) => {
+                if section == Section::Bss && !matches!(directive, Directive::Org | Directive::Align | Directive::Space) {
+                    return Err(AssemblyError::spanning(&tokens, AssemblyErrorVariant::NotInBss));
+                }
+                // `.org` and `.align` count from the start of the section
+                let size = directives::size(&tokens, address - self.starts[section.index()], &self.symbols)?;
+                let end = self.end_after(&tokens, size)?;
+                self.statements.push(Statement { address, section, tokens, size });
+                self.end = end;
+                self.diverted = false;
+            }
+            TokenVariant::Symbol(name) => {
+                // Keeps the addresses of the following lines
+                self.end += 4;
+                return Err(AssemblyError::at(&tokens[0], AssemblyErrorVariant::UnknownOpcode {
+                    name: name.to_owned(),
+                    suggestion: closest(name, Opcode::names().chain(Pseudo::names())),
+                }));
+            }
+            _ => {
+                if section == Section::Bss {
+                    return Err(AssemblyError::spanning(&tokens, AssemblyErrorVariant::NotInBss));
+                }
+                if !address.is_multiple_of(4) {
+                    return Err(AssemblyError::spanning(&tokens, AssemblyErrorVariant::MisalignedInstruction { address }));
+                }
+                if self.diverted {
+                    let warning = AssemblyError::spanning(&tokens, AssemblyErrorVariant::UnreachableInstruction);
+                    self.warnings.push(locate(warning, &tokens[0]));
+                }
+                let size = match relaxation::target(&tokens) {
+                    _ if is_pseudo(&tokens) => self.pseudo_size(line, &tokens)?,
+                    Some(label) if self.relax => self.branch_size(line, address, label),
+                    _ => 4,
+                };
+                let end = self.end_after(&tokens, size)?;
+                self.diverted = diverts(&tokens);
+                self.statements.push(Statement { address, section, tokens, size });
+                self.end = end;
+            }
+        }
 
-    let mut instruction = 0;
+        Ok(())
+    }
 
-    for tokens in token_lines {
-        if let Label(name) = &tokens[0].variant {
-            labels.insert(name.to_owned(), instruction);
-        } else {
-            line_tokens.push(tokens);
-            instruction += 1;
+    /// Address after `size` bytes placed at the end of the program. Everything has to fit into the memory.
+    fn end_after(&self, tokens: &[Token], size: usize) -> Result<usize, AssemblyError> {
+        match self.end.checked_add(size) {
+            Some(end) if end <= self.memory_size => Ok(end),
+            _ => Err(AssemblyError::spanning(tokens, AssemblyErrorVariant::PastEndOfMemory { end: self.end as u128 + size as u128, size: self.memory_size })),
         }
     }
 
-    (line_tokens, labels)
+    /// Bytes a pseudo-instruction takes. Labels that aren't placed yet are taken from the previous pass.
+    fn pseudo_size(&mut self, line: usize, tokens: &[Token]) -> Result<usize, AssemblyError> {
+        let mut estimated = false;
+
+        let lines = pseudo::expand(tokens, self.relocatable, |token| {
+            let expression = Expression::from_token(token).expect("Only values are evaluated");
+
+            match expression.evaluate(&self.symbols, token) {
+                Err(AssemblyError { variant: AssemblyErrorVariant::NoLabelFound { .. }, .. }) => {
+                    estimated = true;
+                    // A label that doesn't exist is reported once the layout is done
+                    Ok(expression.evaluate(&self.previous, token).unwrap_or(0))
+                }
+                result => result,
+            }
+        })?;
+
+        self.estimated |= estimated;
+
+        let size = self.sizes.entry(line).or_default();
+        *size = (*size).max(lines.len() * 4);
+        Ok(*size)
+    }
+
+    /// Bytes a branch to a label at `address` takes, more than one instruction once the label is out of reach
+    fn branch_size(&mut self, line: usize, address: usize, label: &str) -> usize {
+        let target = match self.symbols.labels.get(label) {
+            Some(&target) => Some(target),
+            None => {
+                self.estimated = true;
+                self.previous.labels.get(label).copied()
+            }
+        };
+
+        let size = self.sizes.entry(line).or_insert(4);
+        if let Some(target) = target {
+            *size = relaxation::size(address, target, *size);
+        }
+        *size
+    }
+}
+
+/// Whether execution never continues with the instruction after this one.
+/// Branches are only counted when they go to a label, `b 1` continues with the next instruction.
+fn diverts(tokens: &[Token]) -> bool {
+    match tokens[0].variant {
+        TokenVariant::Opcode(Opcode::Halt | Opcode::Return | Opcode::ReturnFromTrap | Opcode::Jump) => true,
+        TokenVariant::Opcode(Opcode::Branch) => matches!(tokens.get(1).map(|token| &token.variant), Some(TokenVariant::Label(_))),
+        _ => false,
+    }
+}
+
+/// The section named by `.section`
+fn section_argument(tokens: &[Token]) -> Result<Section, AssemblyError> {
+    let name = match tokens {
+        [_, Token { variant: TokenVariant::Expression(Expression::Constant(name)), .. }] => name.parse().ok(),
+        _ => None,
+    };
+
+    name.ok_or_else(|| AssemblyError::spanning(tokens, AssemblyErrorVariant::WrongArguments { directive: Directive::Section, expected: "text, data or bss" }))
+}
+
+/// `.equ NAME value`. The value can only use constants and labels defined before it.
+fn define_constant(tokens: &[Token], symbols: &mut Symbols) -> Result<(), AssemblyError> {
+    let wrong_arguments = || AssemblyError::spanning(tokens, AssemblyErrorVariant::WrongArguments { directive: Directive::Equ, expected: "a name and a value" });
+
+    let [_, name, value] = tokens else {
+        return Err(wrong_arguments());
+    };
+
+    let (TokenVariant::Expression(Expression::Constant(name_text)), Some(expression)) = (&name.variant, Expression::from_token(value)) else {
+        return Err(wrong_arguments());
+    };
+
+    let value = expression.evaluate(symbols, value)?;
+
+    if symbols.constants.insert(name_text.to_owned(), value).is_some() {
+        return Err(AssemblyError::at(name, AssemblyErrorVariant::SymbolRedefined { name: name_text.to_owned() }));
+    }
+
+    Ok(())
 }
 
 fn make_tokens_ambiguous(tokens: &[Token]) -> Vec<AmbiguousToken> {
@@ -62,41 +602,138 @@ fn make_tokens_ambiguous(tokens: &[Token]) -> Vec<AmbiguousToken> {
     ambiguous_tokens
 }
 
-fn collect_into_lines(tokens: Vec<Token>) -> Vec<Vec<Token>> {
-    let mut lines: Vec<Vec<Token>> = vec![];
-    let mut current_line = None;
+/// Splits the tokens of one source file into its lines
+pub fn collect_into_lines(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+    tokens.chunk_by(|a, b| a.position == b.position).flat_map(split_labels).collect()
+}
+
+/// Splits a line so a label followed by `:` becomes a line of its own, so an instruction can follow it on the same line
+pub fn split_labels(line: &[Token]) -> Vec<Vec<Token>> {
+    let mut lines = Vec::new();
+    let mut rest = line;
 
-    for token in tokens {
-        if let Some(line) = current_line {
-            if line == token.line {
-                if let Some(last_line) = lines.last_mut() {
-                    last_line.push(token);
-                }
-            } else {
-                current_line = Some(token.line);
-                lines.push(vec![token]);
+    loop {
+        match rest {
+            [label @ Token { variant: TokenVariant::Label(_), .. }, Token { variant: TokenVariant::Colon, .. }, tail @ ..] => {
+                lines.push(vec![label.clone()]);
+                rest = tail;
+            }
+            // Numeric labels keep their colon, a number alone isn't a label
+            [number @ Token { variant: TokenVariant::Unsigned(_), .. }, colon @ Token { variant: TokenVariant::Colon, .. }, tail @ ..] => {
+                lines.push(vec![number.clone(), colon.clone()]);
+                rest = tail;
+            }
+            [] => break,
+            _ => {
+                lines.push(rest.to_vec());
+                break;
             }
-        } else {
-            current_line = Some(token.line);
-            lines.push(vec![token]);
         }
     }
 
     lines
 }
 
-fn make_raw_tokens_normal(raw_tokens: Vec<RawToken>) -> Result<Vec<Token>, TokenizationError> {
+/// Tokens of one source file. Lines with invalid tokens are left out.
+pub fn tokenize(src: String, file: Option<&Rc<Path>>, errors: &mut Vec<AssemblyError>) -> Vec<Token> {
+    let in_file = |err: TokenizationError| {
+        let mut error = AssemblyError::from(err);
+        error.position.file = file.cloned();
+        error
+    };
+
+    let (raw_tokens, tokenization_errors) = Tokenizer::new(src).tokenize_all();
+    errors.extend(tokenization_errors.into_iter().map(in_file));
+
+    let mut invalid_lines = HashSet::new();
     let mut tokens = Vec::with_capacity(raw_tokens.len());
 
     for raw_token in raw_tokens {
+        let line = raw_token.line;
+
         match Token::try_from(raw_token) {
             Ok(token) => tokens.push(token),
             Err(err) => {
-                println!("{:#?}", err);
-                return Err(err);
+                invalid_lines.insert(line);
+                errors.push(in_file(err));
             }
         }
     }
 
-    Ok(tokens)
+    tokens.retain(|token| !invalid_lines.contains(&token.position.line));
+
+    for token in &mut tokens {
+        token.position.file = file.cloned();
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics(source: &str) -> Vec<String> {
+        match assemble_source(source.to_string(), None, &Options::default()) {
+            Ok(assembly) => assembly.warnings,
+            Err(diagnostics) => diagnostics,
+        }.iter().map(|diagnostic| format!("{}: {}", diagnostic.severity, diagnostic.error)).collect()
+    }
+
+    #[test]
+    fn test_all_errors() {
+        assert_eq!(diagnostics("add r1 r2 'ab'\nfoo r1\nadd r1 r1 0x10000\n.macro m\nb .nowhere\n.endm\nm\nmov r1 5 5\nhlt"), [
+            "error: Line 1: Invalid token 'ab'",
+            "error: Line 2: Unrecognized opcode foo",
+            "error: Line 3: Immediate doesn't fit into 16 bits. Range is 0 to 65535 but got 65536",
+            "error: Line 7: In expansion of macro m\nLine 5: No label named .nowhere found",
+            "error: Line 8: mov expects reg, reg; got reg, imm, imm",
+            "warning: Line 8: Instruction can't be reached, the one before it never continues",
+        ]);
+    }
+
+    #[test]
+    fn test_label_before_instruction() {
+        let source = ".start: ldi r1 3 0\n.loop: sub r1 r1 1\n    cmp r1 0\n    bne .loop\n1: .a: b 1b";
+        let expected = ".start\nldi r1 3 0\n.loop\nsub r1 r1 1\ncmp r1 0\nbne .loop\n1:\n.a\nb 1b";
+
+        assert_eq!(assemble(source.to_string()).unwrap(), assemble(expected.to_string()).unwrap());
+        assert_eq!(diagnostics(".loop add r1 r1 1\nhlt"), ["error: Line 1: Label .loop needs a ':' to be followed by anything on its line"]);
+    }
+
+    #[test]
+    fn test_sections() {
+        let source = ".section data\n.value: .word 7\n.section text\n    la r1 .value\n    hlt\n.section bss\n.buffer: .space 4\n.section data\n.more: .byte 1";
+        let assembly = assemble_source(source.to_string(), None, &Options::default()).ok().unwrap();
+        let label = |name: &str| assembly.symbols.labels[name];
+
+        // Data follows the text and bss the data, each aligned to 16
+        assert_eq!((label(".value"), label(".more"), label(".buffer")), (0x10, 0x14, 0x20));
+        assert_eq!(assembly.linkage.sections, [0..0xc, 0x10..0x15, 0x20..0x24]);
+        assert_eq!(assembly.image.len(), 0x24);
+        assert_eq!(assembly.image[..0xc], assemble_image("li r1 0x10\nhlt".to_string()).unwrap());
+        assert_eq!(assembly.image[0x10..0x15], [0, 0, 0, 7, 1]);
+
+        assert_eq!(diagnostics(".section bss\n.word 1\nnop\n.section text\nhlt"), [
+            "error: Line 2: The bss section only reserves space, use .space for it",
+            "error: Line 3: The bss section only reserves space, use .space for it",
+        ]);
+        assert_eq!(diagnostics(".section rodata\nhlt"), ["error: Line 1: .section expects text, data or bss"]);
+        assert_eq!(diagnostics(".global .main\nhlt"), ["error: Line 1: No label named .main found"]);
+    }
+
+    #[test]
+    fn test_warnings() {
+        assert_eq!(diagnostics("b .end\nnop\n.end\nb 1\nnop\nret\n.word 5"), [
+            "warning: Line 2: Instruction can't be reached, the one before it never continues",
+        ]);
+        assert_eq!(diagnostics("nop\nhlt\nadd r1 r1 r1"), [
+            "warning: Line 3: Instruction can't be reached, the one before it never continues",
+            "warning: Line 3: Execution continues past the last instruction",
+        ]);
+        assert_eq!(diagnostics("foo\nnop"), [
+            "error: Line 1: Unrecognized opcode foo",
+            "warning: Line 2: Execution continues past the last instruction",
+        ]);
+    }
 }
@@ -1,50 +1,157 @@
+use crate::assembler::position::Position;
+use crate::assembler::relaxation::TEMPORARY;
+use crate::assembler::tokenization::token::Token;
 use crate::assembler::tokenization::tokenization_error::TokenizationError;
+use crate::assembler::types::directive::Directive;
+use crate::assembler::types::opcode::Opcode;
 use std::fmt::Display;
+use std::ops::Range;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub struct AssemblyError {
-    pub line: usize,
-    pub column: Option<usize>,
+    pub position: Position,
+    /// Columns of the line the error is about, if it's about a part of the line
+    pub span: Option<Range<usize>>,
     pub variant: AssemblyErrorVariant
 }
 
+impl AssemblyError {
+    /// An error about a single token
+    pub fn at(token: &Token, variant: AssemblyErrorVariant) -> Self {
+        Self { position: token.position.clone(), span: Some(token.range.clone()), variant }
+    }
+
+    /// An error about the tokens of a line
+    pub fn spanning(tokens: &[Token], variant: AssemblyErrorVariant) -> Self {
+        let span = tokens[0].range.start..tokens[tokens.len() - 1].range.end;
+        Self { position: tokens[0].position.clone(), span: Some(span), variant }
+    }
+}
+
 #[derive(Debug)]
 pub enum AssemblyErrorVariant {
-    ImmediateTooLarge { max: u16, got: u16 },
-    NoLabelFound { name: String },
+    ImmediateTooLarge { bits: usize, min: i64, max: i64, got: i128 },
+    NoLabelFound { name: String, suggestion: Option<String> },
     OffsetTooLarge { limit: i32, required: i64 },
     UnknownTokenPattern,
-    UnrecognizableParam { param_index: usize },
+    /// The operands of an instruction don't match any of its forms
+    OperandMismatch { opcode: Opcode, expected: Vec<String>, got: String },
+    /// The operands of a pseudo-instruction don't match any of its forms
+    PseudoOperands { name: String, expected: &'static str, got: String },
+    WrongArguments { directive: Directive, expected: &'static str },
+    OrgBackwards { target: usize, address: usize },
+    /// A program reaching `end`, past the end of a memory of `size` bytes
+    PastEndOfMemory { end: u128, size: usize },
+    InvalidAlignment { alignment: usize },
+    MisalignedInstruction { address: usize },
+    MisalignedLabel { name: String, address: usize },
+    UnknownOpcode { name: String, suggestion: Option<String> },
+    UndefinedSymbol { name: String, suggestion: Option<String> },
+    SymbolRedefined { name: String },
+    LabelRedefined { name: String },
+    /// A label followed by an instruction without a `:` in between
+    MissingColon { name: String },
+    /// A local label with no label before it to belong to
+    NoEnclosingLabel { name: String },
+    ExpressionOverflow,
+    DivisionByZero,
+    InvalidExpression { reason: &'static str },
+    MacroRedefined { name: String },
+    UnterminatedMacro { name: String },
+    UnexpectedEndMacro,
+    NestedMacroDefinition,
+    MacroArguments { name: String, expected: usize, got: usize },
+    MacroRecursion { name: String, limit: usize },
+    /// An error on a line produced by a macro. The error's own line is the invocation.
+    InMacro { name: String, error: Box<AssemblyError> },
+    UnreadableFile { path: PathBuf, reason: String },
+    IncludeCycle { path: PathBuf },
+    /// Warning for an instruction after one that never continues, without a label in between
+    UnreachableInstruction,
+    /// Warning for a program whose last instruction isn't a halt, branch or return
+    RunsPastEnd,
+    /// Note for a branch replaced by a jump through a register
+    BranchRelaxed { label: String, instructions: usize },
+    /// A relocatable program using an address in a way the linker can't fill in
+    NotRelocatable { name: String },
+    /// Anything but reserved space in the bss section
+    NotInBss,
     TokenizationError(TokenizationError)
 }
 
-impl Display for AssemblyError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let str = match &self.variant {
-            AssemblyErrorVariant::ImmediateTooLarge { max, got } => format!("Immediate is too large. Maximum is {} but got {}", max, got),
-            AssemblyErrorVariant::NoLabelFound { name } => format!("No label named {} found", name),
+impl AssemblyError {
+    /// What went wrong, without where
+    pub fn message(&self) -> String {
+        match &self.variant {
+            AssemblyErrorVariant::ImmediateTooLarge { bits, min, max, got } => format!("Immediate doesn't fit into {} bits. Range is {} to {} but got {}", bits, min, max, got),
+            AssemblyErrorVariant::NoLabelFound { name, suggestion } => format!("No label named {} found{}", name, did_you_mean(suggestion)),
             AssemblyErrorVariant::OffsetTooLarge { limit, required } => format!("Offset is too large. Required {} but limit is {}", required, limit),
             AssemblyErrorVariant::UnknownTokenPattern => "Unknown token pattern".to_string(),
-            AssemblyErrorVariant::UnrecognizableParam { param_index: near_idx } => format!("Parameter {} can't be tokenized", *near_idx + 1),
+            AssemblyErrorVariant::OperandMismatch { opcode, expected, got } => format!("{} expects {}; got {}", opcode, expected.join(" or "), got),
+            AssemblyErrorVariant::PseudoOperands { name, expected, got } => format!("{} expects {}; got {}", name, expected, got),
+            AssemblyErrorVariant::WrongArguments { directive, expected } => format!("{} expects {}", directive, expected),
+            AssemblyErrorVariant::OrgBackwards { target, address } => format!("Can't move back to address {:#x}, already at {:#x}", target, address),
+            AssemblyErrorVariant::PastEndOfMemory { end, size } => format!("Program would reach {:#x}, past the end of memory at {:#x}", end, size),
+            AssemblyErrorVariant::InvalidAlignment { alignment } => format!("Alignment {} isn't a power of two", alignment),
+            AssemblyErrorVariant::MisalignedInstruction { address } => format!("Instruction at {:#x} isn't aligned to 4 bytes", address),
+            AssemblyErrorVariant::MisalignedLabel { name, address } => format!("Label {} at {:#x} isn't aligned to an instruction", name, address),
+            AssemblyErrorVariant::UnknownOpcode { name, suggestion } => format!("Unrecognized opcode {}{}", name, did_you_mean(suggestion)),
+            AssemblyErrorVariant::UndefinedSymbol { name, suggestion } => format!("No constant named {} defined{}", name, did_you_mean(suggestion)),
+            AssemblyErrorVariant::SymbolRedefined { name } => format!("Constant {} is already defined", name),
+            AssemblyErrorVariant::LabelRedefined { name } => format!("Label {} is already defined", name),
+            AssemblyErrorVariant::MissingColon { name } => format!("Label {} needs a ':' to be followed by anything on its line", name),
+            AssemblyErrorVariant::NoEnclosingLabel { name } => format!("Local label {} doesn't belong to any label, there is none before it", name),
+            AssemblyErrorVariant::ExpressionOverflow => "Expression doesn't fit into 64 bits".to_string(),
+            AssemblyErrorVariant::DivisionByZero => "Division by zero".to_string(),
+            AssemblyErrorVariant::InvalidExpression { reason } => format!("Invalid expression: {}", reason),
+            AssemblyErrorVariant::MacroRedefined { name } => format!("Macro {} is already defined", name),
+            AssemblyErrorVariant::UnterminatedMacro { name } => format!("Macro {} is never closed with .endm", name),
+            AssemblyErrorVariant::UnexpectedEndMacro => ".endm without .macro".to_string(),
+            AssemblyErrorVariant::NestedMacroDefinition => "Macros can't be defined inside macros".to_string(),
+            AssemblyErrorVariant::MacroArguments { name, expected, got } => format!("Macro {} takes {} arguments but got {}", name, expected, got),
+            AssemblyErrorVariant::MacroRecursion { name, limit } => format!("Macro {} nests deeper than {} invocations", name, limit),
+            AssemblyErrorVariant::InMacro { name, error } => format!("In expansion of macro {}\n{}", name, error),
+            AssemblyErrorVariant::UnreadableFile { path, reason } => format!("Can't read {}: {}", path.display(), reason),
+            AssemblyErrorVariant::IncludeCycle { path } => format!("{} includes itself", path.display()),
+            AssemblyErrorVariant::UnreachableInstruction => "Instruction can't be reached, the one before it never continues".to_string(),
+            AssemblyErrorVariant::RunsPastEnd => "Execution continues past the last instruction".to_string(),
+            AssemblyErrorVariant::BranchRelaxed { label, instructions } => format!("Branch to {} is out of reach, replaced by {} instructions jumping through r{}", label, instructions, TEMPORARY as usize),
+            AssemblyErrorVariant::NotRelocatable { name } => format!("The address of {} is only known once the program is linked, only branches, li, la and .word can use it", name),
+            AssemblyErrorVariant::NotInBss => "The bss section only reserves space, use .space for it".to_string(),
             AssemblyErrorVariant::TokenizationError(err) => err.to_string(),
-        };
+        }
+    }
 
-        let detailed = if let Some(position) = self.column {
-            format!("Line {} position {}: {}", self.line + 1, position + 1, str)
-        } else {
-            format!("Line {}: {}", self.line + 1, str)
-        };
+    /// The line and, if the error is about a part of it, the position on it
+    pub fn location(&self) -> String {
+        match &self.span {
+            Some(span) => format!("{} position {}", self.position, span.start + 1),
+            None => self.position.to_string(),
+        }
+    }
+}
 
-        write!(f, "{}", detailed)
+/// The short form naming only the line. `Diagnostic` shows the position on the line as well.
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.position, self.message())
     }
 }
 
 impl From<TokenizationError> for AssemblyError {
     fn from(error: TokenizationError) -> Self {
         Self {
-            line: error.line,
-            column: Some(error.column),
+            position: Position::new(None, error.line),
+            span: Some(error.column..error.column + 1),
             variant: AssemblyErrorVariant::TokenizationError(error),
         }
     }
 }
+
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(suggestion) => format!(", did you mean {}?", suggestion),
+        None => String::new(),
+    }
+}
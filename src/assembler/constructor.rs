@@ -2,202 +2,46 @@
 
 use either::{Either, Left, Right};
 use arbitrary_int::{u2, u3, u6};
-use super::types::register::Register;
+use crate::isa::{FORMS, Operand};
+use super::types::{opcode::Opcode as Opc, register::Register};
 
-// ---------------------------------------------------------------------------------------------
-
-pub fn pack_nibbles(nibbles: [u32; 8]) -> u32 {
-    let mut out = 0;
-    for (i, &nib) in nibbles.iter().enumerate() {
-        // nib 0 goes to bits [31..28],
-        // nib 1 goes to bits [27..24], etc.
-        out |= nib << (4 * (7 - i));
-    }
-    out
-}
-
-/// Split a u16 into four nibbles (high nibble first).
-pub fn split_u16_into_nibbles(v: u16) -> (u32, u32, u32, u32) {
-    let v = v as u32;
-    (
-        (v >> 12) & 0xF,
-        (v >>  8) & 0xF,
-        (v >>  4) & 0xF,
-        v        & 0xF,
-    )
-}
-
-/// Split a U6 into two nibbles.
-pub fn split_u6_into_nibbles(v: u6) -> (u32, u32) {
-    let val: u32 = v.into();
-    (
-        (val >> 4) & 0xF,
-        val       & 0xF,
-    )
-}
-
-// ---------------------------------------------------------------------------------------------
-
-#[repr(u32)]
-enum InstrType {
-    Arithmetic = 0x1,
-    Bitwise = 0x2,
-    ShiftRotate = 0x3,
-    DataMemoryStack = 0x4,
-    Comparison = 0x5,
-    Branching = 0x6,
-    Conversion = 0x7,
-    FloatingArithmetic = 0x8,
-    DoubleArithmetic = 0x9,
-}
-
-impl From<InstrType> for u32 {
-    fn from(t: InstrType) -> Self {
-        t as u32
-    }
+/// A value for one operand of an instruction form
+#[derive(Debug, Copy, Clone)]
+enum Value {
+    Register(Register),
+    Immediate(u32),
 }
 
-impl From<Register> for u32 {
-    fn from(r: Register) -> Self {
-        r as u32
+impl Value {
+    fn fits(&self, operand: &Operand) -> bool {
+        matches!(
+            (self, operand),
+            (Value::Register(_), Operand::Register(_)) | (Value::Immediate(_), Operand::Unsigned(_) | Operand::Signed(_))
+        )
     }
-}
-
-// ---------------------------------------------------------------------------------------------
 
-fn assemble_arithmetic(
-    instr_type: InstrType,
-    dest: Register,
-    a: Either<Register, u16>,
-    b: Either<Register, u16>,
-    subcode_rr: u32, // subcode if (reg, reg)
-    subcode_ri: u32, // subcode if (reg, imm)
-    subcode_ir: u32, // subcode if (imm, reg)
-) -> u32 {
-    match (a, b) {
-        (Left(r1), Left(r2)) => pack_nibbles([
-            instr_type.into(),  // nibble 0
-            dest.into(),        // nibble 1
-            r1.into(),          // nibble 2
-            r2.into(),          // nibble 3
-            0, 0, 0,
-            subcode_rr
-        ]),
-        (Left(r), Right(imm)) => {
-            let (n0, n1, n2, n3) = split_u16_into_nibbles(imm);
-            pack_nibbles([
-                instr_type.into(),
-                dest.into(),
-                r.into(),
-                n0, n1, n2, n3,
-                subcode_ri
-            ])
+    fn bits(&self) -> u32 {
+        match *self {
+            Value::Register(reg) => reg as u32,
+            Value::Immediate(imm) => imm,
         }
-        (Right(imm), Left(r)) => {
-            let (n0, n1, n2, n3) = split_u16_into_nibbles(imm);
-            pack_nibbles([
-                instr_type.into(),
-                dest.into(),
-                r.into(),
-                n0, n1, n2, n3,
-                subcode_ir
-            ])
-        }
-        // For these instructions, (imm, imm) is disallowed
-        (Right(_), Right(_)) => panic!("Invalid combination: immediate, immediate."),
     }
 }
 
-fn assemble_bitwise(
-    subcode: u32,
-    dest: Register,
-    a: Register,
-    b: Register
-) -> u32 {
-    pack_nibbles([
-        InstrType::Bitwise.into(),
-        dest.into(),
-        a.into(),
-        b.into(),
-        0, 0, 0,
-        subcode
-    ])
-}
-
-fn assemble_shift_or_rotate(
-    instr_type: InstrType,
-    dest: Register,
-    src: Register,
-    amount: Either<Register, u6>,
-    subcode_reg: u32,
-    subcode_imm: u32
-) -> u32 {
-    match amount {
-        Left(reg) => pack_nibbles([
-            instr_type.into(),
-            dest.into(),
-            src.into(),
-            reg.into(),
-            0, 0, 0,
-            subcode_reg
-        ]),
-        Right(u6) => {
-            let (n0, n1) = split_u6_into_nibbles(u6);
-            pack_nibbles([
-                instr_type.into(),
-                dest.into(),
-                src.into(),
-                0, 0, n0, n1,
-                subcode_imm
-            ])
-        }
+fn source(operand: Either<Register, u16>) -> Value {
+    match operand {
+        Left(reg) => Value::Register(reg),
+        Right(imm) => Value::Immediate(imm as u32),
     }
 }
 
-fn assemble_branch(
-    instr_type: InstrType,
-    offset: Either<Register, i16>,
-    subcode_reg: u32,
-    subcode_imm: u32
-) -> u32 {
-    match offset {
-        Left(reg) => pack_nibbles([
-            instr_type.into(),
-            reg.into(),
-            0, 0, 0, 0, 0,
-            subcode_reg
-        ]),
-        Right(imm) => {
-            let (n0, n1, n2, n3) = split_u16_into_nibbles(imm as u16);
-            pack_nibbles([
-                instr_type.into(),
-                n0, n1, n2, n3,
-                0, 0,
-                subcode_imm
-            ])
-        }
+fn offset(operand: Either<Register, i16>) -> Value {
+    match operand {
+        Left(reg) => Value::Register(reg),
+        Right(imm) => Value::Immediate(imm as u16 as u32),
     }
 }
 
-fn assemble_floating(
-    instr_type: InstrType,  // either FloatingArithmetic or DoubleArithmetic
-    dest: Register,
-    a: Register,
-    b: Option<Register>,
-    subcode_major: u32,
-    subcode_minor: u32
-) -> u32 {
-    pack_nibbles([
-        instr_type.into(),
-        dest.into(),
-        a.into(),
-        b.map_or(0, |r| r.into()),
-        0, 0,
-        subcode_major,
-        subcode_minor
-    ])
-}
-
 // ---------------------------------------------------------------------------------------------
 
 #[derive(Debug, Copy, Clone)]
@@ -236,14 +80,20 @@ pub enum Instruction {
     BranchGreaterEqual { offset: Either<Register, i16> },
     BranchNotEqual { offset: Either<Register, i16> },
     BranchSmallerEqual { offset: Either<Register, i16> },
+    Call { offset: Either<Register, i16> },
+    Return,
+    Breakpoint,
+    SetTrapVector { table: Register },
+    ReturnFromTrap,
+    Halt,
     ImmediateToFloat { dest: Register, imm: i16 },
     ImmediateToDouble { dest: Register, imm: i16 },
-    IntegerToFloat { dest: Register, src: Register },
-    IntegerToDouble { dest: Register, src: Register },
-    FloatToInteger { dest: Register, src: Register },
-    FloatToDouble { dest: Register, src: Register },
-    DoubleToInteger { dest: Register, src: Register },
-    DoubleToFloat { dest: Register, src: Register },
+    IntegerToFloat { reg: Register },
+    IntegerToDouble { reg: Register },
+    FloatToInteger { reg: Register },
+    FloatToDouble { reg: Register },
+    DoubleToInteger { reg: Register },
+    DoubleToFloat { reg: Register },
     FloatAdd { dest: Register, a: Register, b: Register },
     FloatSubtract { dest: Register, a: Register, b: Register },
     FloatMultiply { dest: Register, a: Register, b: Register },
@@ -311,177 +161,215 @@ pub enum Instruction {
 }
 
 impl Instruction {
+    /// Encodes the instruction with the matching form from the ISA table
     pub fn assemble(self) -> u32 {
+        let (opcode, values) = self.operands();
+
+        let form = FORMS.iter()
+            .filter(|form| form.opcode == opcode && form.operands.len() == values.len())
+            .find(|form| form.operands.iter().zip(&values).all(|(operand, value)| value.fits(operand)))
+            .unwrap_or_else(|| panic!("Invalid operands for {:?}", self));
+
+        form.encode(&values.iter().map(Value::bits).collect::<Vec<_>>())
+    }
+
+    /// The mnemonic and the operand values in source order
+    fn operands(self) -> (Opc, Vec<Value>) {
         use Instruction::*;
 
+        // Jumps and branches share their conditions, jumps are the register forms
+        let branch = |jump: Opc, branch: Opc, target: Either<Register, i16>| {
+            (if target.is_left() { jump } else { branch }, vec![offset(target)])
+        };
+
         match self {
-            Nop => 0,
+            Nop => (Opc::Nop, vec![]),
 
             // ----------------- Arithmetic -----------------
-            Add          { dest, a, b } => assemble_arithmetic(InstrType::Arithmetic, dest, a, b, 0x0, 0x1,0x2),
-            Subtract     { dest, a, b } => assemble_arithmetic(InstrType::Arithmetic, dest, a, b, 0x3, 0x4, 0x5),
-            Multiply     { dest, a, b } => assemble_arithmetic(InstrType::Arithmetic, dest, a, b, 0x6, 0x7, 0x8),
-            Divide       { dest, a, b } => assemble_arithmetic(InstrType::Arithmetic, dest, a, b, 0x9, 0xA, 0xB),
-            DivideSigned { dest, a, b } => assemble_arithmetic(InstrType::Arithmetic, dest, a, b, 0xC, 0xD, 0xE),
+            Add          { dest, a, b } => (Opc::Add, vec![Value::Register(dest), source(a), source(b)]),
+            Subtract     { dest, a, b } => (Opc::Subtract, vec![Value::Register(dest), source(a), source(b)]),
+            Multiply     { dest, a, b } => (Opc::Multiply, vec![Value::Register(dest), source(a), source(b)]),
+            Divide       { dest, a, b } => (Opc::Divide, vec![Value::Register(dest), source(a), source(b)]),
+            DivideSigned { dest, a, b } => (Opc::DivideSigned, vec![Value::Register(dest), source(a), source(b)]),
 
             // ----------------- Bitwise -----------------
-            And  { dest, a, b } => assemble_bitwise(0x0, dest, a, b),
-            Or   { dest, a, b } => assemble_bitwise(0x1, dest, a, b),
-            Xor  { dest, a, b } => assemble_bitwise(0x2, dest, a, b),
-            Nand { dest, a, b } => assemble_bitwise(0x3, dest, a, b),
-            Nor  { dest, a, b } => assemble_bitwise(0x4, dest, a, b),
-            Xnor { dest, a, b } => assemble_bitwise(0x5, dest, a, b),
-            Not  { dest, src } => pack_nibbles([InstrType::Bitwise.into(), dest.into(), src.into(), 0, 0, 0, 0, 0x6]),
-            
+            And  { dest, a, b } => (Opc::And, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            Or   { dest, a, b } => (Opc::Or, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            Xor  { dest, a, b } => (Opc::Xor, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            Nand { dest, a, b } => (Opc::Nand, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            Nor  { dest, a, b } => (Opc::Nor, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            Xnor { dest, a, b } => (Opc::Xnor, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            Not  { dest, src } => (Opc::Not, vec![Value::Register(dest), Value::Register(src)]),
+
             // ----------------- Shifts & Rotates -----------------
-            RightShift { dest, src, amount } => assemble_shift_or_rotate(InstrType::ShiftRotate, dest, src, amount, 0x0, 0x1),
-            LeftShift  { dest, src, amount } => assemble_shift_or_rotate(InstrType::ShiftRotate, dest, src, amount, 0x2, 0x3),
-            RightRoll  { dest, src, amount } => assemble_shift_or_rotate(InstrType::ShiftRotate, dest, src, amount, 0x4, 0x5),
-            LeftRoll   { dest, src, amount } => assemble_shift_or_rotate(InstrType::ShiftRotate, dest, src, amount, 0x6, 0x7),
+            RightShift { dest, src, amount } => (Opc::RightShift, vec![Value::Register(dest), Value::Register(src), amount_value(amount)]),
+            LeftShift  { dest, src, amount } => (Opc::LeftShift, vec![Value::Register(dest), Value::Register(src), amount_value(amount)]),
+            RightRoll  { dest, src, amount } => (Opc::RightRoll, vec![Value::Register(dest), Value::Register(src), amount_value(amount)]),
+            LeftRoll   { dest, src, amount } => (Opc::LeftRoll, vec![Value::Register(dest), Value::Register(src), amount_value(amount)]),
 
             // ----------------- Data Movement / Stack -----------------
-            Move { dest, src } => pack_nibbles([InstrType::DataMemoryStack.into(), dest.into(), src.into(), 0, 0, 0, 0, 0x0]),
-            LoadImmediate { dest, slice, imm } => {
-                let (n0, n1, n2, n3) = split_u16_into_nibbles(imm);
-                pack_nibbles([InstrType::DataMemoryStack.into(), dest.into(), n0, n1, n2, n3, slice.value() as u32, 0x1])
-            }
-            LoadRegister { dest, mem_ptr, slice } => match mem_ptr {
-                Left(reg) => pack_nibbles([InstrType::DataMemoryStack.into(), dest.into(), reg.into(), 0, 0, 0, slice.value() as u32, 0x2]),
-                Right(imm) => {
-                    let (n0, n1, n2, n3) = split_u16_into_nibbles(imm);
-                    pack_nibbles([InstrType::DataMemoryStack.into(), dest.into(), n0, n1, n2, n3, slice.value() as u32, 0x3])
-                }
-            },
-            StoreRegister { src, mem_ptr, slice } => match mem_ptr {
-                Left(reg) => pack_nibbles([InstrType::DataMemoryStack.into(), src.into(), reg.into(), 0, 0, 0, slice.value() as u32, 0x4]),
-                Right(imm) => {
-                    let (n0, n1, n2, n3) = split_u16_into_nibbles(imm);
-                    pack_nibbles([InstrType::DataMemoryStack.into(), src.into(), n0, n1, n2, n3, slice.value() as u32, 0x5])
-                }
-            },
-            Push { reg } => pack_nibbles([InstrType::DataMemoryStack.into(), reg.into(), 0, 0, 0, 0, 0, 0x6]),
-            Pop { reg } => pack_nibbles([InstrType::DataMemoryStack.into(), reg.into(), 0, 0, 0, 0, 0, 0x7]),
+            Move { dest, src } => (Opc::Move, vec![Value::Register(dest), Value::Register(src)]),
+            LoadImmediate { dest, slice, imm } => (Opc::LoadImmediate, vec![Value::Register(dest), Value::Immediate(imm as u32), Value::Immediate(slice.value() as u32)]),
+            LoadRegister { dest, mem_ptr, slice } => (Opc::LoadRegister, vec![Value::Register(dest), source(mem_ptr), Value::Immediate(slice.value() as u32)]),
+            StoreRegister { src, mem_ptr, slice } => (Opc::StoreRegister, vec![Value::Register(src), source(mem_ptr), Value::Immediate(slice.value() as u32)]),
+            Push { reg } => (Opc::Push, vec![Value::Register(reg)]),
+            Pop { reg } => (Opc::Pop, vec![Value::Register(reg)]),
 
             // ----------------- Comparison -----------------
-            Compare { a, b, signed } => {
-                use either::Either::{Left, Right};
-                match (signed, a, b) {
-                    (false, Left(r1), Left(r2)) => pack_nibbles([InstrType::Comparison.into(), r1.into(), r2.into(), 0, 0, 0, 0, 0x0]),
-                    (false, Left(r), Right(imm)) => {
-                        let (n0, n1, n2, n3) = split_u16_into_nibbles(imm);
-                        pack_nibbles([InstrType::Comparison.into(), r.into(), n0, n1, n2, n3, 0, 0x1])
-                    }
-                    (false, Right(imm), Left(r)) => {
-                        let (n0, n1, n2, n3) = split_u16_into_nibbles(imm);
-                        pack_nibbles([InstrType::Comparison.into(), r.into(), n0, n1, n2, n3, 0, 0x2])
-                    }
-                    (true, Left(r1), Left(r2)) => pack_nibbles([InstrType::Comparison.into(), r1.into(), r2.into(), 0, 0, 0, 0, 0x3]),
-                    (true, Left(r), Right(imm)) => {
-                        let (n0, n1, n2, n3) = split_u16_into_nibbles(imm);
-                        pack_nibbles([InstrType::Comparison.into(), r.into(), n0, n1, n2, n3, 0, 0x4])
-                    }
-                    (true, Right(imm), Left(r)) => {
-                        let (n0, n1, n2, n3) = split_u16_into_nibbles(imm);
-                        pack_nibbles([InstrType::Comparison.into(), r.into(), n0, n1, n2, n3, 0, 0x5])
-                    }
-                    (_, Right(_), Right(_)) => panic!("Invalid combination"),
-                }
-            }
-            CompareFloat { a, b } => pack_nibbles([InstrType::Comparison.into(), a.into(), b.into(), 0, 0, 0, 0, 0x6]),
-            CompareDouble { a, b } => pack_nibbles([InstrType::Comparison.into(), a.into(), b.into(), 0, 0, 0, 0, 0x7]),
+            Compare { a, b, signed } => (if signed { Opc::CompareSigned } else { Opc::Compare }, vec![source(a), source(b)]),
+            CompareFloat { a, b } => (Opc::CompareFloat, vec![Value::Register(a), Value::Register(b)]),
+            CompareDouble { a, b } => (Opc::CompareDouble, vec![Value::Register(a), Value::Register(b)]),
 
             // ----------------- Branching -----------------
-            Branch             { offset } => assemble_branch(InstrType::Branching, offset, 0x0, 0x1),
-            BranchGreater      { offset } => assemble_branch(InstrType::Branching, offset, 0x2, 0x3),
-            BranchEqual        { offset } => assemble_branch(InstrType::Branching, offset, 0x4, 0x5),
-            BranchSmaller      { offset } => assemble_branch(InstrType::Branching, offset, 0x6, 0x7),
-            BranchGreaterEqual { offset } => assemble_branch(InstrType::Branching, offset, 0x8, 0x9),
-            BranchNotEqual     { offset } => assemble_branch(InstrType::Branching, offset, 0xA, 0xB),
-            BranchSmallerEqual { offset } => assemble_branch(InstrType::Branching, offset, 0xC, 0xD),
+            Branch             { offset } => branch(Opc::Jump, Opc::Branch, offset),
+            BranchGreater      { offset } => branch(Opc::JumpGreater, Opc::BranchGreater, offset),
+            BranchEqual        { offset } => branch(Opc::JumpEqual, Opc::BranchEqual, offset),
+            BranchSmaller      { offset } => branch(Opc::JumpSmaller, Opc::BranchSmaller, offset),
+            BranchGreaterEqual { offset } => branch(Opc::JumpGreaterEqual, Opc::BranchGreaterEqual, offset),
+            BranchNotEqual     { offset } => branch(Opc::JumpNotEqual, Opc::BranchNotEqual, offset),
+            BranchSmallerEqual { offset } => branch(Opc::JumpSmallerEqual, Opc::BranchSmallerEqual, offset),
+
+            // ----------------- Subroutines -----------------
+            Call { offset: target } => (Opc::Call, vec![offset(target)]),
+            Return => (Opc::Return, vec![]),
+
+            // ----------------- System -----------------
+            Breakpoint => (Opc::Breakpoint, vec![]),
+            SetTrapVector { table } => (Opc::SetTrapVector, vec![Value::Register(table)]),
+            ReturnFromTrap => (Opc::ReturnFromTrap, vec![]),
+            Halt => (Opc::Halt, vec![]),
 
             // ----------------- Conversions -----------------
-            ImmediateToFloat { dest, imm } => {
-                let (n0, n1, n2, n3) = split_u16_into_nibbles(imm as u16);
-                pack_nibbles([InstrType::Conversion.into(), dest.into(), n0, n1, n2, n3, 0, 0x0])
-            }
-            ImmediateToDouble { dest, imm } => {
-                let (n0, n1, n2, n3) = split_u16_into_nibbles(imm as u16);
-                pack_nibbles([InstrType::Conversion.into(), dest.into(), n0, n1, n2, n3, 0, 0x1])
-            }
-            IntegerToFloat  { dest, src } => pack_nibbles([InstrType::Conversion.into(), dest.into(), src.into(), 0, 0, 0, 0, 0x2]),
-            IntegerToDouble { dest, src } => pack_nibbles([InstrType::Conversion.into(), dest.into(), src.into(), 0, 0, 0, 0, 0x3]),
-            FloatToInteger  { dest, src } => pack_nibbles([InstrType::Conversion.into(), dest.into(), src.into(), 0, 0, 0, 0, 0x4]),
-            FloatToDouble   { dest, src } => pack_nibbles([InstrType::Conversion.into(), dest.into(), src.into(), 0, 0, 0, 0, 0x5]),
-            DoubleToInteger { dest, src } => pack_nibbles([InstrType::Conversion.into(), dest.into(), src.into(), 0, 0, 0, 0, 0x6]),
-            DoubleToFloat   { dest, src } => pack_nibbles([InstrType::Conversion.into(), dest.into(), src.into(), 0, 0, 0, 0, 0x7]),
+            ImmediateToFloat { dest, imm } => (Opc::ImmediateToFloat, vec![Value::Register(dest), Value::Immediate(imm as u16 as u32)]),
+            ImmediateToDouble { dest, imm } => (Opc::ImmediateToDouble, vec![Value::Register(dest), Value::Immediate(imm as u16 as u32)]),
+            IntegerToFloat  { reg } => (Opc::IntegerToFloat, vec![Value::Register(reg)]),
+            IntegerToDouble { reg } => (Opc::IntegerToDouble, vec![Value::Register(reg)]),
+            FloatToInteger  { reg } => (Opc::FloatToInteger, vec![Value::Register(reg)]),
+            FloatToDouble   { reg } => (Opc::FloatToDouble, vec![Value::Register(reg)]),
+            DoubleToInteger { reg } => (Opc::DoubleToInteger, vec![Value::Register(reg)]),
+            DoubleToFloat   { reg } => (Opc::DoubleToFloat, vec![Value::Register(reg)]),
 
-            // ----------------- Floating arithmetic -----------------
-            FloatAdd { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x0, 0x0),
-            FloatSubtract { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x0, 0x1),
-            FloatMultiply { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x0, 0x2),
-            FloatDivide { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x0, 0x3),
-            FloatModulo { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x0, 0x4),
-            FloatNegate { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x0, 0x5),
-            FloatReciprocal { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x0, 0x6),
-            FloatPower { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x0, 0x7),
-            FloatExponential { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x0, 0x8),
-            FloatRoot { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x0, 0x9),
-            FloatSquareRoot { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x0, 0xA),
-            FloatCubeRoot { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x0, 0xB),
-            FloatSquare { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x0, 0xC),
-            FloatCube { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x0, 0xD),
-            FloatLogarithm { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x0, 0xE),
-            FloatNaturalLogarithm { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x0, 0xF),
-            FloatAbsolute { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x0),
-            FloatSine { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x1),
-            FloatCosine { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x2),
-            FloatTangent { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x3),
-            FloatArcsine { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x4),
-            FloatArccosine { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x5),
-            FloatArctangent { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x6),
-            FloatFloor { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x7),
-            FloatCeil { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x8),
-            FloatRound { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0x9),
-            FloatMinimum { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x1, 0xA),
-            FloatMaximum { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x1, 0xB),
-            FloatSign { dest, src } => assemble_floating(InstrType::FloatingArithmetic, dest, src, None, 0x1, 0xC),
-            FloatAbsoluteDifference { dest, a, b } => assemble_floating(InstrType::FloatingArithmetic, dest, a, Some(b), 0x1, 0xD),
-            FloatLoadInfinity { dest } => pack_nibbles([InstrType::FloatingArithmetic.into(), dest.into(), 0, 0, 0, 0, 0x1, 0xE]),
-            FloatLoadNaN { dest } => pack_nibbles([InstrType::FloatingArithmetic.into(), dest.into(), 0, 0, 0, 0, 0x1, 0xF]),
+            // ----------------- Floating & double arithmetic -----------------
+            FloatAdd { dest, a, b } => (Opc::FloatAdd, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatSubtract { dest, a, b } => (Opc::FloatSubtract, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatMultiply { dest, a, b } => (Opc::FloatMultiply, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatDivide { dest, a, b } => (Opc::FloatDivide, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatModulo { dest, a, b } => (Opc::FloatModulo, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatNegate { dest, src } => (Opc::FloatNegate, vec![Value::Register(dest), Value::Register(src)]),
+            FloatReciprocal { dest, src } => (Opc::FloatReciprocal, vec![Value::Register(dest), Value::Register(src)]),
+            FloatPower { dest, a, b } => (Opc::FloatPower, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatExponential { dest, src } => (Opc::FloatExponential, vec![Value::Register(dest), Value::Register(src)]),
+            FloatRoot { dest, a, b } => (Opc::FloatRoot, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatSquareRoot { dest, src } => (Opc::FloatSquareRoot, vec![Value::Register(dest), Value::Register(src)]),
+            FloatCubeRoot { dest, src } => (Opc::FloatCubeRoot, vec![Value::Register(dest), Value::Register(src)]),
+            FloatSquare { dest, src } => (Opc::FloatSquare, vec![Value::Register(dest), Value::Register(src)]),
+            FloatCube { dest, src } => (Opc::FloatCube, vec![Value::Register(dest), Value::Register(src)]),
+            FloatLogarithm { dest, a, b } => (Opc::FloatLogarithm, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatNaturalLogarithm { dest, src } => (Opc::FloatNaturalLogarithm, vec![Value::Register(dest), Value::Register(src)]),
+            FloatAbsolute { dest, src } => (Opc::FloatAbsolute, vec![Value::Register(dest), Value::Register(src)]),
+            FloatSine { dest, src } => (Opc::FloatSine, vec![Value::Register(dest), Value::Register(src)]),
+            FloatCosine { dest, src } => (Opc::FloatCosine, vec![Value::Register(dest), Value::Register(src)]),
+            FloatTangent { dest, src } => (Opc::FloatTangent, vec![Value::Register(dest), Value::Register(src)]),
+            FloatArcsine { dest, src } => (Opc::FloatArcsine, vec![Value::Register(dest), Value::Register(src)]),
+            FloatArccosine { dest, src } => (Opc::FloatArccosine, vec![Value::Register(dest), Value::Register(src)]),
+            FloatArctangent { dest, src } => (Opc::FloatArctangent, vec![Value::Register(dest), Value::Register(src)]),
+            FloatFloor { dest, src } => (Opc::FloatFloor, vec![Value::Register(dest), Value::Register(src)]),
+            FloatCeil { dest, src } => (Opc::FloatCeil, vec![Value::Register(dest), Value::Register(src)]),
+            FloatRound { dest, src } => (Opc::FloatRound, vec![Value::Register(dest), Value::Register(src)]),
+            FloatMinimum { dest, a, b } => (Opc::FloatMinimum, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatMaximum { dest, a, b } => (Opc::FloatMaximum, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatSign { dest, src } => (Opc::FloatSign, vec![Value::Register(dest), Value::Register(src)]),
+            FloatAbsoluteDifference { dest, a, b } => (Opc::FloatAbsoluteDifference, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            FloatLoadInfinity { dest } => (Opc::FloatLoadInfinity, vec![Value::Register(dest)]),
+            FloatLoadNaN { dest } => (Opc::FloatLoadNaN, vec![Value::Register(dest)]),
+            DoubleAdd { dest, a, b } => (Opc::DoubleAdd, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleSubtract { dest, a, b } => (Opc::DoubleSubtract, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleMultiply { dest, a, b } => (Opc::DoubleMultiply, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleDivide { dest, a, b } => (Opc::DoubleDivide, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleModulo { dest, a, b } => (Opc::DoubleModulo, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleNegate { dest, src } => (Opc::DoubleNegate, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleReciprocal { dest, src } => (Opc::DoubleReciprocal, vec![Value::Register(dest), Value::Register(src)]),
+            DoublePower { dest, a, b } => (Opc::DoublePower, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleExponential { dest, src } => (Opc::DoubleExponential, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleRoot { dest, a, b } => (Opc::DoubleRoot, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleSquareRoot { dest, src } => (Opc::DoubleSquareRoot, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleCubeRoot { dest, src } => (Opc::DoubleCubeRoot, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleSquare { dest, src } => (Opc::DoubleSquare, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleCube { dest, src } => (Opc::DoubleCube, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleLogarithm { dest, a, b } => (Opc::DoubleLogarithm, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleNaturalLogarithm { dest, src } => (Opc::DoubleNaturalLogarithm, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleAbsolute { dest, src } => (Opc::DoubleAbsolute, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleSine { dest, src } => (Opc::DoubleSine, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleCosine { dest, src } => (Opc::DoubleCosine, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleTangent { dest, src } => (Opc::DoubleTangent, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleArcsine { dest, src } => (Opc::DoubleArcsine, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleArccosine { dest, src } => (Opc::DoubleArccosine, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleArctangent { dest, src } => (Opc::DoubleArctangent, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleFloor { dest, src } => (Opc::DoubleFloor, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleCeil { dest, src } => (Opc::DoubleCeil, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleRound { dest, src } => (Opc::DoubleRound, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleMinimum { dest, a, b } => (Opc::DoubleMinimum, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleMaximum { dest, a, b } => (Opc::DoubleMaximum, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleSign { dest, src } => (Opc::DoubleSign, vec![Value::Register(dest), Value::Register(src)]),
+            DoubleAbsoluteDifference { dest, a, b } => (Opc::DoubleAbsoluteDifference, vec![Value::Register(dest), Value::Register(a), Value::Register(b)]),
+            DoubleLoadInfinity { dest } => (Opc::DoubleLoadInfinity, vec![Value::Register(dest)]),
+            DoubleLoadNaN { dest } => (Opc::DoubleLoadNaN, vec![Value::Register(dest)]),
+        }
+    }
+}
+
+fn amount_value(amount: Either<Register, u6>) -> Value {
+    match amount {
+        Left(reg) => Value::Register(reg),
+        Right(imm) => Value::Immediate(imm.value() as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble::assemble;
+    use Register::*;
 
-            // ----------------- Double arithmetic -----------------
-            DoubleAdd { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x0, 0x0),
-            DoubleSubtract { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x0, 0x1),
-            DoubleMultiply { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x0, 0x2),
-            DoubleDivide { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x0, 0x3),
-            DoubleModulo { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x0, 0x4),
-            DoubleNegate { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x0, 0x5),
-            DoubleReciprocal { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x0, 0x6),
-            DoublePower { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x0, 0x7),
-            DoubleExponential { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x0, 0x8),
-            DoubleRoot { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x0, 0x9),
-            DoubleSquareRoot { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x0, 0xA),
-            DoubleCubeRoot { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x0, 0xB),
-            DoubleSquare { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x0, 0xC),
-            DoubleCube { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x0, 0xD),
-            DoubleLogarithm { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x0, 0xE),
-            DoubleNaturalLogarithm { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x0, 0xF),
-            DoubleAbsolute { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x0),
-            DoubleSine { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x1),
-            DoubleCosine { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x2),
-            DoubleTangent { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x3),
-            DoubleArcsine { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x4),
-            DoubleArccosine { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x5),
-            DoubleArctangent { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x6),
-            DoubleFloor { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x7),
-            DoubleCeil { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x8),
-            DoubleRound { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0x9),
-            DoubleMinimum { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x1, 0xA),
-            DoubleMaximum { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x1, 0xB),
-            DoubleSign { dest, src } => assemble_floating(InstrType::DoubleArithmetic, dest, src, None, 0x1, 0xC),
-            DoubleAbsoluteDifference { dest, a, b } => assemble_floating(InstrType::DoubleArithmetic, dest, a, Some(b), 0x1, 0xD),
-            DoubleLoadInfinity { dest } => pack_nibbles([InstrType::DoubleArithmetic.into(), dest.into(), 0, 0, 0, 0, 0x1, 0xE]),
-            DoubleLoadNaN { dest } => pack_nibbles([InstrType::DoubleArithmetic.into(), dest.into(), 0, 0, 0, 0, 0x1, 0xF]),
+    #[test]
+    fn test_matches_text_assembler() {
+        let cases = [
+            (Instruction::Nop, "nop"),
+            (Instruction::Add { dest: R1, a: Left(R2), b: Right(7) }, "add r1 r2 7"),
+            (Instruction::Subtract { dest: R1, a: Left(R2), b: Left(R3) }, "sub r1 r2 r3"),
+            (Instruction::Subtract { dest: R1, a: Right(9), b: Left(R3) }, "sub r1 9 r3"),
+            (Instruction::Multiply { dest: R4, a: Left(R5), b: Right(3) }, "mul r4 r5 3"),
+            (Instruction::Divide { dest: R1, a: Right(100), b: Left(R2) }, "div r1 100 r2"),
+            (Instruction::DivideSigned { dest: R1, a: Left(R2), b: Right(5) }, "sdiv r1 r2 5"),
+            (Instruction::Not { dest: R1, src: R2 }, "not r1 r2"),
+            (Instruction::LeftRoll { dest: R1, src: R2, amount: Right(u6::new(5)) }, "lroll r1 r2 5"),
+            (Instruction::LoadImmediate { dest: R3, slice: u2::new(2), imm: 0xBEEF }, "ldi r3 48879 2"),
+            (Instruction::StoreRegister { src: R3, mem_ptr: Right(300), slice: u3::new(7) }, "str r3 300 7"),
+            (Instruction::Compare { a: Right(4), b: Left(R1), signed: true }, "scmp 4 r1"),
+            (Instruction::BranchNotEqual { offset: Left(R6) }, "jmpne r6"),
+            (Instruction::BranchNotEqual { offset: Right(12) }, "bne 12"),
+            (Instruction::Call { offset: Left(R2) }, "call r2"),
+            (Instruction::FloatToDouble { reg: R9 }, "ftod r9"),
+            (Instruction::DoubleAbsoluteDifference { dest: R1, a: R2, b: R3 }, "ddiff r1 r2 r3"),
+            (Instruction::FloatLoadNaN { dest: R1 }, "fnan r1"),
+            (Instruction::Halt, "hlt"),
+        ];
+
+        for (instruction, source) in cases {
+            assert_eq!(instruction.assemble(), assemble(source.to_string()).unwrap()[0], "{}", source);
         }
     }
+
+    #[test]
+    fn test_negative_offset() {
+        let word = Instruction::Branch { offset: Right(-2) }.assemble();
+        let decoded = crate::isa::decode(word).unwrap();
+        assert_eq!(decoded.signed_field('I'), -2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_two_immediates() {
+        Instruction::Add { dest: R1, a: Right(1), b: Right(2) }.assemble();
+    }
 }
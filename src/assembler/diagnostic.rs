@@ -0,0 +1,126 @@
+use crate::assembler::assembly_error::{AssemblyError, AssemblyErrorVariant};
+use crate::assembler::position::SourceMap;
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Severity {
+    /// The program can't be assembled
+    Error,
+    /// The program is assembled, but probably doesn't do what was intended
+    Warning,
+    /// Information about how the program was assembled
+    Note,
+}
+
+/// An error or warning about a line of the program.
+/// It's displayed with the lines it's about, the part of the line it's about underlined.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub error: AssemblyError,
+    sources: Rc<SourceMap>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, error: AssemblyError, sources: &Rc<SourceMap>) -> Self {
+        Self { severity, error, sources: sources.clone() }
+    }
+
+    /// Writes the location of an error and the line it's on
+    fn excerpt(&self, f: &mut fmt::Formatter<'_>, error: &AssemblyError) -> fmt::Result {
+        writeln!(f, " --> {}", error.location())?;
+
+        let Some(text) = self.sources.line(&error.position) else {
+            return Ok(());
+        };
+
+        let number = (error.position.line + 1).to_string();
+        let gutter = " ".repeat(number.len());
+
+        // Tabs count as one column
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", number, text.replace('\t', " "))?;
+
+        if let Some(span) = &error.span {
+            let width = span.end.saturating_sub(span.start).max(1);
+            writeln!(f, "{} | {}{}", gutter, " ".repeat(span.start), "^".repeat(width))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// The error itself comes first, followed by the macro invocations that led to it
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut invocations = Vec::new();
+        let mut error = &self.error;
+
+        while let AssemblyErrorVariant::InMacro { error: inner, .. } = &error.variant {
+            invocations.push(error);
+            error = inner;
+        }
+
+        writeln!(f, "{}: {}", self.severity, error.message())?;
+        self.excerpt(f, error)?;
+
+        for invocation in invocations.into_iter().rev() {
+            let AssemblyErrorVariant::InMacro { name, .. } = &invocation.variant else { unreachable!() };
+            writeln!(f, "note: In expansion of macro {}", name)?;
+            self.excerpt(f, invocation)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble::{assemble_source, Options};
+
+    fn render(source: &str) -> String {
+        let diagnostics = assemble_source(source.to_string(), None, &Options::default()).err().unwrap_or_default();
+        diagnostics.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(render("nop\n\tad r1 r2 r3\nhlt"), "\
+error: Unrecognized opcode ad, did you mean add?
+ --> Line 2 position 2
+  |
+2 |  ad r1 r2 r3
+  |  ^^
+");
+        assert_eq!(render(".loop\n.macro wait\n    b .lop\n.endm\nnop\nwait"), "\
+error: No label named .lop found, did you mean .loop?
+ --> Line 3 position 7
+  |
+3 |     b .lop
+  |       ^^^^
+note: In expansion of macro wait
+ --> Line 6 position 1
+  |
+6 | wait
+  | ^^^^
+");
+        assert_eq!(render("ldi r1 0x10000 + 1 0\nhlt"), "\
+error: Immediate doesn't fit into 16 bits. Range is 0 to 65535 but got 65537
+ --> Line 1 position 8
+  |
+1 | ldi r1 0x10000 + 1 0
+  |        ^^^^^^^^^^^
+");
+    }
+}
@@ -0,0 +1,200 @@
+use crate::assembler::{
+    assembly_error::{AssemblyError, AssemblyErrorVariant},
+    grammar::expression::{Expression, Symbols},
+    tokenization::token::{Token, TokenVariant},
+    types::directive::Directive,
+};
+
+/// Number of bytes the directive of a line occupies when it's placed at `address`.
+/// Only symbols defined before the directive can be used by `.org`, `.align` and `.space`.
+pub fn size(line: &[Token], address: usize, symbols: &Symbols) -> Result<usize, AssemblyError> {
+    let TokenVariant::Directive(directive) = line[0].variant else { unreachable!("The line isn't a directive") };
+    let args = &line[1..];
+    let error = |variant| AssemblyError::spanning(line, variant);
+
+    match directive {
+        Directive::Org => {
+            let target = single_number(directive, line, symbols)?;
+            if target < address {
+                return Err(error(AssemblyErrorVariant::OrgBackwards { target, address }));
+            }
+            Ok(target - address)
+        }
+        Directive::Align => {
+            let alignment = single_number(directive, line, symbols)?;
+            if !alignment.is_power_of_two() {
+                return Err(error(AssemblyErrorVariant::InvalidAlignment { alignment }));
+            }
+            // Counted without the aligned address, which may not fit
+            Ok((alignment - address % alignment) % alignment)
+        }
+        Directive::Space => single_number(directive, line, symbols),
+        Directive::Equ | Directive::Section | Directive::Global | Directive::Extern => Ok(0),
+        Directive::Macro | Directive::EndMacro | Directive::Include => unreachable!("Macros and includes are expanded before the layout"),
+        Directive::Word | Directive::Byte => {
+            let width = if directive == Directive::Word { 4 } else { 1 };
+            let values = args.iter().all(|arg| Expression::from_token(arg).is_some());
+
+            if args.is_empty() || !values {
+                return Err(error(AssemblyErrorVariant::WrongArguments { directive, expected: "numbers or labels" }));
+            }
+            Ok(args.len() * width)
+        }
+        Directive::Ascii => {
+            let lengths = args.iter().map(|arg| match &arg.variant {
+                TokenVariant::String(text) => Some(text.len()),
+                _ => None,
+            }).collect::<Option<Vec<_>>>();
+
+            match lengths {
+                Some(lengths) if !lengths.is_empty() => Ok(lengths.iter().sum()),
+                _ => Err(error(AssemblyErrorVariant::WrongArguments { directive, expected: "strings" })),
+            }
+        }
+    }
+}
+
+/// Bytes written by a data directive. Directives that only move the location counter write nothing.
+pub fn emit(directive: Directive, args: &[Token], symbols: &Symbols) -> Result<Vec<u8>, AssemblyError> {
+    let mut bytes = Vec::new();
+
+    match directive {
+        Directive::Org | Directive::Align | Directive::Space | Directive::Equ | Directive::Macro | Directive::EndMacro | Directive::Include
+        | Directive::Section | Directive::Global | Directive::Extern => {}
+        Directive::Word => {
+            for arg in args {
+                bytes.extend((value(arg, 32, symbols)? as u32).to_be_bytes());
+            }
+        }
+        Directive::Byte => {
+            for arg in args {
+                bytes.push(value(arg, 8, symbols)? as u8);
+            }
+        }
+        Directive::Ascii => {
+            for arg in args {
+                if let TokenVariant::String(text) = &arg.variant {
+                    bytes.extend(text.as_bytes());
+                }
+            }
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// The only argument of `.org`, `.align` or `.space`
+fn single_number(directive: Directive, line: &[Token], symbols: &Symbols) -> Result<usize, AssemblyError> {
+    let value = match line {
+        [_, arg] => match Expression::from_token(arg) {
+            Some(expression) => expression.evaluate(symbols, arg)?,
+            None => -1,
+        },
+        _ => -1,
+    };
+
+    usize::try_from(value).map_err(|_| AssemblyError::spanning(line, AssemblyErrorVariant::WrongArguments { directive, expected: "a single unsigned number" }))
+}
+
+/// A value that has to fit into `bits` bits, either signed or unsigned
+fn value(arg: &Token, bits: usize, symbols: &Symbols) -> Result<i128, AssemblyError> {
+    let value = Expression::from_token(arg).expect("Argument wasn't checked").evaluate(symbols, arg)?;
+
+    let min = -(1_i64 << (bits - 1));
+    let max = ((1_u64 << bits) - 1) as i64;
+
+    if value < min as i128 || value > max as i128 {
+        return Err(AssemblyError::at(arg, AssemblyErrorVariant::ImmediateTooLarge { bits, min, max, got: value }));
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble::{assemble, assemble_image};
+
+    #[test]
+    fn test_data() {
+        let image = assemble_image(".byte 1 2 -1\n.align 4\n.word 0xDEADBEEF .text -2\n.text\n.ascii \"hi\" \"\\n\"\n.space 2\n.byte 'x'".to_string()).unwrap();
+
+        assert_eq!(image, [
+            1, 2, 0xFF, 0,
+            0xDE, 0xAD, 0xBE, 0xEF,
+            0, 0, 0, 16,
+            0xFF, 0xFF, 0xFF, 0xFE,
+            b'h', b'i', b'\n', 0, 0, b'x',
+        ]);
+    }
+
+    #[test]
+    fn test_code_and_data() {
+        let program = assemble(".org 8\n.start\nbe .end\n.ascii \"abc\"\n.align 4\n.end\nb .start\n.space 3".to_string()).unwrap();
+        let expected = assemble("nop\nnop\nbe 2\nnop\nb -2".to_string()).unwrap();
+
+        assert_eq!(&program[..3], &expected[..3]);
+        assert_eq!(program[3], u32::from_be_bytes(*b"abc\0"));
+        assert_eq!(program[4], expected[4]);
+        assert_eq!(program.len(), 6);
+    }
+
+    #[test]
+    fn test_constants() {
+        let source = "
+            .equ BASE 0x10
+            .equ N 3
+            .start
+            add r1 r2 BASE + 4*N
+            ldi r3 lo16(.table) 0
+            cmp r4 (.end - .start) / 4
+            .org BASE + 4*N
+            .table
+            .word N-1 -N
+            .end
+            .equ SIZE .end - .table
+            .byte SIZE";
+        let expected = assemble("
+            add r1 r2 28
+            ldi r3 28 0
+            cmp r4 9
+            nop
+            nop
+            nop
+            nop
+            .word 2 -3
+            .byte 8".to_string()).unwrap();
+
+        assert_eq!(assemble(source.to_string()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_errors() {
+        for (source, expected) in [
+            ("nop\n.org 2", "Line 2: Can't move back to address 0x2, already at 0x4"),
+            (".align 3", "Line 1: Alignment 3 isn't a power of two"),
+            (".byte 1\nnop", "Line 2: Instruction at 0x1 isn't aligned to 4 bytes"),
+            (".word 0x1_0000_0000", "Line 1: Immediate doesn't fit into 32 bits. Range is -2147483648 to 4294967295 but got 4294967296"),
+            (".byte 1 -129", "Line 1: Immediate doesn't fit into 8 bits. Range is -128 to 255 but got -129"),
+            (".word .nowhere", "Line 1: No label named .nowhere found"),
+            (".ascii 5", "Line 1: .ascii expects strings"),
+            (".space", "Line 1: .space expects a single unsigned number"),
+            (".byte", "Line 1: .byte expects numbers or labels"),
+            (".equ N 1\n.equ N 2", "Line 2: Constant N is already defined"),
+            (".equ N M", "Line 1: No constant named M defined"),
+            (".equ N\n", "Line 1: .equ expects a name and a value"),
+            (".org N\n.equ N 4", "Line 1: No constant named N defined"),
+            (".space 1 - 2", "Line 1: .space expects a single unsigned number"),
+            ("ad r1 r2 r3", "Line 1: Unrecognized opcode ad, did you mean add?"),
+            ("add r1 r2 0xFFFF + 1", "Line 1: Immediate doesn't fit into 16 bits. Range is 0 to 65535 but got 65536"),
+            ("add r1 r2 (1", "Line 1: Invalid expression: missing closing parenthesis"),
+            (".byte 1\n.odd\n.space 3\nb .odd", "Line 4: Label .odd at 0x1 isn't aligned to an instruction"),
+            (".org 0xFFFFFFFFFFFFFFFF", "Line 1: Program would reach 0xffffffffffffffff, past the end of memory at 0x1000"),
+            ("nop\n.space 0x10000000000", "Line 2: Program would reach 0x10000000004, past the end of memory at 0x1000"),
+            ("nop\n.space 0xFFFFFFFFFFFFFFFF", "Line 2: Program would reach 0x10000000000000003, past the end of memory at 0x1000"),
+            (".org 0x1000\nhlt", "Line 2: Program would reach 0x1004, past the end of memory at 0x1000"),
+            ("nop\n.align 0x8000000000000000", "Line 2: Program would reach 0x8000000000000000, past the end of memory at 0x1000"),
+        ] {
+            assert_eq!(assemble_image(source.to_string()).unwrap_err().to_string(), expected, "{}", source);
+        }
+    }
+}
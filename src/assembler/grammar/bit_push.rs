@@ -69,7 +69,7 @@ mod tests {
         pusher.push_zeros(2);     // 00 at bits 23-24
         pusher.push(5, 3);   // 101 at bits 20-22
 
-        assert_eq!(pusher.state >> 20, 0b1111_111_00_101);
+        assert_eq!(pusher.state >> 20, 0b1111_1110_0101);
     }
 
     #[test]
@@ -81,6 +81,6 @@ mod tests {
         pusher.push(31, 5);  // 11111
         pusher.push(7, 3);   // 111
 
-        assert_eq!(pusher.state >> 16, 0b111111_10_11111_111);
+        assert_eq!(pusher.state >> 16, 0b1111_1110_1111_1111);
     }
 }
\ No newline at end of file
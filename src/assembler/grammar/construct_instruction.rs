@@ -1,29 +1,70 @@
 use crate::assembler::{
     grammar::bit_push::BitPush,
-    grammar::bit_run_length_coding::BitRunLengthCoding,
-    grammar::get_patterns::get_patterns,
+    grammar::expression::Symbols,
+    grammar::pattern_index::patterns,
     grammar::token_pattern::AmbiguousToken,
     grammar::token_pattern::TokenPattern,
-    grammar::encoding::Encoding,
     assembly_error::{AssemblyError, AssemblyErrorVariant},
     tokenization::token::{Token, TokenVariant},
 };
-use std::collections::HashMap;
-
-pub fn find_matching_pattern(tokens: &[AmbiguousToken]) -> Option<TokenPattern> {
-    let matching_patterns = get_patterns().into_iter().filter(|p| p.matches(tokens)).collect::<Vec<_>>();
-    match matching_patterns.len() {
-        0 => None,
-        1 => Some(matching_patterns[0].clone()),
-        _ => panic!("Found {} matching patterns: {:?}", matching_patterns.len(), matching_patterns),
+
+pub fn find_matching_pattern(tokens: &[AmbiguousToken]) -> Option<&'static TokenPattern> {
+    patterns().find(tokens)
+}
+
+/// Why no pattern matches a line: the operands its opcode takes and the ones it got.
+/// Forms that differ in a single operand are combined, like `reg, reg, reg|imm`, and the forms closest to the line come first.
+pub fn explain_mismatch(tokens: &[AmbiguousToken]) -> AssemblyErrorVariant {
+    let AmbiguousToken::Opcode(opcode) = tokens[0] else {
+        return AssemblyErrorVariant::UnknownTokenPattern;
+    };
+    let got = tokens[1..].iter().map(AmbiguousToken::describe).collect::<Vec<_>>();
+
+    // Every form is a list of operands, every operand a list of the kinds it takes
+    let mut forms: Vec<Vec<Vec<&str>>> = Vec::new();
+
+    for pattern in patterns().forms(opcode) {
+        let form = pattern.expected_tokens[1..].iter().map(|token| vec![token.describe()]).collect();
+        if !forms.contains(&form) {
+            forms.push(form);
+        }
+    }
+
+    let differ_once = |a: &Vec<Vec<&str>>, b: &Vec<Vec<&str>>| a.len() == b.len() && a.iter().zip(b).filter(|(a, b)| a != b).count() == 1;
+
+    while let Some((i, j)) = (0..forms.len())
+        .flat_map(|i| (i + 1..forms.len()).map(move |j| (i, j)))
+        .find(|&(i, j)| differ_once(&forms[i], &forms[j]))
+    {
+        let other = forms.remove(j);
+
+        for (kinds, other_kinds) in forms[i].iter_mut().zip(other) {
+            for kind in other_kinds {
+                if !kinds.contains(&kind) {
+                    kinds.push(kind);
+                }
+            }
+        }
+    }
+
+    let mismatches = |form: &Vec<Vec<&str>>| form.iter().zip(&got).filter(|(kinds, kind)| !kinds.contains(kind)).count();
+    forms.sort_by_key(|form| (form.len().abs_diff(got.len()), mismatches(form)));
+
+    let describe = |operands: Vec<String>| if operands.is_empty() { "nothing".to_string() } else { operands.join(", ") };
+
+    AssemblyErrorVariant::OperandMismatch {
+        opcode,
+        expected: forms.into_iter().map(|form| describe(form.iter().map(|kinds| kinds.join("|")).collect())).collect(),
+        got: describe(got.iter().map(|kind| kind.to_string()).collect()),
     }
 }
 
-/// Attemps to construct an assembled instruction from tokens
-pub fn construct_instruction(tokens: &[Token], bit_pattern: &BitRunLengthCoding, encoding: &Encoding, labels: &HashMap<String, usize>, instruction: usize) -> Result<u32, AssemblyError> {
+/// Attemps to construct an assembled instruction from tokens.
+/// Labels and `address` are byte addresses, label operands are encoded as the offset in instructions.
+pub fn construct_instruction(tokens: &[Token], pattern: &TokenPattern, symbols: &Symbols, address: usize) -> Result<u32, AssemblyError> {
     let mut bit_push = BitPush::new();
 
-    for &(ch, count) in bit_pattern.get() {
+    for &(ch, count) in pattern.bit_pattern.get() {
         if ch != '0' && ch != '1' && !ch.is_ascii_uppercase() {
             panic!("Unexpected character '{}'", ch);
         }
@@ -36,35 +77,45 @@ pub fn construct_instruction(tokens: &[Token], bit_pattern: &BitRunLengthCoding,
             continue;
         }
 
-        let token = match tokens.get(*encoding.get(ch).unwrap()) {
+        let index = *pattern.encoding.get(ch).unwrap();
+        let token = match tokens.get(index) {
             None => panic!("Invalid index"),
-            Some(token) => &token.variant,
+            Some(token) => token,
         };
 
         // ch is in 'A'..='Z'
-        match token {
+        match &token.variant {
             TokenVariant::Opcode(opc) => panic!("Opcodes can't be assembled (opcode: '{:?}')", opc),
+            TokenVariant::Expression(expression) => {
+                let value = expression.evaluate(symbols, token)?;
+                bit_push.push(immediate(token, value, &pattern.expected_tokens[index], count)?, count)
+            }
+            TokenVariant::Pseudo(_) | TokenVariant::Directive(_) | TokenVariant::String(_) | TokenVariant::Symbol(_) | TokenVariant::Operator(_) | TokenVariant::Colon => panic!("No pattern takes {:?}", token.variant),
             TokenVariant::Label(name) => {
-                let label_i = match labels.get(name.as_str()) {
-                    None => return Err(AssemblyError {
-                        line: instruction,
-                        column: None,
-                        variant: AssemblyErrorVariant::NoLabelFound { name: name.to_string() }}),
-                    Some(i) => *i as u32 as i32,
+                let label_address = match symbols.labels.get(name.as_str()) {
+                    None => return Err(AssemblyError::at(token, AssemblyErrorVariant::NoLabelFound {
+                        name: name.to_string(),
+                        suggestion: symbols.closest_label(name),
+                    })),
+                    Some(&label_address) if !label_address.is_multiple_of(4) => return Err(AssemblyError::at(token, AssemblyErrorVariant::MisalignedLabel {
+                        name: name.to_string(),
+                        address: label_address,
+                    })),
+                    Some(&label_address) => label_address,
                 };
 
-                let offset = label_i - instruction as i32;
+                let offset = (label_address as i64 - address as i64) / 4;
 
                 let offset = match offset {
-                    o if o < i16::MIN as i32 => return Err(AssemblyError { line: instruction, column: None, variant: AssemblyErrorVariant::OffsetTooLarge { limit: i16::MIN as i32, required: o as i64 } }),
-                    o if o > i16::MAX as i32 => return Err(AssemblyError { line: instruction, column: None, variant: AssemblyErrorVariant::OffsetTooLarge { limit: i16::MAX as i32, required: o as i64 } }),
+                    o if o < i16::MIN as i64 => return Err(AssemblyError::at(token, AssemblyErrorVariant::OffsetTooLarge { limit: i16::MIN as i32, required: o })),
+                    o if o > i16::MAX as i64 => return Err(AssemblyError::at(token, AssemblyErrorVariant::OffsetTooLarge { limit: i16::MAX as i32, required: o })),
                     _ => offset as u16,
                 };
 
                 bit_push.push(offset as u32, count);
             },
-            TokenVariant::Unsigned(value) => bit_push.push(*value as u32, count),
-            TokenVariant::Signed(value) => bit_push.push(*value as u16 as u32, count),
+            TokenVariant::Unsigned(value) => bit_push.push(immediate(token, *value as i128, &pattern.expected_tokens[index], count)?, count),
+            TokenVariant::Signed(value) => bit_push.push(immediate(token, *value as i128, &pattern.expected_tokens[index], count)?, count),
             TokenVariant::Register(reg) => bit_push.push(*reg as u32, count),
             TokenVariant::Bool(b) => bit_push.push(*b as u32, count),
         }
@@ -73,6 +124,22 @@ pub fn construct_instruction(tokens: &[Token], bit_pattern: &BitRunLengthCoding,
     Ok(bit_push.get_value().expect("Something went wrong"))
 }
 
+/// Checks that an immediate fits into a field of `width` bits and returns its bits.
+/// Signed fields also take their unsigned bit pattern, so `65535` and `-1` are the same 16-bit immediate.
+fn immediate(token: &Token, value: i128, expected: &AmbiguousToken, width: usize) -> Result<u32, AssemblyError> {
+    let max = (1_i64 << width) - 1;
+    let min = match expected {
+        AmbiguousToken::Signed => -(1_i64 << (width - 1)),
+        _ => 0,
+    };
+
+    if value < min as i128 || value > max as i128 {
+        return Err(AssemblyError::at(token, AssemblyErrorVariant::ImmediateTooLarge { bits: width, min, max, got: value }));
+    }
+
+    Ok(value as u32 & max as u32)
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::super::types::opcode::Opcode as Opc;
@@ -98,4 +165,62 @@ mod tests {
         assert!(find_matching_pattern(&[Opcode(Opc::Add), Register, Register, Unsigned]).is_some());
         assert!(find_matching_pattern(&[Opcode(Opc::Add)]).is_none());
     }
+
+    #[test]
+    fn test_call_ret() {
+        assert!(find_matching_pattern(&[Opcode(Opc::Call), Register]).is_some());
+        assert!(find_matching_pattern(&[Opcode(Opc::Call), Label]).is_some());
+        assert!(find_matching_pattern(&[Opcode(Opc::Call)]).is_none());
+        assert!(find_matching_pattern(&[Opcode(Opc::Return)]).is_some());
+        assert!(find_matching_pattern(&[Opcode(Opc::Return), Register]).is_none());
+    }
+
+    #[test]
+    fn test_explain_mismatch() {
+        use crate::assembler::assemble::assemble;
+
+        for (source, expected) in [
+            ("add r1 5 5", "add expects reg, reg, reg|imm; got reg, imm, imm"),
+            ("push", "push expects reg; got nothing"),
+            ("pop 5", "pop expects reg; got imm"),
+            ("b r1", "b expects imm|label; got reg"),
+            ("r1 r2", "Unknown token pattern"),
+        ] {
+            let err = assemble(source.to_string()).unwrap_err();
+            assert_eq!(err.message(), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_immediate_literals() {
+        use crate::assembler::assemble::assemble;
+
+        assert_eq!(assemble("add r1 r2 0xFFFF".to_string()).unwrap(), assemble("add r1 r2 65535".to_string()).unwrap());
+        assert_eq!(assemble("sdiv r1 r2 -1".to_string()).unwrap(), assemble("sdiv r1 r2 65535".to_string()).unwrap());
+        assert_eq!(assemble("cmp r1 'A'".to_string()).unwrap(), assemble("cmp r1 65".to_string()).unwrap());
+        assert_eq!(assemble("lsh r1 r2 0b11_1111".to_string()).unwrap(), assemble("lsh r1 r2 63".to_string()).unwrap());
+        assert_eq!(assemble("b -0x8000".to_string()).unwrap(), assemble("b 32768".to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_immediate_range() {
+        use crate::assembler::assemble::assemble;
+
+        for (source, range) in [
+            ("add r1 r2 65536", (16, 0, 65535, 65536)),
+            ("lsh r1 r2 64", (6, 0, 63, 64)),
+            ("ldi r1 5 4", (2, 0, 3, 4)),
+            ("sdiv r1 r2 -32769", (16, -32768, 65535, -32769)),
+            ("add r1 r2 -1", (16, 0, 65535, -1)),
+            ("nop\nb -0x1_0000", (16, -32768, 65535, -65536)),
+        ] {
+            let err = assemble(source.to_string()).unwrap_err();
+
+            match err.variant {
+                AssemblyErrorVariant::ImmediateTooLarge { bits, min, max, got } => assert_eq!((bits, min, max, got), range, "{}", source),
+                other => panic!("`{}` failed with {:?}", source, other),
+            }
+            assert_eq!(err.position.line, source.lines().count() - 1);
+        }
+    }
 }
@@ -0,0 +1,333 @@
+use crate::assembler::{
+    assembly_error::{AssemblyError, AssemblyErrorVariant},
+    suggest::closest,
+    tokenization::token::{Token, TokenVariant},
+    types::operator::Operator,
+};
+use std::collections::HashMap;
+
+/// Smallest and largest value an expression may take anywhere during its evaluation
+const MIN: i128 = i64::MIN as i128;
+const MAX: i128 = u64::MAX as i128;
+
+/// An operand computed while assembling
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Expression {
+    Number(i128),
+    /// A constant defined with `.equ`
+    Constant(String),
+    /// The byte address of a label
+    Label(String),
+    Unary(Operator, Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+    Function(Function, Box<Expression>),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Function {
+    /// Bits 16 to 31
+    High16,
+    /// Bits 0 to 15
+    Low16,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "hi16" => Some(Function::High16),
+            "lo16" => Some(Function::Low16),
+            _ => None,
+        }
+    }
+}
+
+/// Labels and constants expressions can refer to
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    pub labels: HashMap<String, usize>,
+    pub constants: HashMap<String, i128>,
+}
+
+impl Symbols {
+    /// The label a misspelled label name probably means
+    pub fn closest_label(&self, name: &str) -> Option<String> {
+        // Labels local to a macro expansion can't be named outside of it
+        closest(name, self.labels.keys().map(String::as_str).filter(|label| !label.contains('@')))
+    }
+}
+
+impl Expression {
+    /// The expression a single operand stands for, if it's a value
+    pub fn from_token(token: &Token) -> Option<Expression> {
+        match &token.variant {
+            TokenVariant::Unsigned(value) => Some(Expression::Number(*value as i128)),
+            TokenVariant::Signed(value) => Some(Expression::Number(*value as i128)),
+            TokenVariant::Label(name) => Some(Expression::Label(name.to_owned())),
+            TokenVariant::Expression(expression) => Some(expression.clone()),
+            _ => None,
+        }
+    }
+
+    /// The value of the expression, with errors pointing at `token`, the operand it's from
+    pub fn evaluate(&self, symbols: &Symbols, token: &Token) -> Result<i128, AssemblyError> {
+        let error = |variant| AssemblyError::at(token, variant);
+
+        let value = match self {
+            Expression::Number(value) => *value,
+            Expression::Constant(name) => match symbols.constants.get(name) {
+                Some(value) => *value,
+                None => return Err(error(AssemblyErrorVariant::UndefinedSymbol {
+                    name: name.to_owned(),
+                    suggestion: closest(name, symbols.constants.keys().map(String::as_str)),
+                })),
+            },
+            Expression::Label(name) => match symbols.labels.get(name) {
+                Some(address) => *address as i128,
+                None => return Err(error(AssemblyErrorVariant::NoLabelFound { name: name.to_owned(), suggestion: symbols.closest_label(name) })),
+            },
+            Expression::Unary(operator, operand) => {
+                let operand = operand.evaluate(symbols, token)?;
+                match operator {
+                    Operator::Minus => -operand,
+                    Operator::Not => !operand,
+                    _ => operand,
+                }
+            }
+            Expression::Binary(operator, left, right) => {
+                let left = left.evaluate(symbols, token)?;
+                let right = right.evaluate(symbols, token)?;
+
+                match operator {
+                    Operator::Plus => left + right,
+                    Operator::Minus => left - right,
+                    Operator::Times => left.checked_mul(right).unwrap_or(i128::MAX),
+                    Operator::Divide | Operator::Modulo if right == 0 => return Err(error(AssemblyErrorVariant::DivisionByZero)),
+                    Operator::Divide => left / right,
+                    Operator::Modulo => left % right,
+                    Operator::And => left & right,
+                    Operator::Or => left | right,
+                    Operator::Xor => left ^ right,
+                    Operator::ShiftLeft => match u32::try_from(right) {
+                        Ok(amount) if amount < 64 => left.checked_mul(1 << amount).unwrap_or(i128::MAX),
+                        _ if left == 0 => 0,
+                        _ => return Err(error(AssemblyErrorVariant::ExpressionOverflow)),
+                    },
+                    Operator::ShiftRight => match u32::try_from(right) {
+                        Ok(amount) => left >> amount.min(127),
+                        Err(_) => return Err(error(AssemblyErrorVariant::ExpressionOverflow)),
+                    },
+                    other => unreachable!("{} isn't a binary operator", other),
+                }
+            }
+            Expression::Function(function, argument) => {
+                let argument = argument.evaluate(symbols, token)?;
+                match function {
+                    Function::High16 => (argument >> 16) & 0xFFFF,
+                    Function::Low16 => argument & 0xFFFF,
+                }
+            }
+        };
+
+        if !(MIN..=MAX).contains(&value) {
+            return Err(error(AssemblyErrorVariant::ExpressionOverflow));
+        }
+
+        Ok(value)
+    }
+}
+
+/// Groups the operands of a line into one token each.
+/// Registers, strings and single literals or labels stay as they are, anything longer becomes an expression.
+pub fn group_operands(tokens: Vec<Token>) -> Result<Vec<Token>, AssemblyError> {
+    let mut operands = Vec::with_capacity(tokens.len());
+    let mut i = 1;
+
+    while i < tokens.len() {
+        if matches!(tokens[i].variant, TokenVariant::Register(_) | TokenVariant::String(_)) {
+            operands.push(tokens[i].clone());
+            i += 1;
+            continue;
+        }
+
+        let mut parser = Parser { tokens: &tokens, pos: i };
+        let expression = parser.expression(0)?;
+
+        let single = parser.pos == i + 1 && matches!(tokens[i].variant, TokenVariant::Unsigned(_) | TokenVariant::Signed(_) | TokenVariant::Label(_));
+
+        if single {
+            operands.push(tokens[i].clone());
+        } else {
+            operands.push(Token {
+                position: tokens[i].position.clone(),
+                variant: TokenVariant::Expression(expression),
+                range: tokens[i].range.start..tokens[parser.pos - 1].range.end,
+                expansion: tokens[i].expansion.clone(),
+            });
+        }
+
+        i = parser.pos;
+    }
+
+    let mut tokens = tokens;
+    tokens.truncate(1);
+    tokens.extend(operands);
+
+    Ok(tokens)
+}
+
+/// Precedence climbing parser over the tokens of one line
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &'static str) -> AssemblyError {
+        let token = self.tokens.get(self.pos).unwrap_or(&self.tokens[self.tokens.len() - 1]);
+        AssemblyError::at(token, AssemblyErrorVariant::InvalidExpression { reason })
+    }
+
+    fn peek(&self) -> Option<&TokenVariant> {
+        self.tokens.get(self.pos).map(|token| &token.variant)
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Result<Expression, AssemblyError> {
+        let mut left = self.term()?;
+
+        while let Some((operator, precedence)) = self.binary_operator() {
+            if precedence < min_precedence {
+                break;
+            }
+
+            // A negative literal is its own right operand
+            if let Some(TokenVariant::Operator(_)) = self.peek() {
+                self.pos += 1;
+            }
+
+            let right = self.expression(precedence + 1)?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    /// The binary operator at the current position, without consuming it.
+    /// A sign with a space before it but not after it starts the next operand, so `N-1` and `N - 1` are one operand but `N -1` are two.
+    fn binary_operator(&self) -> Option<(Operator, u8)> {
+        let token = self.tokens.get(self.pos)?;
+        let spaced_before = token.range.start != self.tokens[self.pos - 1].range.end;
+        let spaced_after = self.tokens.get(self.pos + 1).is_none_or(|next| next.range.start != token.range.end);
+
+        match &token.variant {
+            TokenVariant::Operator(Operator::Plus | Operator::Minus) if spaced_before && !spaced_after => None,
+            TokenVariant::Operator(operator) => operator.precedence().map(|precedence| (*operator, precedence)),
+            TokenVariant::Signed(_) if !spaced_before => Some((Operator::Plus, Operator::Plus.precedence().unwrap())),
+            _ => None,
+        }
+    }
+
+    fn term(&mut self) -> Result<Expression, AssemblyError> {
+        let Some(token) = self.tokens.get(self.pos) else {
+            return Err(self.error("expected a value"));
+        };
+        self.pos += 1;
+
+        match &token.variant {
+            TokenVariant::Unsigned(value) => Ok(Expression::Number(*value as i128)),
+            TokenVariant::Signed(value) => Ok(Expression::Number(*value as i128)),
+            TokenVariant::Label(name) => Ok(Expression::Label(name.to_owned())),
+            // A macro argument
+            TokenVariant::Expression(expression) => Ok(expression.clone()),
+            TokenVariant::Symbol(name) => match Function::from_name(name) {
+                Some(function) if self.peek() == Some(&TokenVariant::Operator(Operator::OpenParen)) => {
+                    let argument = self.term()?;
+                    Ok(Expression::Function(function, Box::new(argument)))
+                }
+                _ => Ok(Expression::Constant(name.to_owned())),
+            },
+            TokenVariant::Operator(operator @ (Operator::Minus | Operator::Not | Operator::Plus)) => {
+                let operand = self.term()?;
+                Ok(Expression::Unary(*operator, Box::new(operand)))
+            }
+            TokenVariant::Operator(Operator::OpenParen) => {
+                let expression = self.expression(0)?;
+                match self.peek() {
+                    Some(TokenVariant::Operator(Operator::CloseParen)) => {
+                        self.pos += 1;
+                        Ok(expression)
+                    }
+                    _ => Err(self.error("missing closing parenthesis")),
+                }
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::tokenization::tokenize::Tokenizer;
+
+    fn operands(source: &str) -> Result<Vec<TokenVariant>, AssemblyError> {
+        let tokens = Tokenizer::new(source.to_string()).tokenize().unwrap().into_iter()
+            .map(|token| Token::try_from(token).unwrap())
+            .collect();
+
+        Ok(group_operands(tokens)?.into_iter().skip(1).map(|token| token.variant).collect())
+    }
+
+    fn evaluate(source: &str) -> Result<i128, AssemblyError> {
+        let mut symbols = Symbols::default();
+        symbols.constants.insert("N".to_string(), 3);
+        symbols.labels.insert(".start".to_string(), 8);
+        symbols.labels.insert(".end".to_string(), 20);
+
+        let tokens = Tokenizer::new(format!(".word {}", source)).tokenize().unwrap().into_iter()
+            .map(|token| Token::try_from(token).unwrap())
+            .collect();
+
+        match group_operands(tokens)?.as_slice() {
+            [_, operand] => Expression::from_token(operand).unwrap().evaluate(&symbols, operand),
+            other => panic!("`{}` isn't one operand: {:?}", source, other),
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(evaluate("(1 + 2) * 3").unwrap(), 9);
+        assert_eq!(evaluate("N-1").unwrap(), 2);
+        assert_eq!(evaluate("N - -1").unwrap(), 4);
+        assert_eq!(evaluate("-N").unwrap(), -3);
+        assert_eq!(evaluate("(.end - .start) / 4").unwrap(), 3);
+        assert_eq!(evaluate("1 << 4 | 1").unwrap(), 17);
+        assert_eq!(evaluate("~0 & 0xFF").unwrap(), 255);
+        assert_eq!(evaluate("17 % 5 ^ 1").unwrap(), 3);
+        assert_eq!(evaluate("hi16(0x1234_5678)").unwrap(), 0x1234);
+        assert_eq!(evaluate("lo16(0x1234_5678 + N)").unwrap(), 0x567B);
+        assert_eq!(evaluate("0xFFFF_FFFF_FFFF_FFFF >> 60").unwrap(), 15);
+    }
+
+    #[test]
+    fn test_grouping() {
+        assert_eq!(operands("ldi r1 5 N").unwrap()[..2], [TokenVariant::Register(crate::assembler::types::register::Register::R1), TokenVariant::Unsigned(5)]);
+        assert_eq!(operands(".word 1 -2 .start").unwrap(), [TokenVariant::Unsigned(1), TokenVariant::Signed(-2), TokenVariant::Label(".start".to_string())]);
+        assert_eq!(operands(".word N -1").unwrap().len(), 2);
+        assert_eq!(operands(".word N-1 N - 1 (N) -N").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_errors() {
+        let variant = |source| evaluate(source).unwrap_err().variant;
+
+        assert!(matches!(variant("M + 1"), AssemblyErrorVariant::UndefinedSymbol { name, suggestion } if name == "M" && suggestion.as_deref() == Some("N")));
+        assert!(matches!(variant(".nowhere"), AssemblyErrorVariant::NoLabelFound { .. }));
+        assert!(matches!(variant("N / (N - 3)"), AssemblyErrorVariant::DivisionByZero));
+        assert!(matches!(variant("0xFFFF_FFFF_FFFF_FFFF + 1"), AssemblyErrorVariant::ExpressionOverflow));
+        assert!(matches!(variant("-(0xFFFF_FFFF_FFFF_FFFF)"), AssemblyErrorVariant::ExpressionOverflow));
+        assert!(matches!(variant("1 << 64"), AssemblyErrorVariant::ExpressionOverflow));
+        assert!(matches!(variant("(1 + 2"), AssemblyErrorVariant::InvalidExpression { .. }));
+        assert!(matches!(variant("1 +"), AssemblyErrorVariant::InvalidExpression { .. }));
+    }
+}
@@ -1,127 +1,285 @@
 use std::str::FromStr;
-use crate::assembler::grammar::token_pattern::AmbiguousToken::Signed;
+use crate::isa::{FORMS, Operand};
 use super::{
     encoding::Encoding,
     bit_run_length_coding::BitRunLengthCoding,
-    token_pattern::AmbiguousToken::*,
+    token_pattern::AmbiguousToken,
     token_pattern::TokenPattern,
-    super::types::opcode::Opcode as Opc,
 };
 
+/// Token patterns for every instruction form of the ISA table
 pub fn get_patterns() -> Vec<TokenPattern> {
-    let mut patterns = Vec::new();
-    patterns.append(&mut nop_patterns());
-    patterns.append(&mut add_patterns());
-    patterns.append(&mut jump_patterns());
-    patterns
-}
+    FORMS.iter().map(|form| {
+        let operands = form.operands.iter().map(|operand| match operand {
+            Operand::Register(_) => AmbiguousToken::Register,
+            Operand::Unsigned(_) => AmbiguousToken::Unsigned,
+            Operand::Signed(_) => AmbiguousToken::Signed,
+            Operand::Label(_) => AmbiguousToken::Label,
+        });
+
+        // Token 0 is the opcode, so operand i is token i + 1
+        let encoding = form.operands.iter().enumerate().map(|(i, operand)| (operand.field(), i + 1)).collect();
 
-fn nop_patterns() -> Vec<TokenPattern> {
-    vec![
         TokenPattern {
-            expected_tokens: vec![Opcode(Opc::Nop)],
-            bit_pattern: BitRunLengthCoding::from_str("0000 0000 0000 0000 0000 0000 0000 0000").unwrap(),
-            encoding: Encoding::new(vec![]),
-        },
-    ]
+            expected_tokens: std::iter::once(AmbiguousToken::Opcode(form.opcode)).chain(operands).collect(),
+            bit_pattern: BitRunLengthCoding::from_str(form.bits).unwrap(),
+            encoding: Encoding::new(encoding),
+        }
+    }).collect()
 }
 
-fn add_patterns() -> Vec<TokenPattern> {
-    vec![
-        TokenPattern { // Register addition
-            expected_tokens: vec![Opcode(Opc::Add), Register, Register, Register],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB CCCC 0000 0000 0000 0000").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('C', 3)]),
-        },
-        TokenPattern { // Immediate addition
-            expected_tokens: vec![Opcode(Opc::Add), Register, Register, Unsigned],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB IIII IIII IIII IIII 0001").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('I', 3)]),
-        },
-    ]
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble::assemble_image;
+    use crate::assembler::grammar::construct_instruction::find_matching_pattern;
+    use crate::assembler::types::opcode::Opcode as Opc;
+    use AmbiguousToken::Opcode;
+    use crate::assembler::tokenization::{token::Token, tokenize::Tokenizer};
+    use crate::cpu::{Cpu, RunOutcome};
 
-fn sub_patterns() -> Vec<TokenPattern> {
-    vec![
-        TokenPattern { // Register subtraction
-            expected_tokens: vec![Opcode(Opc::Subtract), Register, Register, Register],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB CCCC 0000 0000 0000 0010").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('C', 3)]),
-        },
-        TokenPattern { // Immediate subtraction
-            expected_tokens: vec![Opcode(Opc::Subtract), Register, Register, Unsigned],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB IIII IIII IIII IIII 0011").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('I', 3)]),
-        },
-        TokenPattern { // Reverse immediate subtraction
-            expected_tokens: vec![Opcode(Opc::Subtract), Register, Unsigned, Register],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB IIII IIII IIII IIII 0100").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 3), ('I', 2)]),
-        },
-    ]
-}
+    /// A snippet of assembly together with the initial CPU state and the expected result
+    struct Case {
+        source: String,
+        setup: Box<dyn Fn(&mut Cpu)>,
+        check: Box<dyn Fn(&Cpu) -> bool>,
+    }
 
-fn mul_patterns() -> Vec<TokenPattern> {
-    vec![
-        TokenPattern { // Register multiplication
-            expected_tokens: vec![Opcode(Opc::Multiply), Register, Register, Register],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB CCCC 0000 0000 0000 0101").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('C', 3)]),
-        },
-        TokenPattern { // Immediate multiplication
-            expected_tokens: vec![Opcode(Opc::Multiply), Register, Register, Unsigned],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB IIII IIII IIII IIII 0110").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('I', 3)]),
-        },
-    ]
-}
+    fn case(source: &str, setup: impl Fn(&mut Cpu) + 'static, check: impl Fn(&Cpu) -> bool + 'static) -> Case {
+        Case { source: source.to_string(), setup: Box::new(setup), check: Box::new(check) }
+    }
 
-fn div_patterns() -> Vec<TokenPattern> {
-    vec![
-        TokenPattern { // Unsigned register division
-            expected_tokens: vec![Opcode(Opc::Divide), Register, Register, Register],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB CCCC 0000 0000 0000 0111").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('C', 3)]),
-        },
-        TokenPattern { // Unsigned immediate division
-            expected_tokens: vec![Opcode(Opc::Divide), Register, Register, Unsigned],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB IIII IIII IIII IIII 1000").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('I', 3)]),
-        },
-        TokenPattern { // Unsigned reverse immediate division
-            expected_tokens: vec![Opcode(Opc::Divide), Register, Unsigned, Register],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB IIII IIII IIII IIII 1001").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('I', 3)]),
-        },
-    ]
-}
+    fn regs(values: Registers) -> impl Fn(&mut Cpu) {
+        move |cpu| values.iter().for_each(|&(reg, value)| cpu.regs[reg] = value)
+    }
 
-fn sdiv_patterns() -> Vec<TokenPattern> {
-    vec![
-        TokenPattern { // Signed register division
-            expected_tokens: vec![Opcode(Opc::Divide), Register, Register, Register],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB CCCC 0000 0000 0000 1010").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('C', 3)]),
-        },
-        TokenPattern { // Signed immediate division
-            expected_tokens: vec![Opcode(Opc::Divide), Register, Register, Signed],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB IIII IIII IIII IIII 1011").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('I', 3)]),
-        },
-        TokenPattern { // Signed reverse immediate division
-            expected_tokens: vec![Opcode(Opc::Divide), Register, Signed, Register],
-            bit_pattern: BitRunLengthCoding::from_str("0001 AAAA BBBB IIII IIII IIII IIII 1100").unwrap(),
-            encoding: Encoding::new(vec![('A', 1), ('B', 2), ('I', 3)]),
-        },
-    ]
-}
+    fn f(value: f32) -> u64 {
+        value.to_bits() as u64
+    }
 
-fn jump_patterns() -> Vec<TokenPattern> {
-    vec![
-        TokenPattern {
-            expected_tokens: vec![Opcode(Opc::Jump), Label],
-            bit_pattern: BitRunLengthCoding::from_str("0110 IIII IIII IIII IIII 0000 0000 0001").unwrap(),
-            encoding: Encoding::new(vec![('I', 1)]),
+    fn d(value: f64) -> u64 {
+        value.to_bits()
+    }
+
+    fn run(case: &Case) -> Cpu {
+        let image = assemble_image(format!("{}\nhlt", case.source))
+            .unwrap_or_else(|err| panic!("`{}` failed to assemble: {}", case.source, err));
+        let mut cpu = Cpu::default();
+        crate::load_program(&mut cpu, &image);
+        (case.setup)(&mut cpu);
+
+        let result = cpu.run(1000);
+        assert_eq!(result.outcome, RunOutcome::Halted, "`{}` didn't halt", case.source);
+        cpu
+    }
+
+    fn integer_cases() -> Vec<Case> {
+        const NEG_10: u64 = -10_i64 as u64;
+
+        vec![
+            // Arithmetic
+            case("add r1 r2 r3", regs(&[(2, 10), (3, 3)]), |cpu| cpu.regs[1] == 13),
+            case("add r1 r2 5", regs(&[(2, 10)]), |cpu| cpu.regs[1] == 15),
+            case("sub r1 r2 r3", regs(&[(2, 10), (3, 3)]), |cpu| cpu.regs[1] == 7),
+            case("sub r1 r2 4", regs(&[(2, 10)]), |cpu| cpu.regs[1] == 6),
+            case("sub r1 20 r2", regs(&[(2, 10)]), |cpu| cpu.regs[1] == 10),
+            case("mul r1 r2 r3", regs(&[(2, 10), (3, 3)]), |cpu| cpu.regs[1] == 30),
+            case("mul r1 r2 4", regs(&[(2, 10)]), |cpu| cpu.regs[1] == 40),
+            case("div r1 r2 r3", regs(&[(2, 10), (3, 3)]), |cpu| cpu.regs[1] == 3),
+            case("div r1 r2 5", regs(&[(2, 10)]), |cpu| cpu.regs[1] == 2),
+            case("div r1 30 r2", regs(&[(2, 10)]), |cpu| cpu.regs[1] == 3),
+            case("sdiv r1 r2 r3", regs(&[(2, NEG_10), (3, 3)]), |cpu| cpu.regs[1] as i64 == -3),
+            case("sdiv r1 r2 65535", regs(&[(2, NEG_10)]), |cpu| cpu.regs[1] == 10),
+            case("sdiv r1 30 r2", regs(&[(2, NEG_10)]), |cpu| cpu.regs[1] as i64 == -3),
+
+            // Bitwise
+            case("and r1 r2 r3", regs(&[(2, 0b1100), (3, 0b1010)]), |cpu| cpu.regs[1] == 0b1000),
+            case("or r1 r2 r3", regs(&[(2, 0b1100), (3, 0b1010)]), |cpu| cpu.regs[1] == 0b1110),
+            case("xor r1 r2 r3", regs(&[(2, 0b1100), (3, 0b1010)]), |cpu| cpu.regs[1] == 0b0110),
+            case("nand r1 r2 r3", regs(&[(2, 0b1100), (3, 0b1010)]), |cpu| cpu.regs[1] == !0b1000),
+            case("nor r1 r2 r3", regs(&[(2, 0b1100), (3, 0b1010)]), |cpu| cpu.regs[1] == !0b1110),
+            case("xnor r1 r2 r3", regs(&[(2, 0b1100), (3, 0b1010)]), |cpu| cpu.regs[1] == !0b0110),
+            case("not r1 r2", regs(&[(2, 0b1100)]), |cpu| cpu.regs[1] == !0b1100),
+
+            // Shift & rotate
+            case("rsh r1 r2 r3", regs(&[(2, 0x10), (3, 2)]), |cpu| cpu.regs[1] == 0x4),
+            case("rsh r1 r2 4", regs(&[(2, 0x10)]), |cpu| cpu.regs[1] == 0x1),
+            case("lsh r1 r2 r3", regs(&[(2, 0x10), (3, 2)]), |cpu| cpu.regs[1] == 0x40),
+            case("lsh r1 r2 63", regs(&[(2, 1)]), |cpu| cpu.regs[1] == 1 << 63),
+            case("rrol r1 r2 r3", regs(&[(2, 0b11), (3, 1)]), |cpu| cpu.regs[1] == 1 << 63 | 1),
+            case("rrol r1 r2 2", regs(&[(2, 0b11)]), |cpu| cpu.regs[1] == 0b11 << 62),
+            case("lroll r1 r2 r3", regs(&[(2, 1 << 63), (3, 1)]), |cpu| cpu.regs[1] == 1),
+            case("lroll r1 r2 4", regs(&[(2, 1 << 63)]), |cpu| cpu.regs[1] == 0b1000),
+
+            // Data movement, memory, stack
+            case("mov r1 r2", regs(&[(2, 42)]), |cpu| cpu.regs[1] == 42),
+            case("ldi r1 4660 2", regs(&[(1, 0xFFFF)]), |cpu| cpu.regs[1] == 4660 << 32 | 0xFFFF),
+            case("ldr r1 r2 1", |cpu| { cpu.regs[2] = 300; cpu.memory[300] = 0xAB }, |cpu| cpu.regs[1] == 0xAB00),
+            case("ldr r1 300 7", |cpu| cpu.memory[300] = 0xCD, |cpu| cpu.regs[1] == 0xCD << 56),
+            case("str r1 r2 1", regs(&[(1, 0xAB00), (2, 300)]), |cpu| cpu.memory[300] == 0xAB),
+            case("str r1 300 0", regs(&[(1, 0xCD)]), |cpu| cpu.memory[300] == 0xCD),
+            case("push r2\npop r1", regs(&[(2, u64::MAX - 1)]), |cpu| cpu.regs[1] == u64::MAX - 1),
+
+            // Comparison, observed through the branch taken
+            case("cmp r1 r2\nbg 2\nhlt\nadd r3 r3 1", regs(&[(1, 10), (2, 3)]), |cpu| cpu.regs[3] == 1),
+            case("cmp r1 5\nbe 2\nhlt\nadd r3 r3 1", regs(&[(1, 5)]), |cpu| cpu.regs[3] == 1),
+            case("cmp 5 r1\nbs 2\nhlt\nadd r3 r3 1", regs(&[(1, 10)]), |cpu| cpu.regs[3] == 1),
+            case("scmp r1 r2\nbs 2\nhlt\nadd r3 r3 1", regs(&[(1, u64::MAX), (2, 3)]), |cpu| cpu.regs[3] == 1),
+            case("scmp r1 65535\nbg 2\nhlt\nadd r3 r3 1", regs(&[(1, 0)]), |cpu| cpu.regs[3] == 1),
+            case("scmp 65535 r1\nbs 2\nhlt\nadd r3 r3 1", regs(&[(1, 0)]), |cpu| cpu.regs[3] == 1),
+            case("fcmp r1 r2\nbs 2\nhlt\nadd r3 r3 1", |cpu| { cpu.regs[1] = f(-1.5); cpu.regs[2] = f(0.5) }, |cpu| cpu.regs[3] == 1),
+            case("dcmp r1 r2\nbg 2\nhlt\nadd r3 r3 1", |cpu| { cpu.regs[1] = d(2.5); cpu.regs[2] = d(-0.5) }, |cpu| cpu.regs[3] == 1),
+
+            // Conversion
+            case("immtof r1 3", regs(&[]), |cpu| cpu.regs[1] == f(3.0)),
+            case("immtod r1 65535", regs(&[]), |cpu| cpu.regs[1] == d(-1.0)),
+            case("itof r1", regs(&[(1, NEG_10)]), |cpu| cpu.regs[1] == f(-10.0)),
+            case("itod r1", regs(&[(1, 7)]), |cpu| cpu.regs[1] == d(7.0)),
+            case("ftoi r1", |cpu| cpu.regs[1] = f(-2.5), |cpu| cpu.regs[1] as i64 == -2),
+            case("ftod r1", |cpu| cpu.regs[1] = f(1.5), |cpu| cpu.regs[1] == d(1.5)),
+            case("dtoi r1", |cpu| cpu.regs[1] = d(9.75), |cpu| cpu.regs[1] == 9),
+            case("dtof r1", |cpu| cpu.regs[1] = d(0.25), |cpu| cpu.regs[1] == f(0.25)),
+
+            // Subroutines and system
+            case("call 3\nhlt\nhlt\nadd r1 r1 1\nret", regs(&[]), |cpu| cpu.regs[1] == 1 && cpu.instruction_ptr() == 8),
+            case("call r2\nhlt\nadd r1 r1 1\nret", regs(&[(2, 2)]), |cpu| cpu.regs[1] == 1 && cpu.instruction_ptr() == 8),
+            case("call .sub\nhlt\n.sub\nadd r1 r1 1\nret", regs(&[]), |cpu| cpu.regs[1] == 1),
+            case("stv r1", regs(&[(1, 0x200)]), |cpu| cpu.trap_vector == Some(0x200)),
+            case("nop", regs(&[]), |cpu| cpu.instruction_ptr() == 8),
+        ]
+    }
+
+    type Registers = &'static [(usize, u64)];
+
+    /// How many registers a floating point operation takes
+    #[derive(Copy, Clone)]
+    enum FloatOperands {
+        Binary, // Dest, src1, src2
+        Unary,  // Dest, src
+        None,   // Dest only
+    }
+
+    fn branch_cases() -> Vec<Case> {
+        // Each condition together with register values for `cmp r4 r5` that make it true
+        let conditions: [(&str, &str, Registers); 7] = [
+            ("jmp",   "b",   &[]),
+            ("jmpg",  "bg",  &[(4, 2), (5, 1)]),
+            ("jmpe",  "be",  &[(4, 1), (5, 1)]),
+            ("jmps",  "bs",  &[(4, 1), (5, 2)]),
+            ("jmpge", "bge", &[(4, 2), (5, 2)]),
+            ("jmpne", "bne", &[(4, 1), (5, 2)]),
+            ("jmpse", "bse", &[(4, 2), (5, 2)]),
+        ];
+
+        conditions.into_iter().flat_map(|(jump, branch, values)| {
+            // The branch is the second instruction and skips the two following ones
+            let targets = [
+                format!("{} r6", jump),
+                format!("{} .target", jump),
+                format!("{} .target", branch),
+                format!("{} 3", branch),
+            ];
+
+            targets.into_iter().map(move |target| Case {
+                source: format!("cmp r4 r5\n{}\nadd r1 r1 1\nhlt\n.target\nadd r2 r2 1", target),
+                setup: Box::new(move |cpu: &mut Cpu| {
+                    regs(values)(cpu);
+                    cpu.regs[6] = 3;
+                }),
+                check: Box::new(|cpu: &Cpu| cpu.regs[1] == 0 && cpu.regs[2] == 1),
+            })
+        }).collect()
+    }
+
+    fn floating_cases(prefix: &'static str, is_double: bool) -> Vec<Case> {
+        type Op = fn(f64, f64) -> f64;
+
+        let operations: [(&str, FloatOperands, Op); 32] = {
+            use FloatOperands::*;
+            [
+                ("add", Binary, |b, c| b + c), ("sub", Binary, |b, c| b - c), ("mul", Binary, |b, c| b * c),
+                ("div", Binary, |b, c| b / c), ("mod", Binary, |b, c| b % c), ("neg", Unary, |b, _| -b),
+                ("rec", Unary, |b, _| 1.0 / b), ("pow", Binary, |b, c| b.powf(c)), ("exp", Unary, |b, _| b.exp()),
+                ("root", Binary, |b, c| b.powf(1.0 / c)), ("sqrt", Unary, |b, _| b.sqrt()), ("cbrt", Unary, |b, _| b.powf(1.0 / 3.0)),
+                ("sq", Unary, |b, _| b * b), ("cube", Unary, |b, _| b * b * b), ("log", Binary, |b, c| b.log(c)),
+                ("ln", Unary, |b, _| b.ln()), ("abs", Unary, |b, _| b.abs()), ("sin", Unary, |b, _| b.sin()),
+                ("cos", Unary, |b, _| b.cos()), ("tan", Unary, |b, _| b.tan()), ("asin", Unary, |b, _| b.asin()),
+                ("acos", Unary, |b, _| b.acos()), ("atan", Unary, |b, _| b.atan()), ("floor", Unary, |b, _| b.floor()),
+                ("ceil", Unary, |b, _| b.ceil()), ("round", Unary, |b, _| b.round()), ("min", Binary, |b, c| b.min(c)),
+                ("max", Binary, |b, c| b.max(c)), ("sign", Unary, |b, _| b.signum()), ("diff", Binary, |b, c| (b - c).abs()),
+                ("inf", None, |_, _| f64::INFINITY), ("nan", None, |_, _| f64::NAN),
+            ]
+        };
+
+        const B: f64 = 0.75;
+        const C: f64 = 2.0;
+
+        operations.into_iter().map(|(name, operands, op)| {
+            let source = match operands {
+                FloatOperands::Binary => format!("{}{} r1 r2 r3", prefix, name),
+                FloatOperands::Unary => format!("{}{} r1 r2", prefix, name),
+                FloatOperands::None => format!("{}{} r1", prefix, name),
+            };
+
+            let expected = op(B, C);
+
+            Case {
+                source,
+                setup: Box::new(move |cpu: &mut Cpu| {
+                    cpu.regs[1] = 0x1234;
+                    cpu.regs[2] = if is_double { d(B) } else { f(B as f32) };
+                    cpu.regs[3] = if is_double { d(C) } else { f(C as f32) };
+                }),
+                check: Box::new(move |cpu: &Cpu| {
+                    let got = if is_double { f64::from_bits(cpu.regs[1]) } else { f32::from_bits(cpu.regs[1] as u32) as f64 };
+                    (got.is_nan() && expected.is_nan()) || got == expected || (got - expected).abs() < 1e-6
+                }),
+            }
+        }).collect()
+    }
+
+    fn all_cases() -> Vec<Case> {
+        let mut cases = integer_cases();
+        cases.append(&mut branch_cases());
+        cases.append(&mut floating_cases("f", false));
+        cases.append(&mut floating_cases("d", true));
+        cases
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for case in all_cases() {
+            let cpu = run(&case);
+            assert!((case.check)(&cpu), "`{}` produced registers {:?}", case.source, cpu.regs);
+        }
+    }
+
+    #[test]
+    fn test_every_pattern_is_covered() {
+        let mut used = Vec::new();
+
+        for case in all_cases() {
+            for line in case.source.lines().chain(["hlt"]) {
+                let tokens = Tokenizer::new(line.to_string()).tokenize().unwrap().into_iter()
+                    .map(|raw| AmbiguousToken::from(Token::try_from(raw).unwrap().variant))
+                    .collect::<Vec<_>>();
+
+                if let Some(pattern) = find_matching_pattern(&tokens) {
+                    used.push(pattern);
+                }
+            }
+        }
+
+        let expected = [
+            Opcode(Opc::Breakpoint), Opcode(Opc::ReturnFromTrap),
+        ];
+
+        for pattern in get_patterns() {
+            // Breakpoints and trap returns stop or redirect execution, they are covered by the CPU tests
+            if expected.contains(&pattern.expected_tokens[0]) {
+                continue;
+            }
+            assert!(used.contains(&&pattern), "No round trip test for {:?}", pattern.expected_tokens);
         }
-    ]
+    }
 }
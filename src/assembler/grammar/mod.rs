@@ -3,4 +3,6 @@ mod bit_run_length_coding;
 pub mod token_pattern;
 mod bit_push;
 mod get_patterns;
+mod pattern_index;
 mod encoding;
+pub mod expression;
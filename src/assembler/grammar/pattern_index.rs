@@ -0,0 +1,109 @@
+use super::get_patterns::get_patterns;
+use super::token_pattern::{AmbiguousToken, TokenPattern};
+use crate::assembler::types::opcode::Opcode;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// The kinds of operands patterns tell apart.
+/// Any literal or expression fits any immediate, whether it fits is checked against the field when encoding.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum Shape {
+    Register,
+    Immediate,
+    Label,
+    Bool,
+}
+
+impl Shape {
+    /// `None` for tokens no pattern takes as an operand
+    fn of(token: &AmbiguousToken) -> Option<Shape> {
+        match token {
+            AmbiguousToken::Register => Some(Shape::Register),
+            AmbiguousToken::Unsigned | AmbiguousToken::Signed | AmbiguousToken::Expression => Some(Shape::Immediate),
+            AmbiguousToken::Label => Some(Shape::Label),
+            AmbiguousToken::Bool => Some(Shape::Bool),
+            _ => None,
+        }
+    }
+}
+
+/// Token patterns looked up by opcode and the shape of the operands
+#[derive(Debug)]
+pub struct PatternIndex {
+    patterns: Vec<TokenPattern>,
+    by_shape: HashMap<(Opcode, Vec<Shape>), usize>,
+}
+
+impl PatternIndex {
+    /// Fails if a line could match two of the patterns
+    pub fn new(patterns: Vec<TokenPattern>) -> Result<Self, String> {
+        let mut by_shape = HashMap::with_capacity(patterns.len());
+
+        for (i, pattern) in patterns.iter().enumerate() {
+            let key = key(&pattern.expected_tokens).ok_or_else(|| format!("Pattern {:?} can't be matched", pattern.expected_tokens))?;
+
+            if let Some(other) = by_shape.insert(key, i) {
+                return Err(format!("Patterns {:?} and {:?} match the same lines", patterns[other].expected_tokens, pattern.expected_tokens));
+            }
+        }
+
+        Ok(Self { patterns, by_shape })
+    }
+
+    /// The pattern a line matches
+    pub fn find(&self, tokens: &[AmbiguousToken]) -> Option<&TokenPattern> {
+        self.by_shape.get(&key(tokens)?).map(|&i| &self.patterns[i])
+    }
+
+    /// Every pattern of an opcode
+    pub fn forms(&self, opcode: Opcode) -> impl Iterator<Item = &TokenPattern> {
+        self.patterns.iter().filter(move |pattern| pattern.expected_tokens[0] == AmbiguousToken::Opcode(opcode))
+    }
+}
+
+fn key(tokens: &[AmbiguousToken]) -> Option<(Opcode, Vec<Shape>)> {
+    let (AmbiguousToken::Opcode(opcode), operands) = tokens.split_first()? else {
+        return None;
+    };
+
+    Some((*opcode, operands.iter().map(Shape::of).collect::<Option<_>>()?))
+}
+
+/// The patterns of every instruction form, built on first use
+pub fn patterns() -> &'static PatternIndex {
+    static PATTERNS: OnceLock<PatternIndex> = OnceLock::new();
+
+    PATTERNS.get_or_init(|| PatternIndex::new(get_patterns()).expect("The ISA table has ambiguous forms"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isa_is_unambiguous() {
+        assert!(PatternIndex::new(get_patterns()).is_ok());
+    }
+
+    #[test]
+    fn test_ambiguity_is_detected() {
+        let mut patterns = get_patterns();
+        let mut signed = patterns.iter().find(|pattern| pattern.expected_tokens.last() == Some(&AmbiguousToken::Unsigned)).unwrap().clone();
+        *signed.expected_tokens.last_mut().unwrap() = AmbiguousToken::Signed;
+        patterns.push(signed);
+
+        assert!(PatternIndex::new(patterns).unwrap_err().contains("match the same lines"));
+    }
+
+    #[test]
+    fn test_find() {
+        let index = patterns();
+        let add = |operands: &[AmbiguousToken]| index.find(&[&[AmbiguousToken::Opcode(Opcode::Add)], operands].concat()).map(|pattern| pattern.expected_tokens.clone());
+
+        assert_eq!(add(&[AmbiguousToken::Register, AmbiguousToken::Register, AmbiguousToken::Expression]), add(&[AmbiguousToken::Register, AmbiguousToken::Register, AmbiguousToken::Signed]));
+        assert!(add(&[AmbiguousToken::Register, AmbiguousToken::Register, AmbiguousToken::Register]).is_some());
+        assert!(add(&[AmbiguousToken::Register, AmbiguousToken::Register, AmbiguousToken::String]).is_none());
+        assert!(add(&[AmbiguousToken::Register]).is_none());
+        assert_eq!(index.forms(Opcode::Add).count(), 2);
+    }
+}
@@ -10,37 +10,58 @@ pub struct TokenPattern {
     pub encoding: Encoding,
 }
 
-impl TokenPattern {
-    pub fn matches(&self, tokens: &[AmbiguousToken]) -> bool {
-        if tokens.len() != self.expected_tokens.len() {
-            return false;
-        }
-
-        tokens.iter()
-            .zip(&self.expected_tokens)
-            .all(|(a, b)| a == b)
-    }
-}
-
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum AmbiguousToken {
     Opcode(Opcode),
+    Pseudo,
     Register,
     Unsigned, // Unsigned 16-bit immediate
     Signed,   // Signed 16-bit immediate
     Label,    // For branch targets
     Bool,     // For signed/unsigned comparisons
+    Directive,
+    String,
+    Symbol,
+    Operator,
+    Expression,
+    Colon,
+}
+
+impl AmbiguousToken {
+    /// Short name of the kind of operand, for explaining which operands an instruction takes
+    pub fn describe(&self) -> &'static str {
+        match self {
+            AmbiguousToken::Opcode(_) => "opcode",
+            AmbiguousToken::Pseudo => "pseudo-instruction",
+            AmbiguousToken::Register => "reg",
+            AmbiguousToken::Unsigned | AmbiguousToken::Signed | AmbiguousToken::Expression => "imm",
+            AmbiguousToken::Label => "label",
+            AmbiguousToken::Bool => "bool",
+            AmbiguousToken::Directive => "directive",
+            AmbiguousToken::String => "string",
+            AmbiguousToken::Symbol => "name",
+            AmbiguousToken::Operator => "operator",
+            AmbiguousToken::Colon => "':'",
+        }
+    }
 }
 
 impl From<&TokenVariant> for AmbiguousToken {
     fn from(value: &TokenVariant) -> Self {
         match value {
             TokenVariant::Opcode(opc) => AmbiguousToken::Opcode(*opc),
+            TokenVariant::Pseudo(_) => AmbiguousToken::Pseudo,
+            TokenVariant::Directive(_) => AmbiguousToken::Directive,
             TokenVariant::Label(_) => AmbiguousToken::Label,
+            TokenVariant::String(_) => AmbiguousToken::String,
+            TokenVariant::Symbol(_) => AmbiguousToken::Symbol,
+            TokenVariant::Operator(_) => AmbiguousToken::Operator,
+            TokenVariant::Expression(_) => AmbiguousToken::Expression,
             TokenVariant::Unsigned(_) => AmbiguousToken::Unsigned,
             TokenVariant::Signed(_) => AmbiguousToken::Signed,
             TokenVariant::Register(_) => AmbiguousToken::Register,
             TokenVariant::Bool(_) => AmbiguousToken::Bool,
+            TokenVariant::Colon => AmbiguousToken::Colon,
         }
     }
 }
@@ -49,11 +70,18 @@ impl From<TokenVariant> for AmbiguousToken {
     fn from(val: TokenVariant) -> Self {
         match val {
             TokenVariant::Opcode(opc) => AmbiguousToken::Opcode(opc),
+            TokenVariant::Pseudo(_) => AmbiguousToken::Pseudo,
+            TokenVariant::Directive(_) => AmbiguousToken::Directive,
             TokenVariant::Label(_) => AmbiguousToken::Label,
+            TokenVariant::String(_) => AmbiguousToken::String,
+            TokenVariant::Symbol(_) => AmbiguousToken::Symbol,
+            TokenVariant::Operator(_) => AmbiguousToken::Operator,
+            TokenVariant::Expression(_) => AmbiguousToken::Expression,
             TokenVariant::Unsigned(_) => AmbiguousToken::Unsigned,
             TokenVariant::Signed(_) => AmbiguousToken::Signed,
             TokenVariant::Register(_) => AmbiguousToken::Register,
             TokenVariant::Bool(_) => AmbiguousToken::Bool,
+            TokenVariant::Colon => AmbiguousToken::Colon,
         }
     }
 }
@@ -0,0 +1,140 @@
+use crate::assembler::{
+    assemble::{collect_into_lines, tokenize},
+    assembly_error::{AssemblyError, AssemblyErrorVariant},
+    position::SourceMap,
+    tokenization::token::{Token, TokenVariant},
+    types::directive::Directive,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Replaces `.include "path"` lines with the lines of the included file.
+/// Paths are relative to the including file. A file can be included several times, but not while it's being included.
+/// The text of every included file is added to `sources`.
+pub fn expand_includes(lines: Vec<Vec<Token>>, file: Option<&Rc<Path>>, sources: &mut SourceMap, errors: &mut Vec<AssemblyError>) -> Vec<Vec<Token>> {
+    let open = file.and_then(|file| fs::canonicalize(file).ok()).into_iter().collect();
+
+    Includer { open, sources }.expand(lines, file, errors)
+}
+
+struct Includer<'a> {
+    /// Canonical paths of the files currently being included
+    open: Vec<PathBuf>,
+    sources: &'a mut SourceMap,
+}
+
+impl Includer<'_> {
+    fn expand(&mut self, lines: Vec<Vec<Token>>, file: Option<&Rc<Path>>, errors: &mut Vec<AssemblyError>) -> Vec<Vec<Token>> {
+        let mut output = Vec::with_capacity(lines.len());
+
+        for line in lines {
+            if line[0].variant != TokenVariant::Directive(Directive::Include) {
+                output.push(line);
+                continue;
+            }
+
+            match self.include(&line, file, errors) {
+                Ok(lines) => output.extend(lines),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        output
+    }
+
+    /// The lines of the file an `.include` line names
+    fn include(&mut self, line: &[Token], file: Option<&Rc<Path>>, errors: &mut Vec<AssemblyError>) -> Result<Vec<Vec<Token>>, AssemblyError> {
+        let error = |variant| AssemblyError::spanning(line, variant);
+
+        let [_, Token { variant: TokenVariant::String(path), .. }] = line else {
+            return Err(error(AssemblyErrorVariant::WrongArguments { directive: Directive::Include, expected: "a file path" }));
+        };
+
+        let path = match file.and_then(|file| file.parent()) {
+            Some(directory) => directory.join(path),
+            None => PathBuf::from(path),
+        };
+        let unreadable = |err: std::io::Error| error(AssemblyErrorVariant::UnreadableFile { path: path.clone(), reason: err.to_string() });
+
+        let canonical = fs::canonicalize(&path).map_err(unreadable)?;
+        if self.open.contains(&canonical) {
+            return Err(error(AssemblyErrorVariant::IncludeCycle { path: path.clone() }));
+        }
+
+        let text = fs::read_to_string(&path).map_err(unreadable)?;
+        let included: Rc<Path> = Rc::from(path.as_path());
+        self.sources.insert(Some(included.clone()), text.clone());
+        let lines = collect_into_lines(tokenize(text, Some(&included), errors));
+
+        self.open.push(canonical);
+        let lines = self.expand(lines, Some(&included), errors);
+        self.open.pop();
+
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble::{assemble, assemble_source, Options};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// A fresh directory with the given files in it
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("bitcpu-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        for (path, text) in files {
+            let path = directory.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+
+        directory
+    }
+
+    fn assemble_main(directory: &Path) -> Result<Vec<u8>, String> {
+        let path = directory.join("main.asm");
+        assemble_source(fs::read_to_string(&path).unwrap(), Some(&path), &Options::default())
+            .map(|assembly| assembly.image)
+            .map_err(|diagnostics| diagnostics[0].error.to_string())
+    }
+
+    #[test]
+    fn test_include() {
+        let directory = directory("include", &[
+            ("main.asm", ".include \"lib/macros.asm\"\n.start\nstart\n.include \"lib/nop.asm\"\n.include \"lib/nop.asm\"\nb .start"),
+            ("lib/macros.asm", ".include \"constants.asm\"\n.macro start\nadd r1 r1 BASE\n.endm"),
+            ("lib/constants.asm", ".equ BASE 0x10"),
+            ("lib/nop.asm", "nop"),
+        ]);
+        let expected = assemble("add r1 r1 16\nnop\nnop\nb -3".to_string()).unwrap();
+
+        assert_eq!(assemble_main(&directory).unwrap(), expected.iter().flat_map(|word| word.to_be_bytes()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_errors() {
+        let directory = directory("include-errors", &[
+            ("main.asm", "nop\n.include \"a.asm\""),
+            ("a.asm", "nop\n\n.include \"b.asm\""),
+            ("b.asm", ".include \"a.asm\""),
+            ("bad.asm", "nop\nfoo r1"),
+        ]);
+        let file = |name: &str| directory.join(name).display().to_string();
+
+        assert_eq!(assemble_main(&directory).unwrap_err(), format!("{} line 1: {} includes itself", file("b.asm"), file("a.asm")));
+
+        for (source, expected) in [
+            (".include \"bad.asm\"", format!("{} line 2: Unrecognized opcode foo", file("bad.asm"))),
+            (".include \"missing.asm\"", format!("{} line 1: Can't read {}: No such file or directory (os error 2)", file("main.asm"), file("missing.asm"))),
+            ("\n.include 5", format!("{} line 2: .include expects a file path", file("main.asm"))),
+            (".include \"main.asm\"", format!("{} line 1: {} includes itself", file("main.asm"), file("main.asm"))),
+        ] {
+            fs::write(directory.join("main.asm"), source).unwrap();
+            assert_eq!(assemble_main(&directory).unwrap_err(), expected, "{}", source);
+        }
+    }
+}
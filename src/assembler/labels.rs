@@ -0,0 +1,236 @@
+use crate::assembler::{
+    assembly_error::{AssemblyError, AssemblyErrorVariant},
+    grammar::expression::Expression,
+    macros::locate,
+    tokenization::raw_token::is_numeric_label_reference,
+    tokenization::token::{Token, TokenVariant},
+};
+use std::collections::HashMap;
+
+/// Gives local and numeric labels the names they have in the symbol table.
+/// `..name` belongs to the label before it, after `.outer` it's `.outer.name`.
+/// `N:` defines the numeric label `N`, which `Nb` and `Nf` refer to, the closest definition before or after them.
+pub fn resolve_labels(lines: Vec<Vec<Token>>, errors: &mut Vec<AssemblyError>) -> Vec<Vec<Token>> {
+    let mut resolver = Resolver { numeric: HashMap::new(), scope: None };
+
+    // Line indices of the definitions of each numeric label
+    for (i, tokens) in lines.iter().enumerate() {
+        if let Some(number) = numeric_definition(tokens) {
+            resolver.numeric.entry(number).or_default().push(i);
+        }
+    }
+
+    let mut resolved = Vec::with_capacity(lines.len());
+
+    for (i, tokens) in lines.into_iter().enumerate() {
+        let first = tokens[0].clone();
+
+        match resolver.line(tokens, i) {
+            Ok(tokens) => resolved.push(tokens),
+            Err(err) => errors.push(locate(err, &first)),
+        }
+    }
+
+    resolved
+}
+
+struct Resolver {
+    numeric: HashMap<u64, Vec<usize>>,
+    /// The last label that wasn't local
+    scope: Option<String>,
+}
+
+impl Resolver {
+    fn line(&mut self, tokens: Vec<Token>, line: usize) -> Result<Vec<Token>, AssemblyError> {
+        if let Some(number) = numeric_definition(&tokens) {
+            let definition = self.numeric[&number].iter().position(|&i| i == line).unwrap();
+
+            return Ok(vec![Token {
+                variant: TokenVariant::Label(numeric_name(number, definition)),
+                range: tokens[0].range.start..tokens[1].range.end,
+                ..tokens[0].clone()
+            }]);
+        }
+
+        // Labels of macro expansions don't start a scope, so the labels after an invocation still belong to the label before it
+        let scope = match &tokens[0].variant {
+            TokenVariant::Label(name) if !name.starts_with("..") && !name.contains('@') => Some(name.to_owned()),
+            _ => None,
+        };
+
+        let mut tokens = tokens;
+
+        for token in &mut tokens {
+            let variant = match &token.variant {
+                TokenVariant::Label(name) => TokenVariant::Label(self.resolve(name, line).map_err(|variant| AssemblyError::at(token, variant))?),
+                TokenVariant::Expression(expression) => {
+                    let mut expression = expression.clone();
+                    let mut result = Ok(());
+
+                    expression.for_each_label(&mut |name| {
+                        if result.is_ok() {
+                            result = self.resolve(name, line).map(|resolved| *name = resolved);
+                        }
+                    });
+
+                    result.map_err(|variant| AssemblyError::at(token, variant))?;
+                    TokenVariant::Expression(expression)
+                }
+                _ => continue,
+            };
+
+            token.variant = variant;
+        }
+
+        if scope.is_some() {
+            self.scope = scope;
+        }
+
+        Ok(tokens)
+    }
+
+    fn resolve(&self, name: &str, line: usize) -> Result<String, AssemblyErrorVariant> {
+        if let Some(local) = name.strip_prefix('.').filter(|name| name.starts_with('.')) {
+            return match &self.scope {
+                Some(scope) => Ok(format!("{}{}", scope, local)),
+                None => Err(AssemblyErrorVariant::NoEnclosingLabel { name: name.to_owned() }),
+            };
+        }
+
+        if !is_numeric_label_reference(name) {
+            return Ok(name.to_owned());
+        }
+
+        let (digits, direction) = name.split_at(name.len() - 1);
+        let definitions = digits.parse().ok().and_then(|number| self.numeric.get(&number).map(|definitions| (number, definitions)));
+
+        let found = definitions.and_then(|(number, definitions)| {
+            let definition = match direction {
+                "b" => definitions.iter().rposition(|&i| i < line),
+                _ => definitions.iter().position(|&i| i > line),
+            };
+            definition.map(|definition| numeric_name(number, definition))
+        });
+
+        found.ok_or_else(|| AssemblyErrorVariant::NoLabelFound { name: name.to_owned(), suggestion: None })
+    }
+}
+
+/// The number of a line like `1:`
+fn numeric_definition(tokens: &[Token]) -> Option<u64> {
+    match tokens {
+        [Token { variant: TokenVariant::Unsigned(number), .. }, Token { variant: TokenVariant::Colon, .. }] => Some(*number),
+        _ => None,
+    }
+}
+
+/// Name of the `definition`th definition of a numeric label. Like the labels of macro expansions, it can't be written in the source.
+fn numeric_name(number: u64, definition: usize) -> String {
+    format!("{}@{}", number, definition)
+}
+
+/// Names of the labels a token refers to, in the order they're written
+pub fn label_names(token: &Token) -> Vec<String> {
+    let mut names = Vec::new();
+
+    match &token.variant {
+        TokenVariant::Label(name) => names.push(name.to_owned()),
+        TokenVariant::Expression(expression) => expression.clone().for_each_label(&mut |name| names.push(name.to_owned())),
+        _ => {}
+    }
+
+    names
+}
+
+impl Expression {
+    /// Calls `f` with the name of every label the expression refers to
+    fn for_each_label(&mut self, f: &mut impl FnMut(&mut String)) {
+        match self {
+            Expression::Label(name) => f(name),
+            Expression::Number(_) | Expression::Constant(_) => {}
+            Expression::Unary(_, operand) | Expression::Function(_, operand) => operand.for_each_label(f),
+            Expression::Binary(_, left, right) => {
+                left.for_each_label(f);
+                right.for_each_label(f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble::{assemble, assemble_image};
+
+    #[test]
+    fn test_names() {
+        let source = ".loop_1\n    b .copy2\n.copy2\n    b .loop_1";
+        assert_eq!(assemble(source.to_string()).unwrap(), assemble(".a\n    b .b\n.b\n    b .a".to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_local_labels() {
+        let source = "
+            .first
+                b ..done
+            ..done
+                b ..done
+            .second
+                b ..done
+                b .first.done
+            ..done
+                hlt
+        ";
+        let expected = "
+            .first
+                b .c
+            .c
+                b .c
+            .second
+                b .d
+                b .c
+            .d
+                hlt
+        ";
+
+        assert_eq!(assemble(source.to_string()).unwrap(), assemble(expected.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_numeric_labels() {
+        let source = "
+            1:
+                b 1f
+            1:
+                b 1b
+                b 2f
+                .word 1b
+            2:
+                hlt
+        ";
+        let expected = "
+            .a
+                b .b
+            .b
+                b .b
+                b .c
+                .word .b
+            .c
+                hlt
+        ";
+
+        assert_eq!(assemble(source.to_string()).unwrap(), assemble(expected.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        for (source, message) in [
+            ("..inner\nhlt", "Line 1: Local label ..inner doesn't belong to any label, there is none before it"),
+            ("1:\nb 1f", "Line 2: No label named 1f found"),
+            ("b 3b\n3:\nhlt", "Line 1: No label named 3b found"),
+            (".a\n.a\nhlt", "Line 2: Label .a is already defined"),
+            (".a\n..b\n.a.b\nhlt", "Line 3: Label .a.b is already defined"),
+        ] {
+            assert_eq!(assemble_image(source.to_string()).unwrap_err().to_string(), message);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::assembler::assemble::{Assembly, PlacementKind};
+use crate::isa;
+use std::fmt::Write;
+
+/// Width of the encoding column, fits an instruction word with a space between each of its fields
+const ENCODING_WIDTH: usize = 40;
+
+/// Data bytes shown per row
+const BYTES_PER_ROW: usize = 4;
+
+/// A listing of an assembled program. Each row shows an address, the bits written there and the source line they come from.
+/// Instruction words are grouped by the fields of their form, data is shown as bytes.
+/// Lines referring to labels are followed by the addresses of those labels.
+pub fn listing(assembly: &Assembly) -> String {
+    let mut text = String::new();
+
+    let mut placements = assembly.placements.iter().peekable();
+
+    while let Some(placement) = placements.next() {
+        // A label before an instruction on the same line is shown by the instruction's row
+        if matches!(placement.kind, PlacementKind::Label(_)) && placements.peek().is_some_and(|next| next.position == placement.position) {
+            continue;
+        }
+
+        let bytes = &assembly.image[placement.address..placement.address + placement.length];
+
+        let encodings = match placement.kind {
+            PlacementKind::Label(_) => Vec::new(),
+            PlacementKind::Code => bytes.chunks(4).map(|word| fields(u32::from_be_bytes(word.try_into().unwrap()))).collect(),
+            PlacementKind::Data => bytes.chunks(BYTES_PER_ROW).map(|row| row.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")).collect(),
+        };
+
+        let mut source = assembly.sources.line(&placement.position).unwrap_or_default().trim().to_string();
+
+        if !placement.targets.is_empty() {
+            let targets = placement.targets.iter().map(|(name, address)| format!("{} = {:#x}", name, address)).collect::<Vec<_>>();
+            write!(source, "    -> {}", targets.join(", ")).unwrap();
+        }
+
+        // Lines without bytes, like labels and `.org`, still show where they are
+        let step = if placement.kind == PlacementKind::Code { 4 } else { BYTES_PER_ROW };
+        let rows = encodings.len().max(1);
+
+        for row in 0..rows {
+            let encoding = encodings.get(row).map_or("", String::as_str);
+            let source = if row == 0 { source.as_str() } else { "" };
+            writeln!(text, "{:#010x}  {:<width$}  {}", placement.address + row * step, encoding, source, width = ENCODING_WIDTH).unwrap();
+        }
+    }
+
+    // Trailing spaces of rows without source
+    text.lines().map(str::trim_end).fold(String::new(), |mut listing, line| {
+        listing.push_str(line);
+        listing.push('\n');
+        listing
+    })
+}
+
+/// The bits of an instruction word, grouped by the fields of its form. Fixed bits are grouped in fours like in the layouts.
+fn fields(word: u32) -> String {
+    let layout = isa::decode(word).map_or("0000 0000 0000 0000 0000 0000 0000 0000", |decoded| decoded.form.bits);
+
+    let fixed = |ch: char| ch == '0' || ch == '1';
+
+    let mut text = String::new();
+    let mut previous: Option<char> = None;
+    let mut nibble_ended = false;
+    let mut bit = 32;
+
+    for ch in layout.chars() {
+        if ch == ' ' {
+            nibble_ended = true;
+            continue;
+        }
+
+        if let Some(previous) = previous {
+            let same_field = if fixed(ch) { fixed(previous) && !nibble_ended } else { previous == ch };
+            if !same_field {
+                text.push(' ');
+            }
+        }
+
+        bit -= 1;
+        text.push(if word >> bit & 1 == 1 { '1' } else { '0' });
+
+        previous = Some(ch);
+        nibble_ended = false;
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble::{assemble_source, Options};
+
+    #[test]
+    fn test_fields() {
+        assert_eq!(fields(0x1123_0002), "0001 0001 0010 0011 0000 0000 0000 0010");
+        assert_eq!(fields(0x6FFF_C001), "0110 1111111111111100 0000 0000 0001");
+        // Words that aren't instructions are shown in fours
+        assert_eq!(fields(0xF000_0000), "1111 0000 0000 0000 0000 0000 0000 0000");
+    }
+
+    #[test]
+    fn test_listing() {
+        let source = "\
+.start: li r1 0x10000
+.loop:
+    dec r1
+    bne .loop     ; back
+    hlt
+.data: .word .data 7
+";
+        let assembly = assemble_source(source.to_string(), None, &Options::default()).ok().unwrap();
+
+        assert_eq!(listing(&assembly), "\
+0x00000000  0010 0001 0001 0001 0000 0000 0000 0010   .start: li r1 0x10000
+0x00000004  0100 0001 0000000000000001 00 01 0001
+0x00000008                                            .loop:
+0x00000008  0001 0001 0001 0000000000000001 0011      dec r1
+0x0000000c  0110 1111111111111111 0000 0000 1011      bne .loop     ; back    -> .loop = 0x8
+0x00000010  1011 0000 0000 0000 0000 0000 0000 0011   hlt
+0x00000014  00 00 00 14                               .data: .word .data 7    -> .data = 0x14
+0x00000018  00 00 00 07
+");
+    }
+}
@@ -0,0 +1,293 @@
+use crate::assembler::{
+    assemble::split_labels,
+    position::Position,
+    assembly_error::{AssemblyError, AssemblyErrorVariant},
+    grammar::expression::group_operands,
+    tokenization::token::{Token, TokenVariant},
+    types::directive::Directive,
+};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::rc::Rc;
+
+/// How deeply macro invocations may nest
+const RECURSION_LIMIT: usize = 64;
+
+/// The macro invocation a token was produced by
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Expansion {
+    pub name: String,
+    /// Where the invocation is
+    pub position: Position,
+    /// Columns of the macro's name in the invocation
+    pub range: Range<usize>,
+    /// Numbers the expansions so labels inside macros are unique
+    pub id: usize,
+    /// The expansion the invocation itself is part of
+    pub parent: Option<Rc<Expansion>>,
+}
+
+#[derive(Debug)]
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<Vec<Token>>,
+    /// Labels defined in the body, renamed for every expansion
+    labels: HashSet<String>,
+}
+
+/// Removes macro definitions and replaces invocations with the macro's body.
+/// Macros have to be defined before they're used.
+pub fn expand_macros(lines: Vec<Vec<Token>>, errors: &mut Vec<AssemblyError>) -> Vec<Vec<Token>> {
+    let mut expander = Expander { macros: HashMap::new(), expansions: 0, output: Vec::with_capacity(lines.len()) };
+    let mut lines = lines.into_iter();
+
+    while let Some(line) = lines.next() {
+        let result = match &line[0].variant {
+            TokenVariant::Directive(Directive::Macro) => expander.define(line, &mut lines, errors),
+            TokenVariant::Directive(Directive::EndMacro) => Err(AssemblyError::spanning(&line, AssemblyErrorVariant::UnexpectedEndMacro)),
+            _ => expander.line(line, None, 0),
+        };
+
+        if let Err(err) = result {
+            errors.push(err);
+        }
+    }
+
+    expander.output
+}
+
+/// Wraps an error raised on a line produced by macros, so it names every invocation that led to it
+pub fn locate(error: AssemblyError, token: &Token) -> AssemblyError {
+    let mut error = error;
+    let mut expansion = token.expansion.as_deref();
+
+    while let Some(outer) = expansion {
+        error = AssemblyError {
+            position: outer.position.clone(),
+            span: Some(outer.range.clone()),
+            variant: AssemblyErrorVariant::InMacro { name: outer.name.clone(), error: Box::new(error) },
+        };
+        expansion = outer.parent.as_deref();
+    }
+
+    error
+}
+
+struct Expander {
+    macros: HashMap<String, Rc<Macro>>,
+    expansions: usize,
+    output: Vec<Vec<Token>>,
+}
+
+impl Expander {
+    /// Defines a macro from its header and the lines up to `.endm`
+    fn define(&mut self, header: Vec<Token>, lines: &mut impl Iterator<Item = Vec<Token>>, errors: &mut Vec<AssemblyError>) -> Result<(), AssemblyError> {
+        let error = |variant| AssemblyError::spanning(&header, variant);
+
+        let mut body = Vec::new();
+        let mut terminated = false;
+
+        for tokens in lines.by_ref() {
+            match &tokens[0].variant {
+                TokenVariant::Directive(Directive::EndMacro) => {
+                    terminated = true;
+                    break;
+                }
+                TokenVariant::Directive(Directive::Macro) => errors.push(AssemblyError::spanning(&tokens, AssemblyErrorVariant::NestedMacroDefinition)),
+                _ => body.push(tokens),
+            }
+        }
+
+        let names = header[1..].iter().map(|token| match &token.variant {
+            TokenVariant::Symbol(name) => Some(name.to_owned()),
+            _ => None,
+        }).collect::<Option<Vec<_>>>();
+
+        let Some((name, parameters)) = names.as_ref().and_then(|names| names.split_first()) else {
+            return Err(error(AssemblyErrorVariant::WrongArguments { directive: Directive::Macro, expected: "a name and parameter names" }));
+        };
+
+        if !terminated {
+            return Err(error(AssemblyErrorVariant::UnterminatedMacro { name: name.to_owned() }));
+        }
+
+        if self.macros.contains_key(name) {
+            return Err(error(AssemblyErrorVariant::MacroRedefined { name: name.to_owned() }));
+        }
+
+        let labels = body.iter().filter_map(|tokens| match &tokens[0].variant {
+            TokenVariant::Label(label) => Some(label.to_owned()),
+            _ => None,
+        }).collect();
+
+        self.macros.insert(name.to_owned(), Rc::new(Macro { parameters: parameters.to_vec(), body, labels }));
+
+        Ok(())
+    }
+
+    fn line(&mut self, tokens: Vec<Token>, parent: Option<&Rc<Expansion>>, depth: usize) -> Result<(), AssemblyError> {
+        let invoked = match &tokens[0].variant {
+            TokenVariant::Symbol(name) => self.macros.get(name).cloned(),
+            _ => None,
+        };
+
+        match invoked {
+            Some(invoked) => self.invoke(&invoked, tokens, parent, depth),
+            None => {
+                self.output.push(tokens);
+                Ok(())
+            }
+        }
+    }
+
+    fn invoke(&mut self, invoked: &Macro, tokens: Vec<Token>, parent: Option<&Rc<Expansion>>, depth: usize) -> Result<(), AssemblyError> {
+        let invocation = tokens[0].clone();
+        let TokenVariant::Symbol(name) = &invocation.variant else { unreachable!() };
+        let error = |variant| locate(AssemblyError::at(&invocation, variant), &invocation);
+
+        if depth >= RECURSION_LIMIT {
+            return Err(error(AssemblyErrorVariant::MacroRecursion { name: name.to_owned(), limit: RECURSION_LIMIT }));
+        }
+
+        let arguments = group_operands(tokens).map_err(|err| locate(err, &invocation))?.split_off(1);
+
+        if arguments.len() != invoked.parameters.len() {
+            return Err(error(AssemblyErrorVariant::MacroArguments { name: name.to_owned(), expected: invoked.parameters.len(), got: arguments.len() }));
+        }
+
+        self.expansions += 1;
+
+        let expansion = Rc::new(Expansion {
+            name: name.to_owned(),
+            position: invocation.position.clone(),
+            range: invocation.range.clone(),
+            id: self.expansions,
+            parent: parent.cloned(),
+        });
+
+        for body_line in &invoked.body {
+            let tokens = body_line.iter().map(|token| {
+                let variant = match &token.variant {
+                    TokenVariant::Symbol(symbol) => match invoked.parameters.iter().position(|parameter| parameter == symbol) {
+                        Some(i) => arguments[i].variant.clone(),
+                        None => token.variant.clone(),
+                    },
+                    TokenVariant::Label(label) if invoked.labels.contains(label) => TokenVariant::Label(format!("{}@{}", label, expansion.id)),
+                    variant => variant.clone(),
+                };
+
+                Token { variant, position: token.position.clone(), range: token.range.clone(), expansion: Some(expansion.clone()) }
+            }).collect::<Vec<_>>();
+
+            // An argument can be a label, which is only split from the rest of its line once it's in place
+            for line in split_labels(&tokens) {
+                self.line(line, Some(&expansion), depth + 1)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble::assemble;
+
+    #[test]
+    fn test_expansion() {
+        let source = "
+            .macro load64 reg value
+                ldi reg lo16(value) 0
+                ldi reg hi16(value) 1
+                ldi reg hi16(value >> 16) 2
+                ldi reg hi16(value >> 32) 3
+            .endm
+            .macro swap x y
+                push x
+                mov x y
+                pop y
+            .endm
+            load64 r1 0x1234_5678_9ABC_DEF0
+            swap r2 r3
+            swap r2 r3";
+        let expected = "
+            ldi r1 0xDEF0 0
+            ldi r1 0x9ABC 1
+            ldi r1 0x5678 2
+            ldi r1 0x1234 3
+            push r2
+            mov r2 r3
+            pop r3
+            push r2
+            mov r2 r3
+            pop r3";
+
+        assert_eq!(assemble(source.to_string()).unwrap(), assemble(expected.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_local_labels() {
+        let source = "
+            .macro wait reg
+            .loop
+                sub reg reg 1
+                cmp reg 0
+                bne .loop
+            .endm
+            .macro twice reg
+                wait reg
+                wait reg
+            .endm
+            twice r1
+            b .loop
+            .loop
+            hlt";
+        let expected = "
+            sub r1 r1 1
+            cmp r1 0
+            bne -2
+            sub r1 r1 1
+            cmp r1 0
+            bne -2
+            b 1
+            hlt";
+
+        assert_eq!(assemble(source.to_string()).unwrap(), assemble(expected.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_label_before_instruction() {
+        let source = "
+            .macro count reg name
+            .mymacro: sub reg reg 1
+                bne .mymacro
+            name: hlt
+            .endm
+            count r1 .done
+            b .done";
+        let expected = "
+            sub r1 r1 1
+            bne -1
+            hlt
+            b -1";
+
+        assert_eq!(assemble(source.to_string()).unwrap(), assemble(expected.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        for (source, expected) in [
+            (".macro m a\nadd r1 r1 a\n.endm\nnop\nm 0x10000", "Line 5: In expansion of macro m\nLine 2: Immediate doesn't fit into 16 bits. Range is 0 to 65535 but got 65536"),
+            (".macro inner\nfoo\n.endm\n.macro outer\ninner\n.endm\nouter", "Line 7: In expansion of macro outer\nLine 5: In expansion of macro inner\nLine 2: Unrecognized opcode foo"),
+            (".macro m x y\n.endm\nm r1", "Line 3: Macro m takes 2 arguments but got 1"),
+            (".macro m\nm\n.endm\nm", &format!("Line 4: In expansion of macro m\n{}Line 2: Macro m nests deeper than 64 invocations", "Line 2: In expansion of macro m\n".repeat(63))),
+            (".macro m\nnop", "Line 1: Macro m is never closed with .endm"),
+            (".endm", "Line 1: .endm without .macro"),
+            (".macro m\n.macro n\n.endm\n.endm", "Line 2: Macros can't be defined inside macros"),
+            (".macro m\n.endm\n.macro m\n.endm", "Line 3: Macro m is already defined"),
+            (".macro add\n.endm", "Line 1: .macro expects a name and parameter names"),
+        ] {
+            assert_eq!(assemble(source.to_string()).unwrap_err().to_string(), expected, "{}", source);
+        }
+    }
+}
@@ -1,6 +1,17 @@
 pub mod constructor;
 pub mod assemble;
 mod assembly_error;
+mod diagnostic;
+mod directives;
+mod includes;
+mod labels;
+pub mod listing;
+mod macros;
+mod pseudo;
+mod relaxation;
+pub mod relocation;
+mod position;
+mod suggest;
 mod grammar;
-mod types;
+pub mod types;
 mod tokenization;
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+/// A line of source code and the file it's in
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Position {
+    /// `None` for source that wasn't read from a file
+    pub file: Option<Rc<Path>>,
+    pub line: usize,
+}
+
+impl Position {
+    pub fn new(file: Option<Rc<Path>>, line: usize) -> Self {
+        Self { file, line }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{} line {}", file.display(), self.line + 1),
+            None => write!(f, "Line {}", self.line + 1),
+        }
+    }
+}
+
+/// The text of every file a program is assembled from
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: HashMap<Option<Rc<Path>>, String>,
+}
+
+impl SourceMap {
+    pub fn insert(&mut self, file: Option<Rc<Path>>, text: String) {
+        self.files.insert(file, text);
+    }
+
+    /// The text of a line, without its line break
+    pub fn line(&self, position: &Position) -> Option<&str> {
+        self.files.get(&position.file)?.lines().nth(position.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Position::new(None, 0).to_string(), "Line 1");
+        assert_eq!(Position::new(Some(Rc::from(Path::new("lib/io.asm"))), 4).to_string(), "lib/io.asm line 5");
+    }
+}
@@ -0,0 +1,200 @@
+use crate::assembler::{
+    assembly_error::{AssemblyError, AssemblyErrorVariant},
+    grammar::expression::Expression,
+    grammar::token_pattern::AmbiguousToken,
+    labels::label_names,
+    tokenization::token::{Token, TokenVariant},
+    types::opcode::Opcode,
+    types::pseudo::Pseudo,
+    types::register::Register,
+};
+
+/// Whether a line is a pseudo-instruction. `ret` with an operand is one too, it returns that value.
+pub fn is_pseudo(tokens: &[Token]) -> bool {
+    match tokens[0].variant {
+        TokenVariant::Pseudo(_) => true,
+        TokenVariant::Opcode(Opcode::Return) => tokens.len() > 1,
+        _ => false,
+    }
+}
+
+/// The lines of real instructions a pseudo-instruction stands for, made of the pseudo-instruction's tokens.
+/// `value` evaluates its immediate operand, the number of instructions may depend on it.
+/// When `relocatable`, a value referring to labels is loaded with all four chunks, so the linker can fill in any address.
+pub fn expand(tokens: &[Token], relocatable: bool, mut value: impl FnMut(&Token) -> Result<i128, AssemblyError>) -> Result<Vec<Vec<Token>>, AssemblyError> {
+    let first = &tokens[0];
+    let instruction = |opcode, operands: &[TokenVariant]| instruction(first, opcode, operands);
+    let mut load = |reg, token: &Token| -> Result<Vec<Vec<Token>>, AssemblyError> {
+        match relocatable && !label_names(token).is_empty() {
+            true => Ok(load_all(first, reg, value(token)?)),
+            false => Ok(load_constant(first, reg, value(token)?)),
+        }
+    };
+
+    let operands = tokens[1..].iter().map(|token| &token.variant).collect::<Vec<_>>();
+    let is_value = |token: &Token| Expression::from_token(token).is_some();
+
+    let pseudo = match first.variant {
+        TokenVariant::Pseudo(pseudo) => pseudo,
+        // `ret value` and `ret reg` put the value into r0, where the calling convention returns it
+        TokenVariant::Opcode(Opcode::Return) => {
+            let mut lines = match (&operands[..], &tokens[1..]) {
+                ([TokenVariant::Register(Register::R0)], _) => Vec::new(),
+                ([TokenVariant::Register(reg)], _) => vec![instruction(Opcode::Move, &[TokenVariant::Register(Register::R0), TokenVariant::Register(*reg)])],
+                (_, [operand]) if is_value(operand) => load(Register::R0, operand)?,
+                _ => return Err(mismatch(tokens, "nothing, reg or imm")),
+            };
+            lines.push(instruction(Opcode::Return, &[]));
+            return Ok(lines);
+        }
+        _ => unreachable!("The line isn't a pseudo-instruction"),
+    };
+
+    let lines = match (pseudo, &operands[..]) {
+        (Pseudo::LoadConstant, [TokenVariant::Register(reg), _]) if is_value(&tokens[2]) => load(*reg, &tokens[2])?,
+        (Pseudo::LoadAddress, [TokenVariant::Register(reg), TokenVariant::Label(_)]) => load(*reg, &tokens[2])?,
+        (Pseudo::Increment, [TokenVariant::Register(reg)]) => vec![instruction(Opcode::Add, &[TokenVariant::Register(*reg), TokenVariant::Register(*reg), TokenVariant::Unsigned(1)])],
+        (Pseudo::Decrement, [TokenVariant::Register(reg)]) => vec![instruction(Opcode::Subtract, &[TokenVariant::Register(*reg), TokenVariant::Register(*reg), TokenVariant::Unsigned(1)])],
+        (Pseudo::Negate, [TokenVariant::Register(reg)]) => vec![instruction(Opcode::Subtract, &[TokenVariant::Register(*reg), TokenVariant::Unsigned(0), TokenVariant::Register(*reg)])],
+        (Pseudo::Negate, [TokenVariant::Register(dest), TokenVariant::Register(src)]) => vec![instruction(Opcode::Subtract, &[TokenVariant::Register(*dest), TokenVariant::Unsigned(0), TokenVariant::Register(*src)])],
+        (Pseudo::Clear, [TokenVariant::Register(reg)]) => vec![instruction(Opcode::Xor, &[TokenVariant::Register(*reg), TokenVariant::Register(*reg), TokenVariant::Register(*reg)])],
+        (pseudo, _) => return Err(mismatch(tokens, match pseudo {
+            Pseudo::LoadConstant => "reg, imm",
+            Pseudo::LoadAddress => "reg, label",
+            Pseudo::Negate => "reg or reg, reg",
+            Pseudo::Increment | Pseudo::Decrement | Pseudo::Clear => "reg",
+        })),
+    };
+
+    Ok(lines)
+}
+
+/// A line of a real instruction, made of tokens at the place of `like`
+pub fn instruction(like: &Token, opcode: Opcode, operands: &[TokenVariant]) -> Vec<Token> {
+    let make = |variant| Token { variant, ..like.clone() };
+
+    let mut line = vec![make(TokenVariant::Opcode(opcode))];
+    line.extend(operands.iter().cloned().map(make));
+    line
+}
+
+/// The shortest sequence setting all 64 bits of a register, made of tokens at the place of `like`.
+/// `ldi` only replaces one 16-bit chunk, so the register is first cleared to zeros or ones unless every chunk is loaded.
+pub fn load_constant(like: &Token, reg: Register, value: i128) -> Vec<Vec<Token>> {
+    let instruction = |opcode, operands: &[TokenVariant]| instruction(like, opcode, operands);
+    let (clearing, chunks) = load_plan(value);
+
+    let register = TokenVariant::Register(reg);
+    let clear = instruction(Opcode::Xor, &[register.clone(), register.clone(), register.clone()]);
+    let invert = instruction(Opcode::Not, &[register.clone(), register.clone()]);
+
+    let mut lines = [clear, invert].into_iter().take(clearing).collect::<Vec<_>>();
+    for (i, chunk) in chunks {
+        lines.push(instruction(Opcode::LoadImmediate, &[register.clone(), TokenVariant::Unsigned(chunk), TokenVariant::Unsigned(i)]));
+    }
+    lines
+}
+
+/// Instructions [`load_constant`] takes for `value`
+pub fn load_length(value: i128) -> usize {
+    let (clearing, chunks) = load_plan(value);
+    clearing + chunks.len()
+}
+
+/// How [`load_constant`] sets a register: the number of instructions clearing it first, `xor` and then `not`,
+/// and the index and value of each chunk loaded by an `ldi` after them
+fn load_plan(value: i128) -> (usize, Vec<(u64, u64)>) {
+    // Expressions never leave the range of i64 and u64, so the low 64 bits are the value
+    let value = value as u64;
+    let chunks = (0..4).map(|i| (i, (value >> (16 * i)) & 0xFFFF));
+
+    // Cleared to zeros, to ones or not at all, with what each chunk holds then
+    [(1, 0), (2, 0xFFFF), (0, u64::MAX)].into_iter()
+        .map(|(clearing, start)| (clearing, chunks.clone().filter(|&(_, chunk)| chunk != start).collect::<Vec<_>>()))
+        .min_by_key(|(clearing, chunks)| clearing + chunks.len())
+        .unwrap()
+}
+
+/// Sets all 64 bits of a register with an `ldi` for each chunk, whatever the value
+pub fn load_all(like: &Token, reg: Register, value: i128) -> Vec<Vec<Token>> {
+    (0..4).map(|i| instruction(like, Opcode::LoadImmediate, &[
+        TokenVariant::Register(reg),
+        TokenVariant::Unsigned((value as u64 >> (16 * i)) & 0xFFFF),
+        TokenVariant::Unsigned(i),
+    ])).collect()
+}
+
+fn mismatch(tokens: &[Token], expected: &'static str) -> AssemblyError {
+    let name = match &tokens[0].variant {
+        TokenVariant::Pseudo(pseudo) => pseudo.to_string(),
+        _ => Opcode::Return.to_string(),
+    };
+    let got = tokens[1..].iter().map(|token| AmbiguousToken::from(&token.variant).describe()).collect::<Vec<_>>();
+
+    AssemblyError::spanning(tokens, AssemblyErrorVariant::PseudoOperands {
+        name,
+        expected,
+        got: if got.is_empty() { "nothing".to_string() } else { got.join(", ") },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble::{assemble, assemble_image, assemble_source, Options};
+
+    fn same(source: &str, expected: &str) {
+        assert_eq!(assemble(source.to_string()).unwrap(), assemble(expected.to_string()).unwrap(), "{}", source);
+    }
+
+    /// Like `same`, for programs larger than the emulator's memory
+    fn same_large(source: &str, expected: &str) {
+        let options = Options { memory_size: 1 << 20, ..Options::default() };
+        let image = |source: &str| assemble_source(source.to_string(), None, &options).ok().unwrap().image;
+
+        assert_eq!(image(source), image(expected), "{}", source);
+    }
+
+    #[test]
+    fn test_load_constant() {
+        same("li r1 0x1234_5678_9abc_def0", "ldi r1 0xdef0 0\nldi r1 0x9abc 1\nldi r1 0x5678 2\nldi r1 0x1234 3");
+        same("li r1 0", "xor r1 r1 r1");
+        same("li r1 5", "xor r1 r1 r1\nldi r1 5 0");
+        same("li r1 0x5_0000_0000", "xor r1 r1 r1\nldi r1 5 2");
+        same("li r1 -1", "xor r1 r1 r1\nnot r1 r1");
+        same("li r1 -2", "xor r1 r1 r1\nnot r1 r1\nldi r1 0xFFFE 0");
+        same(".equ BIG 1 << 40\nli r2 BIG + 1", "xor r2 r2 r2\nldi r2 1 0\nldi r2 0x100 2");
+    }
+
+    #[test]
+    fn test_load_address() {
+        // The address of .data depends on the size of the li before it, which depends on .data
+        same("la r1 .data\n.org 0x10\n.data\nhlt", "xor r1 r1 r1\nldi r1 0x10 0\n.org 0x10\nhlt");
+        same_large("la r1 .data\n.org 0x10000\n.data\nhlt", "xor r1 r1 r1\nldi r1 1 1\n.org 0x10000\nhlt");
+        same(".start\nnop\nla r3 .start", "nop\nxor r3 r3 r3");
+    }
+
+    #[test]
+    fn test_labels_after_expansion() {
+        same("li r1 0x1234_5678\n.end\nb .end", "xor r1 r1 r1\nldi r1 0x5678 0\nldi r1 0x1234 1\nb 0");
+        same("la r1 .end\n.end\nhlt", "xor r1 r1 r1\nldi r1 8 0\nhlt");
+    }
+
+    #[test]
+    fn test_short_forms() {
+        same("inc r1\ndec r2\nneg r3\nneg r4 r5\nclr r6", "add r1 r1 1\nsub r2 r2 1\nsub r3 0 r3\nsub r4 0 r5\nxor r6 r6 r6");
+        same("ret r3\nret r0\nret 7", "mov r0 r3\nret\nret\nxor r0 r0 r0\nldi r0 7 0\nret");
+    }
+
+    #[test]
+    fn test_errors() {
+        for (source, message) in [
+            ("li r1\nhlt", "Line 1: li expects reg, imm; got reg"),
+            ("la r1 5\nhlt", "Line 1: la expects reg, label; got reg, imm"),
+            ("inc 5\nhlt", "Line 1: inc expects reg; got imm"),
+            ("ret r1 r2", "Line 1: ret expects nothing, reg or imm; got reg, reg"),
+            ("li r1 .nowhere\nhlt", "Line 1: No label named .nowhere found"),
+        ] {
+            assert_eq!(assemble_image(source.to_string()).unwrap_err().to_string(), message);
+        }
+    }
+}
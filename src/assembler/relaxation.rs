@@ -0,0 +1,117 @@
+use crate::assembler::{
+    pseudo::{instruction, load_constant, load_length},
+    tokenization::token::{Token, TokenVariant},
+    types::opcode::Opcode,
+    types::register::Register,
+};
+
+/// Register that holds the offset of a branch that's out of reach. It's a temporary, so calls don't expect it to be preserved.
+pub const TEMPORARY: Register = Register::R7;
+
+/// The label a branch or call goes to, if it can be replaced by a jump through a register.
+/// Branch offsets are 16-bit immediates, but a register reaches anywhere.
+pub fn target(tokens: &[Token]) -> Option<&str> {
+    match tokens {
+        [Token { variant: TokenVariant::Opcode(opcode), .. }, Token { variant: TokenVariant::Label(label), .. }] if through_register(*opcode).is_some() => Some(label),
+        _ => None,
+    }
+}
+
+/// Whether a line is a branch or call, whose operand is an offset
+pub fn is_branch(tokens: &[Token]) -> bool {
+    matches!(tokens[0].variant, TokenVariant::Opcode(opcode) if through_register(opcode).is_some())
+}
+
+/// The opcode doing the same as `opcode` with the offset in a register
+fn through_register(opcode: Opcode) -> Option<Opcode> {
+    match opcode {
+        Opcode::Branch | Opcode::Jump => Some(Opcode::Jump),
+        Opcode::BranchGreater | Opcode::JumpGreater => Some(Opcode::JumpGreater),
+        Opcode::BranchEqual | Opcode::JumpEqual => Some(Opcode::JumpEqual),
+        Opcode::BranchSmaller | Opcode::JumpSmaller => Some(Opcode::JumpSmaller),
+        Opcode::BranchGreaterEqual | Opcode::JumpGreaterEqual => Some(Opcode::JumpGreaterEqual),
+        Opcode::BranchNotEqual | Opcode::JumpNotEqual => Some(Opcode::JumpNotEqual),
+        Opcode::BranchSmallerEqual | Opcode::JumpSmallerEqual => Some(Opcode::JumpSmallerEqual),
+        Opcode::Call => Some(Opcode::Call),
+        _ => None,
+    }
+}
+
+/// Offset in instructions from the branch at `address` to `target`
+fn offset(address: usize, target: usize) -> i64 {
+    (target as i64 - address as i64) / 4
+}
+
+/// Bytes a branch at `address` to `target` takes, at least `at_least`.
+/// Once it doesn't fit into one instruction the offset is loaded into [`TEMPORARY`] by the instructions before the jump.
+pub fn size(address: usize, target: usize, at_least: usize) -> usize {
+    if at_least <= 4 && i16::try_from(offset(address, target)).is_ok() {
+        return 4;
+    }
+
+    // The jump is the last instruction, so its offset depends on the size. Five instructions always suffice.
+    (at_least.max(8) / 4..=5)
+        .map(|instructions| instructions * 4)
+        .find(|&size| (load_length(offset(address + size - 4, target) as i128) + 1) * 4 <= size)
+        .unwrap_or(20)
+}
+
+/// The lines replacing a branch of `size` bytes at `address`: the offset is loaded, followed by nops to fill the size and the jump
+pub fn expand(tokens: &[Token], address: usize, target: usize, size: usize) -> Vec<Vec<Token>> {
+    let TokenVariant::Opcode(opcode) = tokens[0].variant else { unreachable!("The line isn't a branch") };
+    let jump = through_register(opcode).expect("The line isn't a branch");
+
+    let mut lines = load_constant(&tokens[0], TEMPORARY, offset(address + size - 4, target) as i128);
+
+    while lines.len() < size / 4 - 1 {
+        lines.push(instruction(&tokens[0], Opcode::Nop, &[]));
+    }
+    lines.push(instruction(&tokens[0], jump, &[TokenVariant::Register(TEMPORARY)]));
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble::{assemble, assemble_source, Options};
+
+    #[test]
+    fn test_size() {
+        assert_eq!(size(0, 4 * 32767, 0), 4);
+        assert_eq!(size(4 * 32768, 0, 4), 4);
+        assert_eq!(size(0, 4 * 32768, 0), 12);
+        assert_eq!(size(4 * 40000, 0, 0), 16);
+        assert_eq!(size(0, 4 * 32768, 16), 16);
+        assert_eq!(size(0, 4, 12), 12);
+    }
+
+    #[test]
+    fn test_relaxation() {
+        // Relaxing the forward branch moves .end, which puts the backward branch out of reach too
+        let source = ".start\n    bne .end\n    call .end\n    b .start\n    .space 0x1FFF4\n.end\n    b .start\n    hlt";
+        let options = Options { memory_size: 1 << 20, ..Options::default() };
+        let assembly = assemble_source(source.to_string(), None, &options).ok().unwrap();
+        let program = assembly.image.chunks(4).map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap())).collect::<Vec<_>>();
+
+        let start = assemble("xor r7 r7 r7\nldi r7 0x8000 0\njmpne r7\ncall 32767\nb -4".to_string()).unwrap();
+        let end = assemble("xor r7 r7 r7\nnot r7 r7\nldi r7 0x7FFB 0\njmp r7\nhlt".to_string()).unwrap();
+
+        assert_eq!(program[..5], start[..]);
+        assert_eq!(program[0x8002..], end[..]);
+
+        let relaxed = assembly.relaxed.iter().map(|note| note.error.to_string()).collect::<Vec<_>>();
+        assert_eq!(relaxed, [
+            "Line 2: Branch to .end is out of reach, replaced by 3 instructions jumping through r7",
+            "Line 7: Branch to .start is out of reach, replaced by 4 instructions jumping through r7",
+        ]);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let options = Options { relax_branches: false, memory_size: 1 << 20, ..Options::default() };
+        let diagnostics = assemble_source("b .end\n.org 0x20000\n.end\nhlt".to_string(), None, &options).err().unwrap();
+
+        assert_eq!(diagnostics[0].error.to_string(), "Line 1: Offset is too large. Required 32768 but limit is 32767");
+    }
+}
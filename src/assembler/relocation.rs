@@ -0,0 +1,234 @@
+use crate::assembler::{
+    assembly_error::{AssemblyError, AssemblyErrorVariant},
+    grammar::expression::{Expression, Symbols},
+    labels::label_names,
+    pseudo::is_pseudo,
+    relaxation,
+    tokenization::token::{Token, TokenVariant},
+    types::directive::Directive,
+    types::operator::Operator,
+    types::section::Section,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A field of a relocatable program that holds an address only known once the program is linked
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Relocation {
+    /// Where the field is, in the program as if it wasn't linked
+    pub address: usize,
+    pub kind: RelocationKind,
+    /// The label whose address goes into the field
+    pub symbol: String,
+    /// Added to the label's address
+    pub addend: i128,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum RelocationKind {
+    /// The offset of a branch or call to the label
+    Branch,
+    /// A 32-bit `.word`
+    Word,
+    /// The four `ldi` of a `li` or `la`, each loading one chunk of the address
+    Address,
+}
+
+static KIND_TABLE: &[(RelocationKind, &str)] = &[
+    (RelocationKind::Branch,  "branch"),
+    (RelocationKind::Word,    "word"),
+    (RelocationKind::Address, "address"),
+];
+
+impl FromStr for RelocationKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        KIND_TABLE
+            .iter()
+            .find(|(_, name)| *name == value)
+            .map(|(kind, _)| *kind)
+            .ok_or_else(|| format!("Unknown relocation: '{}'", value))
+    }
+}
+
+impl fmt::Display for RelocationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, name) = KIND_TABLE.iter().find(|(kind, _)| kind == self).expect("Relocation missing from the table");
+        write!(f, "{}", name)
+    }
+}
+
+/// The relocations of a line at `address` in `section`. Labels missing from `sections` are imported.
+/// Branches only need one to a label of another section, the distance within a section stays the same wherever it's placed.
+/// Addresses loaded by `li` and `la` and those of `.word` always need one.
+pub fn relocations(tokens: &[Token], address: usize, section: Section, sections: &HashMap<String, Section>, symbols: &Symbols) -> Result<Vec<Relocation>, AssemblyError> {
+    let local = |name: &str| sections.get(name) == Some(&section);
+    let operands = &tokens[1..];
+
+    match &tokens[0].variant {
+        _ if is_pseudo(tokens) => {
+            let mut relocations = Vec::new();
+            for operand in operands {
+                if let Some((symbol, addend)) = absolute(operand, sections, symbols)? {
+                    relocations.push(Relocation { address, kind: RelocationKind::Address, symbol, addend });
+                }
+            }
+            Ok(relocations)
+        }
+        TokenVariant::Opcode(_) => {
+            if let Some(label) = relaxation::target(tokens) {
+                return Ok(match local(label) {
+                    true => Vec::new(),
+                    false => vec![Relocation { address, kind: RelocationKind::Branch, symbol: label.to_owned(), addend: 0 }],
+                });
+            }
+
+            for operand in operands {
+                let Some(name) = unrelocatable(operand, sections, symbols)? else { continue };
+
+                // The distance to a label of the same section stays the same wherever it's placed
+                match label_names(operand).into_iter().find(|name| !local(name)) {
+                    Some(foreign) => return Err(not_relocatable(operand, &foreign)),
+                    None if relaxation::is_branch(tokens) => {}
+                    None => return Err(not_relocatable(operand, &name)),
+                }
+            }
+            Ok(Vec::new())
+        }
+        TokenVariant::Directive(Directive::Word) => {
+            let mut relocations = Vec::new();
+            for (i, operand) in operands.iter().enumerate() {
+                if let Some((symbol, addend)) = absolute(operand, sections, symbols)? {
+                    relocations.push(Relocation { address: address + 4 * i, kind: RelocationKind::Word, symbol, addend });
+                }
+            }
+            Ok(relocations)
+        }
+        // Their operands are the labels themselves
+        TokenVariant::Directive(Directive::Global | Directive::Extern) => Ok(Vec::new()),
+        TokenVariant::Directive(_) => {
+            // Sizes and bytes narrower than an address can't hold one
+            for operand in operands {
+                if let Some(name) = unrelocatable(operand, sections, symbols)? {
+                    return Err(not_relocatable(operand, &name));
+                }
+            }
+            Ok(Vec::new())
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// The label and addend of an address in an operand, `None` if it doesn't refer to one.
+/// Only a label plus or minus a constant is an address. The difference of two labels in the same section is a constant.
+fn absolute(token: &Token, sections: &HashMap<String, Section>, symbols: &Symbols) -> Result<Option<(String, i128)>, AssemblyError> {
+    let Some(expression) = Expression::from_token(token) else { return Ok(None) };
+
+    match address(&expression, token, sections, symbols)? {
+        Term::Constant => Ok(None),
+        Term::Address(name, addend) => Ok(Some((name, addend))),
+        Term::Unrelocatable(name) => Err(not_relocatable(token, &name)),
+    }
+}
+
+/// A label whose address an operand depends on, `None` if it's a constant
+fn unrelocatable(token: &Token, sections: &HashMap<String, Section>, symbols: &Symbols) -> Result<Option<String>, AssemblyError> {
+    let Some(expression) = Expression::from_token(token) else { return Ok(None) };
+
+    match address(&expression, token, sections, symbols)? {
+        Term::Constant => Ok(None),
+        Term::Address(name, _) | Term::Unrelocatable(name) => Ok(Some(name)),
+    }
+}
+
+enum Term {
+    Constant,
+    Address(String, i128),
+    /// Uses the address of this label in a way the linker can't fill in
+    Unrelocatable(String),
+}
+
+fn address(expression: &Expression, token: &Token, sections: &HashMap<String, Section>, symbols: &Symbols) -> Result<Term, AssemblyError> {
+    let term = |expression| address(expression, token, sections, symbols);
+    let value = |expression: &Expression| expression.evaluate(symbols, token);
+
+    let term = match expression {
+        Expression::Label(name) => Term::Address(name.to_owned(), 0),
+        Expression::Binary(Operator::Plus, left, right) => match (term(left)?, term(right)?) {
+            (Term::Address(name, addend), Term::Constant) => Term::Address(name, addend + value(right)?),
+            (Term::Constant, Term::Address(name, addend)) => Term::Address(name, addend + value(left)?),
+            (Term::Constant, Term::Constant) => Term::Constant,
+            (Term::Unrelocatable(name), _) | (_, Term::Unrelocatable(name)) | (_, Term::Address(name, _)) => Term::Unrelocatable(name),
+        },
+        Expression::Binary(Operator::Minus, left, right) => match (term(left)?, term(right)?) {
+            (Term::Address(name, addend), Term::Constant) => Term::Address(name, addend - value(right)?),
+            (Term::Address(first, _), Term::Address(second, _)) if sections.get(&first).is_some() && sections.get(&first) == sections.get(&second) => Term::Constant,
+            (Term::Constant, Term::Constant) => Term::Constant,
+            (Term::Unrelocatable(name), _) | (_, Term::Unrelocatable(name)) | (Term::Address(name, _), _) | (_, Term::Address(name, _)) => Term::Unrelocatable(name),
+        },
+        Expression::Number(_) | Expression::Constant(_) => Term::Constant,
+        Expression::Unary(_, inner) | Expression::Function(_, inner) => match term(inner)? {
+            Term::Constant => Term::Constant,
+            Term::Address(name, _) | Term::Unrelocatable(name) => Term::Unrelocatable(name),
+        },
+        Expression::Binary(_, left, right) => match (term(left)?, term(right)?) {
+            (Term::Constant, Term::Constant) => Term::Constant,
+            (Term::Address(name, _) | Term::Unrelocatable(name), _) | (_, Term::Address(name, _) | Term::Unrelocatable(name)) => Term::Unrelocatable(name),
+        },
+    };
+
+    Ok(term)
+}
+
+fn not_relocatable(token: &Token, name: &str) -> AssemblyError {
+    AssemblyError::at(token, AssemblyErrorVariant::NotRelocatable { name: name.to_owned() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble::{assemble_source, Options};
+
+    fn relocations(source: &str) -> Result<Vec<(usize, RelocationKind, String, i128)>, String> {
+        let options = Options { relocatable: true, ..Options::default() };
+
+        match assemble_source(source.to_string(), None, &options) {
+            Ok(assembly) => Ok(assembly.linkage.relocations.into_iter().map(|relocation| (relocation.address, relocation.kind, relocation.symbol, relocation.addend)).collect()),
+            Err(diagnostics) => Err(diagnostics[0].error.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_relocations() {
+        let source = "\
+.extern .print
+.start: call .print
+    b .start
+    la r1 .table
+    li r2 .table + 8
+    li r3 .end - .start
+    hlt
+.end
+.section data
+.table: .word 1 .start .print - 4
+";
+        assert_eq!(relocations(source), Ok(vec![
+            (0x0, RelocationKind::Branch, ".print".to_string(), 0),
+            (0x8, RelocationKind::Address, ".table".to_string(), 0),
+            (0x18, RelocationKind::Address, ".table".to_string(), 8),
+            // Data starts aligned after the text
+            (0x44, RelocationKind::Word, ".start".to_string(), 0),
+            (0x48, RelocationKind::Word, ".print".to_string(), -4),
+        ]));
+    }
+
+    #[test]
+    fn test_not_relocatable() {
+        assert_eq!(relocations("hlt\n.a: .byte .a"), Err("Line 2: The address of .a is only known once the program is linked, only branches, li, la and .word can use it".to_string()));
+        assert_eq!(relocations(".a: ldi r1 .a 0\nhlt"), Err("Line 1: The address of .a is only known once the program is linked, only branches, li, la and .word can use it".to_string()));
+        assert_eq!(relocations(".a: li r1 .a * 2\nhlt"), Err("Line 1: The address of .a is only known once the program is linked, only branches, li, la and .word can use it".to_string()));
+        assert!(relocations(".a: ldi r1 .b - .a 0\n.b\nhlt").is_ok());
+    }
+}
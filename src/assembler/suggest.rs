@@ -0,0 +1,64 @@
+/// The candidate closest to a misspelled name, if any is close enough to be what was meant
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    // Allows about one typo per three characters
+    let limit = (name.chars().count() / 3).max(1);
+
+    candidates.into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min()
+        .map(|(_, candidate)| candidate.to_owned())
+}
+
+/// Number of inserted, removed, replaced or swapped neighbouring characters needed to turn `a` into `b`
+fn distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    // Rows of the distances between prefixes of `a` and `b`
+    let mut before_previous: Vec<usize> = Vec::new();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for i in 0..a.len() {
+        let mut current = vec![i + 1; b.len() + 1];
+
+        for j in 0..b.len() {
+            let replace = previous[j] + usize::from(a[i] != b[j]);
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                current[j + 1] = current[j + 1].min(before_previous[j - 1] + 1);
+            }
+        }
+
+        before_previous = std::mem::replace(&mut previous, current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("add", "add"), 0);
+        assert_eq!(distance("ad", "add"), 1);
+        assert_eq!(distance("mvo", "mov"), 1);
+        assert_eq!(distance("abc", "ca"), 3);
+        assert_eq!(distance(".loop", ".lop"), 1);
+        assert_eq!(distance("", "push"), 4);
+    }
+
+    #[test]
+    fn test_closest() {
+        assert_eq!(closest("ad", ["add", "and", "sub"]), Some("add".to_string()));
+        assert_eq!(closest("pusj", ["push", "pop"]), Some("push".to_string()));
+        assert_eq!(closest("mvo", ["mov", "nop"]), Some("mov".to_string()));
+        assert_eq!(closest(".lop", [".loop", ".end"]), Some(".loop".to_string()));
+        assert_eq!(closest("xyz", ["add", "sub"]), None);
+        assert_eq!(closest("add", ["add"]), None);
+    }
+}
@@ -1,6 +1,10 @@
 pub mod provider;
 pub mod provider_opcodes;
 pub mod provider_unsigned;
+pub mod provider_signed;
+pub mod provider_char;
+pub mod provider_string;
+pub mod provider_operators;
 pub mod provider_single_tokens;
 pub mod provider_labels;
-pub mod provider_registers;
\ No newline at end of file
+pub mod provider_registers;
@@ -3,6 +3,8 @@ use std::fmt::Debug;
 
 pub trait TokenProvider : Debug {
     fn new() -> Self where Self: Sized;
+    /// Forgets the characters given so far, so the provider can read the next token
+    fn reset(&mut self);
     fn give(&mut self, ch: char) -> ProviderResponse;
     fn request_end(&mut self) -> Option<(RawTokenVariant, String)>;
 }
@@ -12,3 +14,11 @@ pub enum ProviderResponse {
     TokenFinished(RawTokenVariant, String), // The token is finished
     Destroyed, // The provider can't continue anymore
 }
+
+/// Characters that end a token: whitespace, the start of a comment, operators and the `:` after a label
+pub fn is_delimiter(ch: char) -> bool {
+    ch.is_whitespace() || ch == ';' || ch == ':' || OPERATOR_CHARS.contains(&ch)
+}
+
+/// Characters operators are made of. `/` is also the start of a comment.
+pub const OPERATOR_CHARS: [char; 13] = ['+', '-', '*', '/', '%', '&', '|', '^', '~', '(', ')', '<', '>'];
@@ -0,0 +1,55 @@
+use crate::assembler::tokenization::providers::provider::{is_delimiter, ProviderResponse, TokenProvider};
+use crate::assembler::tokenization::raw_token::RawTokenVariant;
+
+/// A single quoted character like `'a'` or `'\n'`. Escapes are checked when the token is parsed.
+#[derive(Debug)]
+pub struct CharProvider {
+    input: String,
+    closed: bool,
+}
+
+impl TokenProvider for CharProvider {
+    fn new() -> Self {
+        Self {
+            input: String::new(),
+            closed: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.input.clear();
+        self.closed = false;
+    }
+
+    fn give(&mut self, ch: char) -> ProviderResponse {
+        if self.closed {
+            return if is_delimiter(ch) {
+                ProviderResponse::TokenFinished(RawTokenVariant::Char, self.input.clone())
+            } else {
+                ProviderResponse::Destroyed
+            };
+        }
+
+        let accepted = match self.input.chars().count() {
+            0 => ch == '\'',
+            1 => ch != '\'' && ch != '\n',
+            // Only an escape takes two characters
+            2 if self.input.ends_with('\\') => ch != '\n',
+            _ => {
+                self.closed = ch == '\'';
+                self.closed
+            }
+        };
+
+        if accepted {
+            self.input.push(ch);
+            ProviderResponse::Accepted
+        } else {
+            ProviderResponse::Destroyed
+        }
+    }
+
+    fn request_end(&mut self) -> Option<(RawTokenVariant, String)> {
+        self.closed.then(|| (RawTokenVariant::Char, self.input.clone()))
+    }
+}
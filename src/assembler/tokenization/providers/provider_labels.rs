@@ -1,7 +1,8 @@
-use crate::assembler::tokenization::providers::provider::{ProviderResponse, TokenProvider};
+use crate::assembler::tokenization::providers::provider::{is_delimiter, ProviderResponse, TokenProvider};
 use crate::assembler::tokenization::raw_token::RawTokenVariant;
 use std::fmt::Debug;
 
+/// A `.` followed by letters, digits, `_` and `.`, like `.loop_2` or the local label `..done`
 #[derive(Debug)]
 pub struct LabelProvider {
     input: String,
@@ -14,6 +15,10 @@ impl TokenProvider for LabelProvider {
         }
     }
 
+    fn reset(&mut self) {
+        self.input.clear();
+    }
+
     fn give(&mut self, ch: char) -> ProviderResponse {
         if self.input.is_empty() {
             if ch == '.' {
@@ -22,9 +27,9 @@ impl TokenProvider for LabelProvider {
             } else {
                 ProviderResponse::Destroyed
             }
-        } else if ch.is_whitespace() {
+        } else if is_delimiter(ch) {
             ProviderResponse::TokenFinished(RawTokenVariant::Label, self.input.clone())
-        } else if ch.is_alphabetic() {
+        } else if ch.is_alphanumeric() || ch == '_' || ch == '.' {
             self.input.push(ch);
             ProviderResponse::Accepted
         } else {
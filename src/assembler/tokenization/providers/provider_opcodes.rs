@@ -1,7 +1,8 @@
-use crate::assembler::tokenization::providers::provider::{ProviderResponse, TokenProvider};
+use crate::assembler::tokenization::providers::provider::{is_delimiter, ProviderResponse, TokenProvider};
 use crate::assembler::tokenization::raw_token::RawTokenVariant;
 use std::fmt::Debug;
 
+/// Words like opcodes and constant names. A letter followed by letters, digits or `_`.
 #[derive(Debug)]
 pub struct OpcodeProvider {
     input: String,
@@ -14,10 +15,17 @@ impl TokenProvider for OpcodeProvider {
         }
     }
 
+    fn reset(&mut self) {
+        self.input.clear();
+    }
+
     fn give(&mut self, ch: char) -> ProviderResponse {
-        if ch.is_whitespace() {
-            ProviderResponse::TokenFinished(RawTokenVariant::Opcode, self.input.clone())
-        } else if ch.is_alphabetic() {
+        if is_delimiter(ch) {
+            match self.request_end() {
+                Some((variant, value)) => ProviderResponse::TokenFinished(variant, value),
+                None => ProviderResponse::Destroyed,
+            }
+        } else if ch.is_alphabetic() || (!self.input.is_empty() && (ch.is_ascii_digit() || ch == '_')) {
             self.input.push(ch);
             ProviderResponse::Accepted
         } else {
@@ -26,6 +34,13 @@ impl TokenProvider for OpcodeProvider {
     }
 
     fn request_end(&mut self) -> Option<(RawTokenVariant, String)> {
-        Some((RawTokenVariant::Opcode, self.input.clone()))
+        // Register names are left to the register provider
+        let register = self.input.strip_prefix('r').is_some_and(|number| number.chars().all(|ch| ch.is_ascii_digit()));
+
+        if register || self.input.is_empty() {
+            None
+        } else {
+            Some((RawTokenVariant::Opcode, self.input.clone()))
+        }
     }
 }
@@ -0,0 +1,43 @@
+use crate::assembler::tokenization::providers::provider::{ProviderResponse, TokenProvider, OPERATOR_CHARS};
+use crate::assembler::tokenization::raw_token::RawTokenVariant;
+
+/// Operators and parentheses. A `-` directly followed by a digit is left to the signed provider.
+#[derive(Debug)]
+pub struct OperatorProvider {
+    input: String,
+}
+
+impl TokenProvider for OperatorProvider {
+    fn new() -> Self {
+        Self {
+            input: String::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.input.clear();
+    }
+
+    fn give(&mut self, ch: char) -> ProviderResponse {
+        match (self.input.as_str(), ch) {
+            ("", ch) if OPERATOR_CHARS.contains(&ch) => {
+                self.input.push(ch);
+                ProviderResponse::Accepted
+            }
+            ("<", '<') | (">", '>') => {
+                self.input.push(ch);
+                ProviderResponse::Accepted
+            }
+            ("" | "<" | ">", _) => ProviderResponse::Destroyed,
+            ("-", ch) if ch.is_ascii_digit() => ProviderResponse::Destroyed,
+            _ => ProviderResponse::TokenFinished(RawTokenVariant::Operator, self.input.clone()),
+        }
+    }
+
+    fn request_end(&mut self) -> Option<(RawTokenVariant, String)> {
+        match self.input.as_str() {
+            "" | "<" | ">" => None,
+            _ => Some((RawTokenVariant::Operator, self.input.clone())),
+        }
+    }
+}
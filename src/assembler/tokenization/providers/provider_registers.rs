@@ -1,4 +1,4 @@
-use crate::assembler::tokenization::providers::provider::{ProviderResponse, TokenProvider};
+use crate::assembler::tokenization::providers::provider::{is_delimiter, ProviderResponse, TokenProvider};
 use crate::assembler::tokenization::raw_token::RawTokenVariant;
 use std::fmt::Debug;
 
@@ -14,6 +14,10 @@ impl TokenProvider for RegisterProvider {
         }
     }
 
+    fn reset(&mut self) {
+        self.input.clear();
+    }
+
     fn give(&mut self, ch: char) -> ProviderResponse {
         if self.input.is_empty() {
             if ch == 'r' {
@@ -22,7 +26,7 @@ impl TokenProvider for RegisterProvider {
             } else {
                 ProviderResponse::Destroyed
             }
-        } else if ch.is_whitespace() {
+        } else if is_delimiter(ch) {
             ProviderResponse::TokenFinished(RawTokenVariant::Register, self.input.clone())
         } else if ch.is_numeric() {
             self.input.push(ch);
@@ -0,0 +1,44 @@
+use crate::assembler::tokenization::providers::provider::{ProviderResponse, TokenProvider};
+use crate::assembler::tokenization::providers::provider_unsigned::UnsignedProvider;
+use crate::assembler::tokenization::raw_token::RawTokenVariant;
+
+/// A `-` followed by anything the [`UnsignedProvider`] accepts
+#[derive(Debug)]
+pub struct SignedProvider {
+    minus: bool,
+    magnitude: UnsignedProvider,
+}
+
+impl TokenProvider for SignedProvider {
+    fn new() -> Self {
+        Self {
+            minus: false,
+            magnitude: UnsignedProvider::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.minus = false;
+        self.magnitude.reset();
+    }
+
+    fn give(&mut self, ch: char) -> ProviderResponse {
+        if !self.minus {
+            return if ch == '-' {
+                self.minus = true;
+                ProviderResponse::Accepted
+            } else {
+                ProviderResponse::Destroyed
+            };
+        }
+
+        match self.magnitude.give(ch) {
+            ProviderResponse::TokenFinished(_, digits) => ProviderResponse::TokenFinished(RawTokenVariant::Signed, format!("-{}", digits)),
+            response => response,
+        }
+    }
+
+    fn request_end(&mut self) -> Option<(RawTokenVariant, String)> {
+        self.magnitude.request_end().map(|(_, digits)| (RawTokenVariant::Signed, format!("-{}", digits)))
+    }
+}
@@ -1,7 +1,7 @@
-/*
 use crate::assembler::tokenization::providers::provider::{ProviderResponse, TokenProvider};
 use crate::assembler::tokenization::raw_token::RawTokenVariant;
 
+/// Tokens made of a single character, like the `:` after a label
 #[derive(Debug)]
 pub struct SingleTokenProvider;
 
@@ -10,10 +10,11 @@ impl TokenProvider for SingleTokenProvider {
         Self
     }
 
+    fn reset(&mut self) {}
+
     fn give(&mut self, ch: char) -> ProviderResponse {
         match ch {
-            '=' => ProviderResponse::TokenFinished(RawTokenVariant::Equals, ch.to_string()),
-            ';' => ProviderResponse::TokenFinished(RawTokenVariant::Semicolon, ch.to_string()),
+            ':' => ProviderResponse::TokenFinished(RawTokenVariant::Colon, ch.to_string()),
             _ => ProviderResponse::Destroyed,
         }
     }
@@ -22,4 +23,3 @@ impl TokenProvider for SingleTokenProvider {
         None
     }
 }
-*/
\ No newline at end of file
@@ -0,0 +1,52 @@
+use crate::assembler::tokenization::providers::provider::{is_delimiter, ProviderResponse, TokenProvider};
+use crate::assembler::tokenization::raw_token::RawTokenVariant;
+
+/// A double quoted string on a single line like `"Hello\n"`. Escapes are checked when the token is parsed.
+#[derive(Debug)]
+pub struct StringProvider {
+    input: String,
+    escaped: bool,
+    closed: bool,
+}
+
+impl TokenProvider for StringProvider {
+    fn new() -> Self {
+        Self {
+            input: String::new(),
+            escaped: false,
+            closed: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.input.clear();
+        self.escaped = false;
+        self.closed = false;
+    }
+
+    fn give(&mut self, ch: char) -> ProviderResponse {
+        if self.closed {
+            return if is_delimiter(ch) {
+                ProviderResponse::TokenFinished(RawTokenVariant::String, self.input.clone())
+            } else {
+                ProviderResponse::Destroyed
+            };
+        }
+
+        if (self.input.is_empty() && ch != '"') || ch == '\n' {
+            return ProviderResponse::Destroyed;
+        }
+
+        if !self.input.is_empty() && !self.escaped && ch == '"' {
+            self.closed = true;
+        }
+
+        self.escaped = !self.escaped && ch == '\\';
+        self.input.push(ch);
+        ProviderResponse::Accepted
+    }
+
+    fn request_end(&mut self) -> Option<(RawTokenVariant, String)> {
+        self.closed.then(|| (RawTokenVariant::String, self.input.clone()))
+    }
+}
@@ -1,6 +1,7 @@
-use crate::assembler::tokenization::providers::provider::{ProviderResponse, TokenProvider};
+use crate::assembler::tokenization::providers::provider::{is_delimiter, ProviderResponse, TokenProvider};
 use crate::assembler::tokenization::raw_token::RawTokenVariant;
 
+/// Decimal, `0x` hexadecimal and `0b` binary numbers. The digits are checked when the token is parsed.
 #[derive(Debug)]
 pub struct UnsignedProvider {
     input: String,
@@ -13,18 +14,20 @@ impl TokenProvider for UnsignedProvider {
         }
     }
 
+    fn reset(&mut self) {
+        self.input.clear();
+    }
+
     fn give(&mut self, ch: char) -> ProviderResponse {
-        if self.input.is_empty() && !ch.is_numeric() {
+        if self.input.is_empty() && !ch.is_ascii_digit() {
             return ProviderResponse::Destroyed;
         }
 
-        if ch.is_numeric() {
+        if ch.is_ascii_alphanumeric() || ch == '_' {
             self.input.push(ch);
             ProviderResponse::Accepted
-        } else if !self.input.is_empty() {
-            // If we've gathered at least one digit and encounter a non-digit,
-            // finish the token
-            ProviderResponse::TokenFinished(RawTokenVariant::Unsigned, self.input.parse().unwrap())
+        } else if is_delimiter(ch) {
+            ProviderResponse::TokenFinished(RawTokenVariant::Unsigned, self.input.clone())
         } else {
             ProviderResponse::Destroyed
         }
@@ -34,7 +37,7 @@ impl TokenProvider for UnsignedProvider {
         if self.input.is_empty() {
             None
         } else {
-            Some((RawTokenVariant::Unsigned, self.input.parse().unwrap()))
+            Some((RawTokenVariant::Unsigned, self.input.clone()))
         }
     }
 }
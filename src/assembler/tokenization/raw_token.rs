@@ -1,5 +1,9 @@
 use crate::assembler::{
+    position::Position,
+    types::directive::Directive,
     types::opcode::Opcode,
+    types::operator::Operator,
+    types::pseudo::Pseudo,
     types::register::Register,
     tokenization::token::{Token, TokenVariant},
     tokenization::tokenization_error::{TokenizationError, TokenizationErrorVariant},
@@ -11,8 +15,12 @@ pub enum RawTokenVariant {
     Opcode,
     Unsigned,
     Signed,
+    Char,
+    String,
+    Operator,
     Label,
     Register,
+    Colon,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -23,29 +31,25 @@ pub struct RawToken {
     pub column: usize,
 }
 
-impl RawToken {
-    pub fn len(&self) -> usize {
-        self.value.len()
-    }
-}
-
 impl TryFrom<RawToken> for Token {
     type Error = TokenizationError;
 
     fn try_from(token: RawToken) -> Result<Self, Self::Error> {
         let variant = match token.variant {
             RawTokenVariant::Opcode => {
-                match Opcode::from_str(&token.value) {
-                    Ok(opc) => TokenVariant::Opcode(opc),
-                    Err(_) => return Err(TokenizationError {
-                        line: token.line,
-                        column: token.column,
-                        variant: TokenizationErrorVariant::OpcodeNotRecognised,
-                    }),
+                if let Ok(opc) = Opcode::from_str(&token.value) {
+                    TokenVariant::Opcode(opc)
+                } else if let Ok(pseudo) = Pseudo::from_str(&token.value) {
+                    TokenVariant::Pseudo(pseudo)
+                } else {
+                    TokenVariant::Symbol(token.value.to_owned())
                 }
             }
+            // `1f` and `1b` refer to the numeric label `1:` after or before them
+            RawTokenVariant::Unsigned if is_numeric_label_reference(&token.value) => TokenVariant::Label(token.value.to_owned()),
             RawTokenVariant::Unsigned => {
-                match token.value.parse::<u16>() {
+                let (digits, radix) = split_number(&token.value);
+                match u64::from_str_radix(&digits, radix) {
                     Ok(unsigned) => TokenVariant::Unsigned(unsigned),
                     Err(error) => return Err(TokenizationError {
                         line: token.line,
@@ -55,7 +59,8 @@ impl TryFrom<RawToken> for Token {
                 }
             }
             RawTokenVariant::Signed => {
-                match token.value.parse::<i16>() {
+                let (digits, radix) = split_number(&token.value[1..]);
+                match i64::from_str_radix(&format!("-{}", digits), radix) {
                     Ok(signed) => TokenVariant::Signed(signed),
                     Err(error) => return Err(TokenizationError {
                         line: token.line,
@@ -64,7 +69,31 @@ impl TryFrom<RawToken> for Token {
                     }),
                 }
             }
-            RawTokenVariant::Label => TokenVariant::Label(token.value.to_owned()),
+            RawTokenVariant::Char => {
+                match unescape(&token.value[1..token.value.len() - 1]) {
+                    Ok(text) => TokenVariant::Unsigned(text.chars().next().unwrap() as u64),
+                    Err(ch) => return Err(TokenizationError {
+                        line: token.line,
+                        column: token.column,
+                        variant: TokenizationErrorVariant::UnknownEscape(ch),
+                    }),
+                }
+            }
+            RawTokenVariant::String => {
+                match unescape(&token.value[1..token.value.len() - 1]) {
+                    Ok(text) => TokenVariant::String(text),
+                    Err(ch) => return Err(TokenizationError {
+                        line: token.line,
+                        column: token.column,
+                        variant: TokenizationErrorVariant::UnknownEscape(ch),
+                    }),
+                }
+            }
+            RawTokenVariant::Operator => TokenVariant::Operator(Operator::from_str(&token.value).expect("The operator provider only produces operators")),
+            RawTokenVariant::Label => match Directive::from_str(&token.value) {
+                Ok(directive) => TokenVariant::Directive(directive),
+                Err(_) => TokenVariant::Label(token.value.to_owned()),
+            },
             RawTokenVariant::Register => {
                 match Register::from_str(&token.value) {
                     Ok(reg) => TokenVariant::Register(reg),
@@ -75,11 +104,135 @@ impl TryFrom<RawToken> for Token {
                     }),
                 }
             }
+            RawTokenVariant::Colon => TokenVariant::Colon,
         };
 
         Ok(Token {
-            line: token.line,
+            position: Position::new(None, token.line),
             variant,
+            range: token.column..token.column + token.value.chars().count(),
+            expansion: None,
         })
     }
 }
+
+/// Decimal digits followed by `f` or `b`
+pub fn is_numeric_label_reference(text: &str) -> bool {
+    text.strip_suffix(['f', 'b']).is_some_and(|digits| !digits.is_empty() && digits.chars().all(|ch| ch.is_ascii_digit()))
+}
+
+/// Splits a number into its digits and radix. `0x` is hexadecimal, `0b` binary and anything else decimal.
+/// `_` may separate digits but can't come first.
+fn split_number(text: &str) -> (String, u32) {
+    let (digits, radix) = match text.get(..2) {
+        Some("0x" | "0X") => (&text[2..], 16),
+        Some("0b" | "0B") => (&text[2..], 2),
+        _ => (text, 10),
+    };
+
+    // A leading `_` is kept so parsing fails on it
+    let digits = digits.chars().enumerate().filter(|&(i, ch)| i == 0 || ch != '_').map(|(_, ch)| ch).collect();
+
+    (digits, radix)
+}
+
+/// Replaces escape sequences like `\n` in the contents of a character or string literal.
+/// Returns the escaped character if an escape is unknown.
+fn unescape(text: &str) -> Result<String, char> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+
+        unescaped.push(match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some(escaped @ ('\\' | '\'' | '"')) => escaped,
+            Some(other) => return Err(other),
+            None => return Err('\\'),
+        });
+    }
+
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(variant: RawTokenVariant, value: &str) -> Result<TokenVariant, TokenizationError> {
+        Token::try_from(RawToken { variant, value: value.to_string(), line: 0, column: 0 }).map(|token| token.variant)
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(parse(RawTokenVariant::Opcode, "add").unwrap(), TokenVariant::Opcode(Opcode::Add));
+        assert_eq!(parse(RawTokenVariant::Opcode, "BASE_2").unwrap(), TokenVariant::Symbol("BASE_2".to_string()));
+        assert_eq!(parse(RawTokenVariant::Opcode, "li").unwrap(), TokenVariant::Pseudo(Pseudo::LoadConstant));
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(parse(RawTokenVariant::Unsigned, "1234").unwrap(), TokenVariant::Unsigned(1234));
+        assert_eq!(parse(RawTokenVariant::Unsigned, "0xBEEF").unwrap(), TokenVariant::Unsigned(0xBEEF));
+        assert_eq!(parse(RawTokenVariant::Unsigned, "0b1010").unwrap(), TokenVariant::Unsigned(10));
+        assert_eq!(parse(RawTokenVariant::Unsigned, "1_000_000").unwrap(), TokenVariant::Unsigned(1_000_000));
+        assert_eq!(parse(RawTokenVariant::Unsigned, "0xFFFF_FFFF").unwrap(), TokenVariant::Unsigned(0xFFFF_FFFF));
+        assert_eq!(parse(RawTokenVariant::Signed, "-12").unwrap(), TokenVariant::Signed(-12));
+        assert_eq!(parse(RawTokenVariant::Signed, "-0x80").unwrap(), TokenVariant::Signed(-128));
+        assert_eq!(parse(RawTokenVariant::Signed, "-9223372036854775808").unwrap(), TokenVariant::Signed(i64::MIN));
+    }
+
+    #[test]
+    fn test_invalid_numbers() {
+        for (variant, value) in [
+            (RawTokenVariant::Unsigned, "12a"),
+            (RawTokenVariant::Unsigned, "0x"),
+            (RawTokenVariant::Unsigned, "0x_1"),
+            (RawTokenVariant::Unsigned, "0b102"),
+            (RawTokenVariant::Unsigned, "18446744073709551616"),
+            (RawTokenVariant::Signed, "-9223372036854775809"),
+        ] {
+            let err = parse(variant, value).unwrap_err();
+            assert!(matches!(err.variant, TokenizationErrorVariant::ParseIntError(_)), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_chars() {
+        assert_eq!(parse(RawTokenVariant::Char, "'A'").unwrap(), TokenVariant::Unsigned(65));
+        assert_eq!(parse(RawTokenVariant::Char, "';'").unwrap(), TokenVariant::Unsigned(59));
+        assert_eq!(parse(RawTokenVariant::Char, "'\\n'").unwrap(), TokenVariant::Unsigned(10));
+        assert_eq!(parse(RawTokenVariant::Char, "'\\''").unwrap(), TokenVariant::Unsigned(39));
+        assert_eq!(parse(RawTokenVariant::Char, "'\\\\'").unwrap(), TokenVariant::Unsigned(92));
+
+        let err = parse(RawTokenVariant::Char, "'\\q'").unwrap_err();
+        assert!(matches!(err.variant, TokenizationErrorVariant::UnknownEscape('q')));
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(parse(RawTokenVariant::String, "\"\"").unwrap(), TokenVariant::String(String::new()));
+        assert_eq!(parse(RawTokenVariant::String, "\"a; b\\t\\\"c\\\"\\n\"").unwrap(), TokenVariant::String("a; b\t\"c\"\n".to_string()));
+    }
+
+    #[test]
+    fn test_directives() {
+        assert_eq!(parse(RawTokenVariant::Label, ".word").unwrap(), TokenVariant::Directive(Directive::Word));
+        assert_eq!(parse(RawTokenVariant::Label, ".words").unwrap(), TokenVariant::Label(".words".to_string()));
+    }
+
+    #[test]
+    fn test_numeric_labels() {
+        assert_eq!(parse(RawTokenVariant::Unsigned, "1f").unwrap(), TokenVariant::Label("1f".to_string()));
+        assert_eq!(parse(RawTokenVariant::Unsigned, "12b").unwrap(), TokenVariant::Label("12b".to_string()));
+        assert_eq!(parse(RawTokenVariant::Unsigned, "0b1").unwrap(), TokenVariant::Unsigned(1));
+        assert_eq!(parse(RawTokenVariant::Unsigned, "0x1f").unwrap(), TokenVariant::Unsigned(0x1f));
+    }
+}
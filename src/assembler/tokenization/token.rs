@@ -1,25 +1,45 @@
+use crate::assembler::grammar::expression::Expression;
+use crate::assembler::macros::Expansion;
+use crate::assembler::position::Position;
 use crate::assembler::types::{
+    directive::Directive,
     opcode::Opcode,
+    operator::Operator,
+    pseudo::Pseudo,
     register::Register,
 };
-//use std::ops::Range;
+use std::ops::Range;
+use std::rc::Rc;
 use std::str::FromStr;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Token {
-    pub line: usize,
+    pub position: Position,
     pub variant: TokenVariant,
-//    pub range: Range<usize>,
+    /// Columns the token spans on its line
+    pub range: Range<usize>,
+    /// The macro invocation the token comes from, if any
+    pub expansion: Option<Rc<Expansion>>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum TokenVariant {
     Opcode(Opcode),
+    Pseudo(Pseudo),
+    Directive(Directive),
     Label(String),
-    Unsigned(u16),
-    Signed(i16),
+    String(String),
+    Unsigned(u64),
+    Signed(i64),
+    /// A name that isn't an opcode, like a constant defined with `.equ`
+    Symbol(String),
+    Operator(Operator),
+    /// An operand made of several tokens
+    Expression(Expression),
     Register(Register),
     Bool(bool),
+    /// Ends the label at the start of a line
+    Colon,
 }
 
 impl FromStr for TokenVariant {
@@ -28,11 +48,13 @@ impl FromStr for TokenVariant {
     fn from_str(value: &str) -> Result<Self, Self::Err> {
         if let Ok(opcode) = FromStr::from_str(value) {
             Ok(Self::Opcode(opcode))
+        } else if let Ok(directive) = FromStr::from_str(value) {
+            Ok(Self::Directive(directive))
         } else if value.chars().nth(0) == Some('.') {
             Ok(Self::Label(value.to_owned()))
-        } else if let Ok(value) = value.parse::<u16>() {
+        } else if let Ok(value) = value.parse::<u64>() {
             Ok(Self::Unsigned(value))
-        } else if let Ok(value) = value.parse::<i16>() {
+        } else if let Ok(value) = value.parse::<i64>() {
             Ok(Self::Signed(value))
         } else if let Ok(register) = Register::from_str(value) {
             Ok(Self::Register(register))
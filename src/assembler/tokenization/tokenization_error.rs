@@ -10,23 +10,24 @@ pub struct TokenizationError {
 
 #[derive(Debug)]
 pub enum TokenizationErrorVariant {
-    InvalidCharacter(char),
-    OpcodeNotRecognised,
     ParseIntError(ParseIntError),
     ParseRegisterError,
-    NoProviderFinished,
+    /// Text up to the next whitespace that no provider recognizes as a token
+    InvalidToken(String),
     MultipleProvidersFinished,
+    UnterminatedComment,
+    UnknownEscape(char),
 }
 
 impl Display for TokenizationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let str = match self.variant {
-            TokenizationErrorVariant::InvalidCharacter(char) => format!("Invalid character '{}'", char),
-            TokenizationErrorVariant::OpcodeNotRecognised => "Unrecognized opcode".to_string(),
-            TokenizationErrorVariant::ParseIntError(_) => "Integer parsing error".to_string(),
+        let str = match &self.variant {
+            TokenizationErrorVariant::ParseIntError(error) => format!("Integer parsing error: {}", error),
             TokenizationErrorVariant::ParseRegisterError => "Unparsable register".to_string(),
-            TokenizationErrorVariant::NoProviderFinished => "No provider finished".to_string(),
+            TokenizationErrorVariant::InvalidToken(text) => format!("Invalid token {}", text),
             TokenizationErrorVariant::MultipleProvidersFinished => "Multiple providers finished".to_string(),
+            TokenizationErrorVariant::UnterminatedComment => "Block comment is never closed".to_string(),
+            TokenizationErrorVariant::UnknownEscape(char) => format!("Unknown escape sequence '\\{}'", char),
         };
         write!(f, "{}", str)
     }
@@ -1,40 +1,85 @@
 use crate::assembler::tokenization::providers::provider::{TokenProvider, ProviderResponse};
+use crate::assembler::tokenization::providers::provider_char::CharProvider;
 use crate::assembler::tokenization::providers::provider_labels::LabelProvider;
 use crate::assembler::tokenization::providers::provider_opcodes::OpcodeProvider;
+use crate::assembler::tokenization::providers::provider_operators::OperatorProvider;
 use crate::assembler::tokenization::providers::provider_registers::RegisterProvider;
+use crate::assembler::tokenization::providers::provider_signed::SignedProvider;
+use crate::assembler::tokenization::providers::provider_single_tokens::SingleTokenProvider;
+use crate::assembler::tokenization::providers::provider_string::StringProvider;
 use crate::assembler::tokenization::providers::provider_unsigned::UnsignedProvider;
-use crate::assembler::tokenization::raw_token::{RawToken, RawTokenVariant};
+use crate::assembler::tokenization::raw_token::RawToken;
 use crate::assembler::tokenization::tokenization_error::{TokenizationError, TokenizationErrorVariant};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 struct Position {
+    /// Byte offset into the input
     pos: usize,
     line: usize,
     column: usize,
 }
 
+/// Splits the input into tokens in a single pass.
+/// At the start of a token every provider is given the same characters side by side until all of them finished or gave up.
 pub struct Tokenizer {
-    input: Vec<char>,
+    input: String,
     position: Position,
+    providers: Vec<Box<dyn TokenProvider>>,
+    /// Which providers are still reading the current token
+    alive: Vec<bool>,
 }
 
 impl Tokenizer {
     pub fn new(input: String) -> Self {
         Self {
-            input: input.chars().collect(),
+            input,
             position: Position {
                 pos: 0,
                 line: 0,
                 column: 0,
-            }
+            },
+            providers: Vec::new(),
+            alive: Vec::new(),
         }
+        .with_provider::<OpcodeProvider>()
+        .with_provider::<UnsignedProvider>()
+        .with_provider::<SignedProvider>()
+        .with_provider::<CharProvider>()
+        .with_provider::<StringProvider>()
+        .with_provider::<OperatorProvider>()
+        .with_provider::<LabelProvider>()
+        .with_provider::<RegisterProvider>()
+        .with_provider::<SingleTokenProvider>()
+    }
+
+    /// Adds a provider for another kind of token. A token must still be recognized by exactly one provider.
+    pub fn with_provider<P: TokenProvider + 'static>(mut self) -> Self {
+        self.providers.push(Box::new(P::new()));
+        self.alive.push(false);
+        self
     }
 
+    /// Tokenizes the input, stopping at the first error
+    #[cfg(test)]
     pub fn tokenize(&mut self) -> Result<Vec<RawToken>, TokenizationError> {
-        let mut tokens = Vec::new();
+        let (tokens, errors) = self.tokenize_all();
+
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(tokens),
+        }
+    }
+
+    /// Tokenizes the whole input. A line with an invalid token is left out and tokenizing continues on the next line.
+    pub fn tokenize_all(&mut self) -> (Vec<RawToken>, Vec<TokenizationError>) {
+        let mut tokens: Vec<RawToken> = Vec::new();
+        let mut errors = Vec::new();
 
         loop {
-            self.skip_whitespace();
+            if let Err(error) = self.skip_whitespace_and_comments() {
+                errors.push(error);
+                break;
+            }
 
             if self.position.pos >= self.input.len() {
                 break;
@@ -42,120 +87,327 @@ impl Tokenizer {
 
             match self.get_next_token() {
                 Ok(token) => {
-                    // println!("Constructed token: {:?}", token);
-                    // println!();
-
-                    for ch in token.value.chars() {
-                        // advance
-                        if ch == '\n' {
-                            self.position.line += 1;
-                            self.position.column = 0;
-                        } else {
-                            self.position.column += 1;
-                        }
-                        self.position.pos += 1;
-                    }
-
+                    self.skip(token.value.len());
                     tokens.push(token);
                 }
-                Err(variant) => return Err(TokenizationError {
-                    line: self.position.line,
-                    column: self.position.column,
-                    variant,
-                }),
+                Err(variant) => {
+                    let line = self.position.line;
+
+                    errors.push(TokenizationError { line, column: self.position.column, variant });
+
+                    while tokens.last().is_some_and(|token| token.line == line) {
+                        tokens.pop();
+                    }
+                    while self.peek().is_some_and(|ch| ch != '\n') {
+                        self.advance();
+                    }
+                }
             }
         }
 
-        Ok(tokens)
+        (tokens, errors)
     }
 
     fn get_next_token(&mut self) -> Result<RawToken, TokenizationErrorVariant> {
-        // Skip until we have a character
-        self.skip_whitespace();
         if self.position.pos >= self.input.len() {
             panic!("Reached end of input while parsing input");
         }
 
-        // Establish all providers we want to use
-        let providers: Vec<Box<dyn TokenProvider>> = vec![
-            Box::from(OpcodeProvider::new()),
-            Box::from(UnsignedProvider::new()),
-            Box::from(LabelProvider::new()),
-            Box::from(RegisterProvider::new()),
-        ];
-        let mut provider_results = Vec::with_capacity(1);
-
-        // Try each provider
-        for provider in providers {
-            if let Some(result) = self.simulate_provider(provider, self.position) {
-                provider_results.push(result);
+        for (provider, alive) in self.providers.iter_mut().zip(&mut self.alive) {
+            provider.reset();
+            *alive = true;
+        }
+
+        let mut result = None;
+        let mut finished = 0;
+        let mut chars = self.input[self.position.pos..].chars();
+
+        // Feed every provider the same characters until none of them is left reading
+        while self.alive.contains(&true) {
+            let ch = chars.next();
+
+            for (provider, alive) in self.providers.iter_mut().zip(&mut self.alive).filter(|(_, alive)| **alive) {
+                let token = match ch {
+                    Some(ch) => match provider.give(ch) {
+                        ProviderResponse::Accepted => continue,
+                        ProviderResponse::TokenFinished(variant, value) => Some((variant, value)),
+                        ProviderResponse::Destroyed => None,
+                    },
+                    None => provider.request_end(),
+                };
+
+                *alive = false;
+
+                if token.is_some() {
+                    finished += 1;
+                    result = token;
+                }
             }
         }
 
         // Check if there is exactly one provider that gave back a token
-        if provider_results.is_empty() {
-            Err(TokenizationErrorVariant::NoProviderFinished)
-        } else if provider_results.len() == 1 {
-            let (variant, value) = provider_results.pop().unwrap();
-            Ok(RawToken {
+        match (finished, result) {
+            (1, Some((variant, value))) => Ok(RawToken {
                 variant,
                 value,
                 line: self.position.line,
                 column: self.position.column,
-            })
-        } else {
-            Err(TokenizationErrorVariant::MultipleProvidersFinished)
+            }),
+            (0, _) => {
+                let text = self.input[self.position.pos..].split(char::is_whitespace).next().unwrap_or_default();
+                Err(TokenizationErrorVariant::InvalidToken(text.to_string()))
+            }
+            _ => Err(TokenizationErrorVariant::MultipleProvidersFinished),
         }
     }
 
-    fn simulate_provider(&mut self, mut provider: Box<dyn TokenProvider>, position: Position) -> Option<(RawTokenVariant, String)> {
-        let mut position = position;
-
+    /// Skips whitespace and comments.
+    /// `;` and `//` comment out the rest of the line, `/* */` comments may span multiple lines.
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), TokenizationError> {
         loop {
-            if position.pos >= self.input.len() {
-                return provider.request_end();
+            let rest = &self.input[self.position.pos..];
+
+            if rest.starts_with(';') || rest.starts_with("//") {
+                let length = rest.find('\n').unwrap_or(rest.len());
+                self.skip(length);
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let Some(end) = comment.find("*/") else {
+                    return Err(TokenizationError {
+                        line: self.position.line,
+                        column: self.position.column,
+                        variant: TokenizationErrorVariant::UnterminatedComment,
+                    });
+                };
+
+                self.skip(end + 4);
+            } else if self.peek().is_some_and(char::is_whitespace) {
+                self.advance();
+            } else {
+                return Ok(());
             }
+        }
+    }
 
-            let ch = self.input[position.pos];
+    fn peek(&self) -> Option<char> {
+        self.input[self.position.pos..].chars().next()
+    }
 
-            // print!("Gave '{}' to {:?} ---- ", ch, provider);
-            let response = provider.give(ch);
+    /// Advances over the next `length` bytes
+    fn skip(&mut self, length: usize) {
+        let end = self.position.pos + length;
 
-            match response {
-                ProviderResponse::Accepted => {
-                    // println!("Provider accepted");
-                }
-                ProviderResponse::TokenFinished(variant, value) => {
-                    // println!("variant: '{:?}' value: '{}'", variant, value);
-                    return Some((variant, value))
-                }
-                ProviderResponse::Destroyed => {
-                    // println!("Provider destroyed");
-                    return None
-                }
-            }
+        while self.position.pos < end {
+            self.advance();
+        }
+    }
 
-            // advance
-            if let Some('\n') = self.input.get(position.pos) {
-                position.line += 1;
-                position.column = 0;
-            } else {
-                position.column += 1;
-            }
+    fn advance(&mut self) {
+        let Some(ch) = self.peek() else {
+            return;
+        };
 
-            position.pos += 1;
+        if ch == '\n' {
+            self.position.line += 1;
+            self.position.column = 0;
+        } else {
+            self.position.column += 1;
         }
+        self.position.pos += ch.len_utf8();
     }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::tokenization::providers::provider::is_delimiter;
+    use crate::assembler::tokenization::raw_token::RawTokenVariant;
+    use std::time::Instant;
 
-    fn skip_whitespace(&mut self) {
-        while self.position.pos < self.input.len() && self.input[self.position.pos].is_whitespace() {
-            if self.input[self.position.pos] == '\n' {
-                self.position.line += 1;
-                self.position.column = 0;
+    fn positions(input: &str) -> Vec<(String, usize, usize)> {
+        Tokenizer::new(input.to_string()).tokenize().unwrap().into_iter()
+            .map(|token| (token.value, token.line, token.column))
+            .collect()
+    }
+
+    #[test]
+    fn test_line_comments() {
+        assert_eq!(positions("; header\nadd r1 r2 r3 ; sum\n// done\nhlt// end"), [
+            ("add".to_string(), 1, 0),
+            ("r1".to_string(), 1, 4),
+            ("r2".to_string(), 1, 7),
+            ("r3".to_string(), 1, 10),
+            ("hlt".to_string(), 3, 0),
+        ]);
+    }
+
+    #[test]
+    fn test_comment_ends_token() {
+        assert_eq!(positions("push r1;save\n.loop//x"), [
+            ("push".to_string(), 0, 0),
+            ("r1".to_string(), 0, 5),
+            (".loop".to_string(), 1, 0),
+        ]);
+    }
+
+    #[test]
+    fn test_block_comments() {
+        assert_eq!(positions("nop /* one\ntwo */ hlt\n/**/mov r1/* x */r2"), [
+            ("nop".to_string(), 0, 0),
+            ("hlt".to_string(), 1, 7),
+            ("mov".to_string(), 2, 4),
+            ("r1".to_string(), 2, 8),
+            ("r2".to_string(), 2, 17),
+        ]);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let err = Tokenizer::new("nop\n  /* never closed\n".to_string()).tokenize().unwrap_err();
+
+        assert!(matches!(err.variant, TokenizationErrorVariant::UnterminatedComment));
+        assert_eq!((err.line, err.column), (1, 2));
+    }
+
+    #[test]
+    fn test_literals() {
+        let tokens = Tokenizer::new("add r1 0xFF_FF -0b101 ';' '\\'';x\ncmp 1_000 -7".to_string()).tokenize().unwrap();
+        let tokens = tokens.into_iter().map(|token| (token.variant, token.value)).collect::<Vec<_>>();
+
+        assert_eq!(tokens, [
+            (RawTokenVariant::Opcode, "add".to_string()),
+            (RawTokenVariant::Register, "r1".to_string()),
+            (RawTokenVariant::Unsigned, "0xFF_FF".to_string()),
+            (RawTokenVariant::Signed, "-0b101".to_string()),
+            (RawTokenVariant::Char, "';'".to_string()),
+            (RawTokenVariant::Char, "'\\''".to_string()),
+            (RawTokenVariant::Opcode, "cmp".to_string()),
+            (RawTokenVariant::Unsigned, "1_000".to_string()),
+            (RawTokenVariant::Signed, "-7".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_unclosed_char() {
+        let err = Tokenizer::new("cmp r1 'ab'".to_string()).tokenize().unwrap_err();
+
+        assert!(matches!(err.variant, TokenizationErrorVariant::InvalidToken(ref text) if text == "'ab'"));
+        assert_eq!((err.line, err.column), (0, 7));
+    }
+
+    #[test]
+    fn test_recovery() {
+        let (tokens, errors) = Tokenizer::new("nop\ncmp r1 'ab'\nhlt\nmov r1 'x\n".to_string()).tokenize_all();
+
+        assert_eq!(tokens.iter().map(|token| (token.value.as_str(), token.line)).collect::<Vec<_>>(), [("nop", 0), ("hlt", 2)]);
+        assert_eq!(errors.iter().map(|err| (err.line, err.column)).collect::<Vec<_>>(), [(1, 7), (3, 7)]);
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(positions(".ascii \"a; b\" \"\\\"\" ; done"), [
+            (".ascii".to_string(), 0, 0),
+            ("\"a; b\"".to_string(), 0, 7),
+            ("\"\\\"\"".to_string(), 0, 14),
+        ]);
+    }
+
+    #[test]
+    fn test_operators() {
+        let tokens = Tokenizer::new("ldi r1 hi16(.a+4)<<2 -1 - -2".to_string()).tokenize().unwrap();
+        let tokens = tokens.into_iter().map(|token| (token.variant, token.value)).collect::<Vec<_>>();
+
+        assert_eq!(tokens, [
+            (RawTokenVariant::Opcode, "ldi".to_string()),
+            (RawTokenVariant::Register, "r1".to_string()),
+            (RawTokenVariant::Opcode, "hi16".to_string()),
+            (RawTokenVariant::Operator, "(".to_string()),
+            (RawTokenVariant::Label, ".a".to_string()),
+            (RawTokenVariant::Operator, "+".to_string()),
+            (RawTokenVariant::Unsigned, "4".to_string()),
+            (RawTokenVariant::Operator, ")".to_string()),
+            (RawTokenVariant::Operator, "<<".to_string()),
+            (RawTokenVariant::Unsigned, "2".to_string()),
+            (RawTokenVariant::Signed, "-1".to_string()),
+            (RawTokenVariant::Operator, "-".to_string()),
+            (RawTokenVariant::Signed, "-2".to_string()),
+        ]);
+    }
+
+    /// `$` followed by hexadecimal digits
+    #[derive(Debug)]
+    struct DollarHexProvider {
+        input: String,
+    }
+
+    impl TokenProvider for DollarHexProvider {
+        fn new() -> Self {
+            Self { input: String::new() }
+        }
+
+        fn reset(&mut self) {
+            self.input.clear();
+        }
+
+        fn give(&mut self, ch: char) -> ProviderResponse {
+            if (self.input.is_empty() && ch == '$') || (!self.input.is_empty() && ch.is_ascii_hexdigit()) {
+                self.input.push(ch);
+                ProviderResponse::Accepted
+            } else if self.input.len() > 1 && is_delimiter(ch) {
+                ProviderResponse::TokenFinished(RawTokenVariant::Unsigned, self.input.clone())
             } else {
-                self.position.column += 1;
+                ProviderResponse::Destroyed
             }
-            self.position.pos += 1;
         }
+
+        fn request_end(&mut self) -> Option<(RawTokenVariant, String)> {
+            (self.input.len() > 1).then(|| (RawTokenVariant::Unsigned, self.input.clone()))
+        }
+    }
+
+    #[test]
+    fn test_custom_provider() {
+        let tokens = Tokenizer::new("ldi r1 $fF\nldi r2 $".to_string()).with_provider::<DollarHexProvider>().tokenize_all();
+        let values = tokens.0.into_iter().map(|token| (token.variant, token.value)).collect::<Vec<_>>();
+
+        assert_eq!(values, [
+            (RawTokenVariant::Opcode, "ldi".to_string()),
+            (RawTokenVariant::Register, "r1".to_string()),
+            (RawTokenVariant::Unsigned, "$fF".to_string()),
+        ]);
+        assert!(matches!(tokens.1[..], [TokenizationError { line: 1, column: 7, variant: TokenizationErrorVariant::InvalidToken(ref text) }] if text == "$"));
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        assert_eq!(positions("/* ä */ .ascii \"ü\" ; é\nnop"), [
+            (".ascii".to_string(), 0, 8),
+            ("\"ü\"".to_string(), 0, 15),
+            ("nop".to_string(), 1, 0),
+        ]);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture` to see the throughput
+    #[test]
+    #[ignore]
+    fn bench_throughput() {
+        let line = ".loop\n    add r1 r2 0x1F ; count\n    ldi r3 'a' /* c */ \"text\" -5 (1 << 2)\n    b .loop\n";
+        let time = |lines: usize| {
+            let source = line.repeat(lines);
+            let start = Instant::now();
+            let (tokens, errors) = Tokenizer::new(source.clone()).tokenize_all();
+            let elapsed = start.elapsed();
+
+            assert!(errors.is_empty());
+            assert_eq!(tokens.len(), lines * 17);
+            println!("{} bytes in {:?}, {:.1} MB/s", source.len(), elapsed, source.len() as f64 / elapsed.as_secs_f64() / 1e6);
+            elapsed
+        };
+
+        let small = time(1 << 12);
+        let large = time(1 << 16);
+
+        // Sixteen times the input takes about sixteen times as long
+        assert!(large < small * 64, "Tokenizing doesn't scale linearly: {:?} then {:?}", small, large);
     }
 }
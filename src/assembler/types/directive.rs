@@ -0,0 +1,75 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Assembler directives. They're spelled like labels, so these names can't be used as labels.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum Directive {
+    Org,
+    Word,
+    Byte,
+    Ascii,
+    Align,
+    Space,
+    Equ,
+    Macro,
+    EndMacro,
+    Include,
+    Section,
+    Global,
+    Extern,
+}
+
+static DIRECTIVE_TABLE: &[(Directive, &str)] = &[
+    (Directive::Org,   ".org"),
+    (Directive::Word,  ".word"),
+    (Directive::Byte,  ".byte"),
+    (Directive::Ascii, ".ascii"),
+    (Directive::Align, ".align"),
+    (Directive::Space, ".space"),
+    (Directive::Equ,   ".equ"),
+    (Directive::Macro, ".macro"),
+    (Directive::EndMacro, ".endm"),
+    (Directive::Include, ".include"),
+    (Directive::Section, ".section"),
+    (Directive::Global, ".global"),
+    (Directive::Extern, ".extern"),
+];
+
+impl FromStr for Directive {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        DIRECTIVE_TABLE
+            .iter()
+            .find(|(_, name)| *name == value)
+            .map(|(directive, _)| *directive)
+            .ok_or_else(|| format!("Unknown directive: '{}'", value))
+    }
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, name) = DIRECTIVE_TABLE.iter().find(|(directive, _)| directive == self).expect("Directive missing from the table");
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(Directive::from_str(".org"), Ok(Directive::Org));
+        assert_eq!(Directive::from_str(".ascii"), Ok(Directive::Ascii));
+        assert!(Directive::from_str("org").is_err());
+        assert!(Directive::from_str(".loop").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        for (directive, name) in DIRECTIVE_TABLE {
+            assert_eq!(directive.to_string(), *name);
+        }
+    }
+}
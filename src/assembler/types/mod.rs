@@ -1,2 +1,6 @@
 pub mod opcode;
 pub mod register;
+pub mod directive;
+pub mod operator;
+pub mod pseudo;
+pub mod section;
@@ -27,6 +27,7 @@ pub enum Opcode {
     Push,
     Pop,
     Compare,
+    CompareSigned,
     CompareFloat,
     CompareDouble,
     Jump,
@@ -43,6 +44,12 @@ pub enum Opcode {
     BranchGreaterEqual,
     BranchNotEqual,
     BranchSmallerEqual,
+    Call,
+    Return,
+    Breakpoint,
+    SetTrapVector,
+    ReturnFromTrap,
+    Halt,
     ImmediateToFloat,
     ImmediateToDouble,
     IntegerToFloat,
@@ -51,6 +58,70 @@ pub enum Opcode {
     FloatToDouble,
     DoubleToInteger,
     DoubleToFloat,
+    FloatAdd,
+    FloatSubtract,
+    FloatMultiply,
+    FloatDivide,
+    FloatModulo,
+    FloatNegate,
+    FloatReciprocal,
+    FloatPower,
+    FloatExponential,
+    FloatRoot,
+    FloatSquareRoot,
+    FloatCubeRoot,
+    FloatSquare,
+    FloatCube,
+    FloatLogarithm,
+    FloatNaturalLogarithm,
+    FloatAbsolute,
+    FloatSine,
+    FloatCosine,
+    FloatTangent,
+    FloatArcsine,
+    FloatArccosine,
+    FloatArctangent,
+    FloatFloor,
+    FloatCeil,
+    FloatRound,
+    FloatMinimum,
+    FloatMaximum,
+    FloatSign,
+    FloatAbsoluteDifference,
+    FloatLoadInfinity,
+    FloatLoadNaN,
+    DoubleAdd,
+    DoubleSubtract,
+    DoubleMultiply,
+    DoubleDivide,
+    DoubleModulo,
+    DoubleNegate,
+    DoubleReciprocal,
+    DoublePower,
+    DoubleExponential,
+    DoubleRoot,
+    DoubleSquareRoot,
+    DoubleCubeRoot,
+    DoubleSquare,
+    DoubleCube,
+    DoubleLogarithm,
+    DoubleNaturalLogarithm,
+    DoubleAbsolute,
+    DoubleSine,
+    DoubleCosine,
+    DoubleTangent,
+    DoubleArcsine,
+    DoubleArccosine,
+    DoubleArctangent,
+    DoubleFloor,
+    DoubleCeil,
+    DoubleRound,
+    DoubleMinimum,
+    DoubleMaximum,
+    DoubleSign,
+    DoubleAbsoluteDifference,
+    DoubleLoadInfinity,
+    DoubleLoadNaN,
 }
 
 /// A static lookup table of `(Opcode, &str)` pairs.
@@ -81,6 +152,7 @@ static OPCODE_TABLE: &[(Opcode, &str)] = &[
     (Opcode::Push,              "push"),
     (Opcode::Pop,               "pop"),
     (Opcode::Compare,           "cmp"),
+    (Opcode::CompareSigned,     "scmp"),
     (Opcode::CompareFloat,      "fcmp"),
     (Opcode::CompareDouble,     "dcmp"),
     (Opcode::Jump,            "jmp"),
@@ -97,6 +169,12 @@ static OPCODE_TABLE: &[(Opcode, &str)] = &[
     (Opcode::BranchGreaterEqual,"bge"),
     (Opcode::BranchNotEqual,    "bne"),
     (Opcode::BranchSmallerEqual,"bse"),
+    (Opcode::Call,              "call"),
+    (Opcode::Return,            "ret"),
+    (Opcode::Breakpoint,        "brk"),
+    (Opcode::SetTrapVector,     "stv"),
+    (Opcode::ReturnFromTrap,    "rti"),
+    (Opcode::Halt,              "hlt"),
     (Opcode::ImmediateToFloat,  "immtof"),
     (Opcode::ImmediateToDouble, "immtod"),
     (Opcode::IntegerToFloat,    "itof"),
@@ -105,8 +183,85 @@ static OPCODE_TABLE: &[(Opcode, &str)] = &[
     (Opcode::FloatToDouble,     "ftod"),
     (Opcode::DoubleToInteger,   "dtoi"),
     (Opcode::DoubleToFloat,     "dtof"),
+    (Opcode::FloatAdd,             "fadd"),
+    (Opcode::FloatSubtract,        "fsub"),
+    (Opcode::FloatMultiply,        "fmul"),
+    (Opcode::FloatDivide,          "fdiv"),
+    (Opcode::FloatModulo,          "fmod"),
+    (Opcode::FloatNegate,          "fneg"),
+    (Opcode::FloatReciprocal,      "frec"),
+    (Opcode::FloatPower,           "fpow"),
+    (Opcode::FloatExponential,     "fexp"),
+    (Opcode::FloatRoot,            "froot"),
+    (Opcode::FloatSquareRoot,      "fsqrt"),
+    (Opcode::FloatCubeRoot,        "fcbrt"),
+    (Opcode::FloatSquare,          "fsq"),
+    (Opcode::FloatCube,            "fcube"),
+    (Opcode::FloatLogarithm,       "flog"),
+    (Opcode::FloatNaturalLogarithm,"fln"),
+    (Opcode::FloatAbsolute,        "fabs"),
+    (Opcode::FloatSine,            "fsin"),
+    (Opcode::FloatCosine,          "fcos"),
+    (Opcode::FloatTangent,         "ftan"),
+    (Opcode::FloatArcsine,         "fasin"),
+    (Opcode::FloatArccosine,       "facos"),
+    (Opcode::FloatArctangent,      "fatan"),
+    (Opcode::FloatFloor,           "ffloor"),
+    (Opcode::FloatCeil,            "fceil"),
+    (Opcode::FloatRound,           "fround"),
+    (Opcode::FloatMinimum,         "fmin"),
+    (Opcode::FloatMaximum,         "fmax"),
+    (Opcode::FloatSign,            "fsign"),
+    (Opcode::FloatAbsoluteDifference,"fdiff"),
+    (Opcode::FloatLoadInfinity,    "finf"),
+    (Opcode::FloatLoadNaN,         "fnan"),
+    (Opcode::DoubleAdd,            "dadd"),
+    (Opcode::DoubleSubtract,       "dsub"),
+    (Opcode::DoubleMultiply,       "dmul"),
+    (Opcode::DoubleDivide,         "ddiv"),
+    (Opcode::DoubleModulo,         "dmod"),
+    (Opcode::DoubleNegate,         "dneg"),
+    (Opcode::DoubleReciprocal,     "drec"),
+    (Opcode::DoublePower,          "dpow"),
+    (Opcode::DoubleExponential,    "dexp"),
+    (Opcode::DoubleRoot,           "droot"),
+    (Opcode::DoubleSquareRoot,     "dsqrt"),
+    (Opcode::DoubleCubeRoot,       "dcbrt"),
+    (Opcode::DoubleSquare,         "dsq"),
+    (Opcode::DoubleCube,           "dcube"),
+    (Opcode::DoubleLogarithm,      "dlog"),
+    (Opcode::DoubleNaturalLogarithm,"dln"),
+    (Opcode::DoubleAbsolute,       "dabs"),
+    (Opcode::DoubleSine,           "dsin"),
+    (Opcode::DoubleCosine,         "dcos"),
+    (Opcode::DoubleTangent,        "dtan"),
+    (Opcode::DoubleArcsine,        "dasin"),
+    (Opcode::DoubleArccosine,      "dacos"),
+    (Opcode::DoubleArctangent,     "datan"),
+    (Opcode::DoubleFloor,          "dfloor"),
+    (Opcode::DoubleCeil,           "dceil"),
+    (Opcode::DoubleRound,          "dround"),
+    (Opcode::DoubleMinimum,        "dmin"),
+    (Opcode::DoubleMaximum,        "dmax"),
+    (Opcode::DoubleSign,           "dsign"),
+    (Opcode::DoubleAbsoluteDifference,"ddiff"),
+    (Opcode::DoubleLoadInfinity,   "dinf"),
+    (Opcode::DoubleLoadNaN,        "dnan"),
 ];
 
+impl Opcode {
+    /// Every opcode, in the order of the opcode table
+    #[cfg(test)]
+    pub fn all() -> impl Iterator<Item = Opcode> {
+        OPCODE_TABLE.iter().map(|(opcode, _)| *opcode)
+    }
+
+    /// The names of every opcode, for suggestions when one is misspelled
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        OPCODE_TABLE.iter().map(|(_, name)| *name)
+    }
+}
+
 impl FromStr for Opcode {
     type Err = String;
 
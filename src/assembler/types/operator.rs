@@ -0,0 +1,84 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Operators and parentheses of assemble-time expressions
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum Operator {
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Modulo,
+    And,
+    Or,
+    Xor,
+    Not,
+    ShiftLeft,
+    ShiftRight,
+    OpenParen,
+    CloseParen,
+}
+
+static OPERATOR_TABLE: &[(Operator, &str)] = &[
+    (Operator::Plus,       "+"),
+    (Operator::Minus,      "-"),
+    (Operator::Times,      "*"),
+    (Operator::Divide,     "/"),
+    (Operator::Modulo,     "%"),
+    (Operator::And,        "&"),
+    (Operator::Or,         "|"),
+    (Operator::Xor,        "^"),
+    (Operator::Not,        "~"),
+    (Operator::ShiftLeft,  "<<"),
+    (Operator::ShiftRight, ">>"),
+    (Operator::OpenParen,  "("),
+    (Operator::CloseParen, ")"),
+];
+
+impl Operator {
+    /// How tightly a binary operator binds, higher binds tighter. `None` if it isn't a binary operator.
+    pub fn precedence(&self) -> Option<u8> {
+        match self {
+            Operator::Or => Some(1),
+            Operator::Xor => Some(2),
+            Operator::And => Some(3),
+            Operator::ShiftLeft | Operator::ShiftRight => Some(4),
+            Operator::Plus | Operator::Minus => Some(5),
+            Operator::Times | Operator::Divide | Operator::Modulo => Some(6),
+            Operator::Not | Operator::OpenParen | Operator::CloseParen => None,
+        }
+    }
+}
+
+impl FromStr for Operator {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        OPERATOR_TABLE
+            .iter()
+            .find(|(_, name)| *name == value)
+            .map(|(operator, _)| *operator)
+            .ok_or_else(|| format!("Unknown operator: '{}'", value))
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, name) = OPERATOR_TABLE.iter().find(|(operator, _)| operator == self).expect("Operator missing from the table");
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        for (operator, name) in OPERATOR_TABLE {
+            assert_eq!(Operator::from_str(name), Ok(*operator));
+            assert_eq!(operator.to_string(), *name);
+        }
+        assert!(Operator::from_str("<").is_err());
+    }
+}
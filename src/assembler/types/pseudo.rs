@@ -0,0 +1,67 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Instructions the assembler writes as one or more real instructions
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum Pseudo {
+    LoadConstant,
+    LoadAddress,
+    Increment,
+    Decrement,
+    Negate,
+    Clear,
+}
+
+static PSEUDO_TABLE: &[(Pseudo, &str)] = &[
+    (Pseudo::LoadConstant, "li"),
+    (Pseudo::LoadAddress,  "la"),
+    (Pseudo::Increment,    "inc"),
+    (Pseudo::Decrement,    "dec"),
+    (Pseudo::Negate,       "neg"),
+    (Pseudo::Clear,        "clr"),
+];
+
+impl Pseudo {
+    /// The names of every pseudo-instruction, for suggestions when one is misspelled
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        PSEUDO_TABLE.iter().map(|(_, name)| *name)
+    }
+}
+
+impl FromStr for Pseudo {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        PSEUDO_TABLE
+            .iter()
+            .find(|(_, name)| *name == value)
+            .map(|(pseudo, _)| *pseudo)
+            .ok_or_else(|| format!("Unknown pseudo-instruction: '{}'", value))
+    }
+}
+
+impl fmt::Display for Pseudo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, name) = PSEUDO_TABLE.iter().find(|(pseudo, _)| pseudo == self).expect("Pseudo-instruction missing from the table");
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(Pseudo::from_str("li"), Ok(Pseudo::LoadConstant));
+        assert_eq!(Pseudo::from_str("clr"), Ok(Pseudo::Clear));
+        assert!(Pseudo::from_str("ldi").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        for (pseudo, name) in PSEUDO_TABLE {
+            assert_eq!(pseudo.to_string(), *name);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Parts of a program that are laid out separately, chosen with `.section`
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, PartialOrd, Ord)]
+pub enum Section {
+    /// Instructions
+    Text,
+    /// Data with initial values
+    Data,
+    /// Space that starts out zeroed. Nothing but its size is stored.
+    Bss,
+}
+
+static SECTION_TABLE: &[(Section, &str)] = &[
+    (Section::Text, "text"),
+    (Section::Data, "data"),
+    (Section::Bss,  "bss"),
+];
+
+impl Section {
+    /// Every section in the order they're placed in an image
+    pub const ALL: [Section; 3] = [Section::Text, Section::Data, Section::Bss];
+
+    /// Sections start at multiples of this, so `.align` up to it holds wherever a section is placed
+    pub const ALIGNMENT: usize = 16;
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl FromStr for Section {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        SECTION_TABLE
+            .iter()
+            .find(|(_, name)| *name == value)
+            .map(|(section, _)| *section)
+            .ok_or_else(|| format!("Unknown section: '{}'", value))
+    }
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, name) = SECTION_TABLE.iter().find(|(section, _)| section == self).expect("Section missing from the table");
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(Section::from_str("bss"), Ok(Section::Bss));
+        assert!(Section::from_str(".text").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        for (section, name) in SECTION_TABLE {
+            assert_eq!(section.to_string(), *name);
+        }
+    }
+}
@@ -0,0 +1,1079 @@
+mod run_outcome;
+mod trap;
+
+pub use run_outcome::{RunOutcome, RunResult};
+pub use trap::Trap;
+use crate::isa::{self, ArithmeticOperation, BitwiseOperation, Condition, Conversion, Decoded, FloatOperation, Semantics, ShiftOperation, Source, Sources};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+const INSTR_PTR: usize = 15;
+const STACK_PTR: usize = 14;
+
+/// Bytes of memory. Programs are loaded at address 0 and have to fit into it.
+pub const MEMORY_SIZE: usize = 4096;
+
+/// Size of a single stack slot in bytes. Push and pop always move whole 64-bit registers.
+const STACK_SLOT: u64 = 8;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Cpu {
+    pub regs: [u64; 16],
+    pub memory: Vec<u8>,
+    pub privileged: bool,
+    pub flags: Flags,
+    pub stack: StackConfig,
+    /// Base address of the trap vector table, `None` while no table is installed
+    pub trap_vector: Option<u64>,
+    /// Set by `hlt`, execution doesn't continue until this is cleared
+    pub halted: bool,
+    /// Addresses at which `run` stops before executing the instruction
+    pub breakpoints: HashSet<u64>,
+    /// Breakpoint the last run stopped at, passed over when the next run starts there
+    resume_at: Option<u64>,
+    next_instr_ptr: Option<u64>,
+}
+
+/// Which way the stack pointer moves when a value is pushed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StackDirection {
+    /// Push decrements the stack pointer. The stack pointer points at the last pushed value.
+    Downward,
+    /// Push increments the stack pointer. The stack pointer points at the next free slot.
+    Upward,
+}
+
+/// Describes the memory region reserved for the stack.
+///
+/// The stack occupies the addresses between `base` and `limit`.
+/// Pushing past `limit` is a stack overflow, popping past `base` is a stack underflow.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct StackConfig {
+    /// Value of the stack pointer while the stack is empty
+    pub base: u64,
+    /// Address the stack must not grow past
+    pub limit: u64,
+    pub direction: StackDirection,
+}
+
+impl StackConfig {
+    /// Bytes the stack may grow by from its base
+    const SIZE: u64 = 1024;
+
+    /// A 1 KiB stack growing from `base` in `direction`
+    pub fn new(base: u64, direction: StackDirection) -> Self {
+        let limit = match direction {
+            StackDirection::Downward => base.saturating_sub(Self::SIZE),
+            StackDirection::Upward => base.saturating_add(Self::SIZE),
+        };
+
+        Self { base, limit, direction }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Flags {
+    // Set when an arithmetic operation results in a carry out of the most significant bit
+    // Example: 0xFF + 0x01 = 0x100 (carry = true)
+    // Useful for:
+    // - Multi-precision arithmetic (working with numbers bigger than 64 bits)
+    // - Detecting unsigned overflow
+    carry: bool,
+
+    // Set when the result of an operation is exactly zero
+    // Example: 5 - 5 = 0 (zero = true)
+    // Useful for:
+    // - Equality comparisons
+    // - Loop termination conditions
+    zero: bool,
+
+    // Set when the result of an operation has its most significant bit set (is negative)
+    // Example: -1 in two's complement (negative = true)
+    // Useful for:
+    // - Sign detection
+    // - Signed number comparisons
+    negative: bool,
+
+    // Set when an arithmetic operation exceeds the valid range for signed numbers
+    // Example: 0x7FFFFFFF + 1 (overflow = true because it went from positive to negative)
+    // Useful for:
+    // - Detecting signed arithmetic overflow
+    // - Error checking in calculations
+    overflow: bool,
+
+    // Set when a comparison shows the first operand is greater than the second
+    // Example: CMP 5, 3 (greater = true)
+    // Useful for:
+    // - Conditional branching
+    // - Implementing sorting algorithms
+    greater: bool,
+
+    // Set when a comparison shows the operands are equal
+    // Example: CMP 4, 4 (equal = true)
+    // Useful for:
+    // - Conditional branching
+    // - Loop termination
+    equal: bool,
+
+    // Set when a comparison shows the first operand is less than the second
+    // Example: CMP 3, 5 (less = true)
+    // Useful for:
+    // - Conditional branching
+    // - Implementing sorting algorithms
+    smaller: bool,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        let mut cpu = Self {
+            regs: [0; 16],
+            memory: vec![0; MEMORY_SIZE],
+            privileged: true,
+            flags: Flags::default(),
+            stack: StackConfig::new(MEMORY_SIZE as u64, StackDirection::Downward),
+            trap_vector: None,
+            halted: false,
+            breakpoints: HashSet::new(),
+            resume_at: None,
+            next_instr_ptr: None,
+        };
+        cpu.regs[STACK_PTR] = cpu.stack.base;
+        cpu
+    }
+}
+
+impl Cpu {
+    /// Executes up to `cycles` instructions and reports why execution stopped.
+    ///
+    /// Execution stops early when the CPU halts, a trap can't be handled or a breakpoint is reached.
+    /// Breakpoints are checked before an instruction executes, including the first one of a run.
+    /// Calling `run` again resumes from the breakpoint it stopped at. After an unhandled `brk` the
+    /// instruction pointer is moved past it for the same reason.
+    pub fn run(&mut self, cycles: u64) -> RunResult {
+        self.run_traced(cycles, |_| {})
+    }
+
+    /// Like [`Cpu::run`], but calls `trace` before each instruction executes
+    pub fn run_traced(&mut self, cycles: u64, mut trace: impl FnMut(&Cpu)) -> RunResult {
+        let mut executed = 0;
+        let resume_at = self.resume_at.take();
+
+        let outcome = loop {
+            if self.halted {
+                break RunOutcome::Halted;
+            }
+
+            if executed == cycles {
+                break RunOutcome::CycleBudgetExhausted;
+            }
+
+            let address = self.regs[INSTR_PTR];
+
+            if self.breakpoints.contains(&address) && (executed > 0 || resume_at != Some(address)) {
+                self.resume_at = Some(address);
+                break RunOutcome::Breakpoint { address };
+            }
+
+            trace(self);
+
+            match self.step() {
+                Ok(()) => executed += 1,
+                Err(Trap::Breakpoint) => {
+                    self.regs[INSTR_PTR] += 4;
+                    executed += 1;
+                    break RunOutcome::Breakpoint { address };
+                }
+                Err(trap) => break RunOutcome::Trapped(trap),
+            }
+        };
+
+        RunResult { outcome, executed }
+    }
+
+    /// Fetches and executes the instruction the instruction pointer points at
+    pub fn step(&mut self) -> Result<(), Trap> {
+        match self.fetch_instruction(self.regs[INSTR_PTR]) {
+            Ok(instruction) => self.exec(instruction),
+            Err(trap) => self.raise(trap),
+        }
+    }
+
+    pub fn set_instruction_ptr(&mut self, value: u64) {
+        self.regs[INSTR_PTR] = value;
+    }
+
+    pub fn instruction_ptr(&self) -> u64 {
+        self.regs[INSTR_PTR]
+    }
+
+    /// Replaces the stack configuration and resets the stack pointer to the new base
+    pub fn set_stack(&mut self, stack: StackConfig) {
+        self.stack = stack;
+        self.regs[STACK_PTR] = stack.base;
+    }
+
+    /// Executes a single instruction.
+    ///
+    /// A trap raised by the instruction is delivered to the guest's trap handler if one is installed.
+    /// Otherwise the trap is returned and the instruction pointer keeps pointing at the faulting instruction.
+    pub fn exec(&mut self, instruction: u32) -> Result<(), Trap> {
+        self.next_instr_ptr = None;
+
+        let result = match isa::decode(instruction) {
+            None => Err(Trap::IllegalInstruction { instruction }),
+            Some(decoded) => self.execute(instruction, &decoded),
+        };
+
+        if let Err(trap) = result {
+            return self.raise(trap);
+        }
+
+        if let Some(next_instr_ptr) = self.next_instr_ptr {
+            self.regs[INSTR_PTR] = next_instr_ptr;
+        } else {
+            self.regs[INSTR_PTR] += 4;
+        }
+
+        Ok(())
+    }
+
+    /// Delivers a trap to the handler registered in the trap vector table.
+    ///
+    /// The handler is entered in privileged mode with two values pushed onto the stack:
+    /// first the address of the instruction after the faulting one, then a status word holding the trap code
+    /// in the low bits and the previous privilege level in bit 63. `rti` undoes this.
+    /// If no handler is installed or the trap frame can't be pushed, the trap is returned unhandled.
+    fn raise(&mut self, trap: Trap) -> Result<(), Trap> {
+        let Some(table) = self.trap_vector else {
+            return Err(trap);
+        };
+
+        let handler = match table.checked_add(trap.code() * 8).map(|entry| self.read_u64(entry)) {
+            Some(Ok(handler)) if handler != 0 => handler,
+            _ => return Err(trap),
+        };
+
+        let stack_ptr = self.regs[STACK_PTR];
+        let return_address = self.regs[INSTR_PTR].wrapping_add(4);
+        let status = trap.code() | (self.privileged as u64) << 63;
+
+        if self.push(return_address).and_then(|_| self.push(status)).is_err() {
+            self.regs[STACK_PTR] = stack_ptr;
+            return Err(trap);
+        }
+
+        self.privileged = true;
+        self.regs[INSTR_PTR] = handler;
+        Ok(())
+    }
+
+    fn require_privilege(&self, instruction: u32) -> Result<(), Trap> {
+        if self.privileged {
+            Ok(())
+        } else {
+            Err(Trap::PrivilegeViolation { instruction })
+        }
+    }
+
+    /// Runs a decoded instruction, the semantics from the ISA table select what it does
+    fn execute(&mut self, instruction: u32, decoded: &Decoded) -> Result<(), Trap> {
+        use Semantics::*;
+
+        match decoded.form.semantics {
+            Nop => Ok(()),
+            Arithmetic(operation, sources) => self.execute_arithmetic_operations(operation, sources, decoded),
+            Bitwise(operation) => self.execute_bitwise_operations(operation, decoded),
+            Shift(operation, amount) => self.execute_shift_and_rotate(operation, amount, decoded),
+            semantics @ (Move | LoadImmediate | Load(_) | Store(_) | Push | Pop) => self.execute_data_movement_memory_stack(semantics, decoded),
+            semantics @ (Compare(_) | CompareSigned(_) | CompareFloat | CompareDouble) => self.execute_comparison(semantics, decoded),
+            Branch(condition, target) => self.execute_branching(condition, target, decoded),
+            Convert(conversion) => self.execute_conversion(conversion, decoded),
+            Float(operation) => self.execute_floating(operation, decoded),
+            Double(operation) => self.execute_double(operation, decoded),
+            semantics @ (Call(_) | Return) => self.execute_subroutine(semantics, decoded),
+            semantics @ (Breakpoint | SetTrapVector | ReturnFromTrap | Halt) => self.execute_system(semantics, instruction, decoded),
+        }
+    }
+
+    fn execute_arithmetic_operations(&mut self, operation: ArithmeticOperation, sources: Sources, decoded: &Decoded) -> Result<(), Trap> {
+        // Type for an arithmetic function: (self, dest, lhs, rhs)
+        type ArithmeticOperationFn = fn(&mut Cpu, usize, u64, u64) -> Result<(), Trap>;
+
+        let dest = decoded.register('A');
+        let b = self.regs[decoded.register('B')];
+        let c = self.regs[decoded.register('C')];
+
+        // The immediate of signed division is sign-extended
+        let imm = match operation {
+            ArithmeticOperation::DivideSigned => decoded.signed_field('I') as u64,
+            _ => decoded.field('I') as u64,
+        };
+
+        // decide which CPU method we’ll call (add, sub, mul, etc.)
+        let arith_fn: ArithmeticOperationFn = match operation {
+            ArithmeticOperation::Add => Self::addition,
+            ArithmeticOperation::Subtract => Self::subtraction,
+            ArithmeticOperation::Multiply => Self::multiplication,
+            ArithmeticOperation::Divide => Self::unsigned_division,
+            ArithmeticOperation::DivideSigned => Self::signed_division,
+        };
+
+        // decide which operands (lhs, rhs) to pass
+        let (lhs, rhs) = match sources {
+            Sources::Registers => (b, c),
+            Sources::RegisterImmediate => (b, imm),
+            Sources::ImmediateRegister => (imm, b),
+        };
+
+        // call the chosen arithmetic function with the decoded operands
+        arith_fn(self, dest, lhs, rhs)
+    }
+
+    fn execute_bitwise_operations(&mut self, operation: BitwiseOperation, decoded: &Decoded) -> Result<(), Trap> {
+        let b = self.regs[decoded.register('B')];
+        let c = self.regs[decoded.register('C')];
+
+        self.regs[decoded.register('A')] = match operation {
+            BitwiseOperation::And => b & c,
+            BitwiseOperation::Or => b | c,
+            BitwiseOperation::Xor => b ^ c,
+            BitwiseOperation::Nand => !(b & c),
+            BitwiseOperation::Nor => !(b | c),
+            BitwiseOperation::Xnor => !(b ^ c),
+            BitwiseOperation::Not => !b,
+        };
+
+        Ok(())
+    }
+
+    fn execute_shift_and_rotate(&mut self, operation: ShiftOperation, amount: Source, decoded: &Decoded) -> Result<(), Trap> {
+        let b = self.regs[decoded.register('B')];
+
+        let amount = match amount {
+            Source::Register => self.regs[decoded.register('C')],
+            Source::Immediate => decoded.field('I') as u64,
+        };
+
+        // Shifting by 64 or more clears the register, rotating wraps around
+        let shift = u32::try_from(amount).unwrap_or(u32::MAX);
+
+        self.regs[decoded.register('A')] = match operation {
+            ShiftOperation::RightShift => b.checked_shr(shift).unwrap_or(0),
+            ShiftOperation::LeftShift => b.checked_shl(shift).unwrap_or(0),
+            ShiftOperation::RightRoll => b.rotate_right((amount % 64) as u32),
+            ShiftOperation::LeftRoll => b.rotate_left((amount % 64) as u32),
+        };
+
+        Ok(())
+    }
+
+    fn execute_data_movement_memory_stack(&mut self, semantics: Semantics, decoded: &Decoded) -> Result<(), Trap> {
+        let dest = decoded.register('A');
+        let b = self.regs[decoded.register('B')];
+        let imm = decoded.field('I');
+        let section = decoded.field('S') as u8;
+
+        let address = |source| match source {
+            Source::Register => b,
+            Source::Immediate => imm as u64,
+        };
+
+        match semantics {
+            Semantics::Move => self.regs[dest] = b,
+            Semantics::LoadImmediate => {
+                let chunk = decoded.field('C') as u8;
+                self.regs[dest] = Self::set_chunk(self.regs[dest], imm as u16, chunk);
+            }
+            Semantics::Load(source) => self.regs[dest] = Self::set_byte(self.regs[dest], self.read_byte(address(source))?, section),
+            Semantics::Store(source) => self.write_byte(address(source), Self::get_byte(self.regs[dest], section))?,
+            Semantics::Push => self.push(self.regs[dest])?,
+            Semantics::Pop => self.regs[dest] = self.pop()?,
+            _ => unreachable!("Not a data movement instruction: {semantics:?}"),
+        }
+
+        Ok(())
+    }
+
+    fn execute_comparison(&mut self, semantics: Semantics, decoded: &Decoded) -> Result<(), Trap> {
+        let reg1 = self.regs[decoded.register('A')];
+        let reg2 = self.regs[decoded.register('B')];
+        let imm = decoded.field('I') as u64;
+        let signed_imm = decoded.signed_field('I');
+
+        fn compare<T: Ord>(cpu: &mut Cpu, a: T, b: T) {
+            let cmp = a.cmp(&b);
+            cpu.flags.greater = cmp == Ordering::Greater;
+            cpu.flags.equal = cmp == Ordering::Equal;
+            cpu.flags.smaller = cmp == Ordering::Less;
+        }
+
+        fn partial_compare<T: PartialOrd>(cpu: &mut Cpu, a: T, b: T) {
+            match a.partial_cmp(&b) {
+                None => {
+                    cpu.flags.greater = false;
+                    cpu.flags.equal = false;
+                    cpu.flags.smaller = false;
+                }
+                Some(cmp) => {
+                    cpu.flags.greater = cmp == Ordering::Greater;
+                    cpu.flags.equal = cmp == Ordering::Equal;
+                    cpu.flags.smaller = cmp == Ordering::Less;
+                }
+            }
+        }
+
+        match semantics {
+            Semantics::Compare(Sources::Registers) => compare(self, reg1, reg2),
+            Semantics::Compare(Sources::RegisterImmediate) => compare(self, reg1, imm),
+            Semantics::Compare(Sources::ImmediateRegister) => compare(self, imm, reg1),
+            Semantics::CompareSigned(Sources::Registers) => compare(self, reg1 as i64, reg2 as i64),
+            Semantics::CompareSigned(Sources::RegisterImmediate) => compare(self, reg1 as i64, signed_imm),
+            Semantics::CompareSigned(Sources::ImmediateRegister) => compare(self, signed_imm, reg1 as i64),
+            Semantics::CompareFloat => partial_compare(self, f32::from_bits(reg1 as u32), f32::from_bits(reg2 as u32)),
+            Semantics::CompareDouble => partial_compare(self, f64::from_bits(reg1), f64::from_bits(reg2)),
+            _ => unreachable!("Not a comparison: {semantics:?}"),
+        }
+
+        Ok(())
+    }
+
+    fn execute_branching(&mut self, condition: Condition, target: Source, decoded: &Decoded) -> Result<(), Trap> {
+        let taken = match condition {
+            Condition::Always => true,
+            Condition::Greater => self.flags.greater,
+            Condition::Equal => self.flags.equal,
+            Condition::Smaller => self.flags.smaller,
+            Condition::GreaterEqual => self.flags.greater || self.flags.equal,
+            Condition::NotEqual => !self.flags.equal,
+            Condition::SmallerEqual => self.flags.smaller || self.flags.equal,
+        };
+
+        if taken {
+            self.next_instr_ptr = Some(self.branch_target(target, decoded));
+        }
+
+        Ok(())
+    }
+
+    /// Address a branch or call jumps to. Offsets are counted in instructions and relative to the current one.
+    fn branch_target(&self, target: Source, decoded: &Decoded) -> u64 {
+        let offset = match target {
+            Source::Register => self.regs[decoded.register('A')],
+            Source::Immediate => decoded.signed_field('I') as u64,
+        };
+
+        self.regs[INSTR_PTR].wrapping_add(offset.wrapping_mul(4))
+    }
+
+    fn execute_conversion(&mut self, conversion: Conversion, decoded: &Decoded) -> Result<(), Trap> {
+        let reg = decoded.register('A');
+        let imm = decoded.signed_field('I');
+        let a = self.regs[reg];
+
+        self.regs[reg] = match conversion {
+            Conversion::ImmediateToFloat => (imm as f32).to_bits() as u64,
+            Conversion::ImmediateToDouble => (imm as f64).to_bits(),
+            Conversion::IntegerToFloat => (a as i64 as f32).to_bits() as u64,
+            Conversion::IntegerToDouble => (a as i64 as f64).to_bits(),
+            Conversion::FloatToInteger => f32::from_bits(a as u32) as i64 as u64,
+            Conversion::FloatToDouble => (f32::from_bits(a as u32) as f64).to_bits(),
+            Conversion::DoubleToInteger => f64::from_bits(a) as i64 as u64,
+            Conversion::DoubleToFloat => (f64::from_bits(a) as f32).to_bits() as u64,
+        };
+
+        Ok(())
+    }
+
+    fn execute_floating(&mut self, operation: FloatOperation, decoded: &Decoded) -> Result<(), Trap> {
+        use FloatOperation::*;
+
+        let b = f32::from_bits(self.regs[decoded.register('B')] as u32);
+        let c = f32::from_bits(self.regs[decoded.register('C')] as u32);
+
+        self.regs[decoded.register('A')] = match operation {
+            Add => b + c,
+            Subtract => b - c,
+            Multiply => b * c,
+            Divide => b / c,
+            Modulo => b % c,
+            Negate => -b,
+            Reciprocal => 1.0 / b,
+            Power => b.powf(c),
+            Exponential => f32::exp(b),
+            Root => b.nth_root(c),
+            SquareRoot => b.sqrt(),
+            CubeRoot => b.nth_root(3.0),
+            Square => b * b,
+            Cube => b * b * b,
+            Logarithm => b.log(c),
+            NaturalLogarithm => f32::ln(b),
+            Absolute => b.abs(),
+            Sine => b.sin(),
+            Cosine => b.cos(),
+            Tangent => b.tan(),
+            Arcsine => b.asin(),
+            Arccosine => b.acos(),
+            Arctangent => b.atan(),
+            Floor => b.floor(),
+            Ceil => b.ceil(),
+            Round => b.round(),
+            Minimum => b.min(c),
+            Maximum => b.max(c),
+            Sign => b.signum(),
+            AbsoluteDifference => (b - c).abs(),
+            LoadInfinity => f32::INFINITY,
+            LoadNaN => f32::NAN,
+        }.to_bits() as u64;
+
+        Ok(())
+    }
+
+    fn execute_double(&mut self, operation: FloatOperation, decoded: &Decoded) -> Result<(), Trap> {
+        use FloatOperation::*;
+
+        let b = f64::from_bits(self.regs[decoded.register('B')]);
+        let c = f64::from_bits(self.regs[decoded.register('C')]);
+
+        self.regs[decoded.register('A')] = match operation {
+            Add => b + c,
+            Subtract => b - c,
+            Multiply => b * c,
+            Divide => b / c,
+            Modulo => b % c,
+            Negate => -b,
+            Reciprocal => 1.0 / b,
+            Power => b.powf(c),
+            Exponential => f64::exp(b),
+            Root => b.nth_root(c),
+            SquareRoot => b.sqrt(),
+            CubeRoot => b.nth_root(3.0),
+            Square => b * b,
+            Cube => b * b * b,
+            Logarithm => b.log(c),
+            NaturalLogarithm => f64::ln(b),
+            Absolute => b.abs(),
+            Sine => b.sin(),
+            Cosine => b.cos(),
+            Tangent => b.tan(),
+            Arcsine => b.asin(),
+            Arccosine => b.acos(),
+            Arctangent => b.atan(),
+            Floor => b.floor(),
+            Ceil => b.ceil(),
+            Round => b.round(),
+            Minimum => b.min(c),
+            Maximum => b.max(c),
+            Sign => b.signum(),
+            AbsoluteDifference => (b - c).abs(),
+            LoadInfinity => f64::INFINITY,
+            LoadNaN => f64::NAN,
+        }.to_bits();
+
+        Ok(())
+    }
+
+    fn execute_subroutine(&mut self, semantics: Semantics, decoded: &Decoded) -> Result<(), Trap> {
+        match semantics {
+            Semantics::Call(target) => {
+                let return_address = self.regs[INSTR_PTR].wrapping_add(4);
+                let target = self.branch_target(target, decoded);
+                self.push(return_address)?;
+                self.next_instr_ptr = Some(target);
+            }
+            Semantics::Return => self.next_instr_ptr = Some(self.pop()?),
+            _ => unreachable!("Not a subroutine instruction: {semantics:?}"),
+        }
+
+        Ok(())
+    }
+
+    fn execute_system(&mut self, semantics: Semantics, instruction: u32, decoded: &Decoded) -> Result<(), Trap> {
+        match semantics {
+            Semantics::Breakpoint => return Err(Trap::Breakpoint),
+            Semantics::SetTrapVector => {
+                self.require_privilege(instruction)?;
+                let table = self.regs[decoded.register('A')];
+                self.trap_vector = (table != 0).then_some(table);
+            }
+            Semantics::ReturnFromTrap => {
+                self.require_privilege(instruction)?;
+                // Both slots are read first, so a fault leaves the stack pointer where it was
+                let (status, sp) = self.peek(self.regs[STACK_PTR])?;
+                let (return_address, sp) = self.peek(sp)?;
+                self.regs[STACK_PTR] = sp;
+                self.privileged = status >> 63 == 1;
+                self.next_instr_ptr = Some(return_address);
+            }
+            Semantics::Halt => self.halted = true,
+            _ => unreachable!("Not a system instruction: {semantics:?}"),
+        }
+
+        Ok(())
+    }
+
+    /// Helper function to perform an unsigned arithmetic operation and set flags
+    fn exec_arithmetic_operation(&mut self, reg_a: usize, left_hand_side: u64, right_hand_side: u64, op_unsigned: fn(u64, u64) -> (u64, bool), op_signed: fn(i64, i64) -> (i64, bool)) {
+        let (result, carry) = op_unsigned(left_hand_side, right_hand_side);
+        // For signed overflow detection, we perform the operation in signed space
+        let (signed_result, signed_overflow) = op_signed(left_hand_side as i64, right_hand_side as i64);
+
+        self.regs[reg_a] = result;
+        self.flags.carry = carry;
+        self.flags.zero = result == 0;
+        self.flags.negative = signed_result < 0;
+        self.flags.overflow = signed_overflow;
+    }
+
+    fn addition(&mut self, dest_reg: usize, lhs: u64, rhs: u64) -> Result<(), Trap> {
+        self.exec_arithmetic_operation(dest_reg, lhs, rhs, u64::overflowing_add, i64::overflowing_add);
+        Ok(())
+    }
+
+    fn subtraction(&mut self, dest_reg: usize, lhs: u64, rhs: u64) -> Result<(), Trap> {
+        self.exec_arithmetic_operation(dest_reg, lhs, rhs, u64::overflowing_sub, i64::overflowing_sub);
+        Ok(())
+    }
+
+    fn multiplication(&mut self, dest_reg: usize, lhs: u64, rhs: u64) -> Result<(), Trap> {
+        self.exec_arithmetic_operation(dest_reg, lhs, rhs, u64::overflowing_mul, i64::overflowing_mul);
+        Ok(())
+    }
+
+    fn unsigned_division(&mut self, dest_reg: usize, lhs: u64, rhs: u64) -> Result<(), Trap> {
+        let result = lhs.checked_div(rhs).ok_or(Trap::DivideByZero)?;
+
+        self.regs[dest_reg] = result;
+        self.flags.carry = false;
+        self.flags.zero = result == 0;
+        self.flags.negative = (result as i64) < 0;
+        self.flags.overflow = false; // Unsigned division doesn't typically overflow
+        Ok(())
+    }
+
+    fn signed_division(&mut self, dest_reg: usize, lhs: u64, rhs: u64) -> Result<(), Trap> {
+        let lhs = lhs as i64;
+        let rhs = rhs as i64;
+
+        if rhs == 0 {
+            return Err(Trap::DivideByZero);
+        }
+
+        // Check the potential overflow case: i64::MIN / -1
+        // This would cause an overflow in signed division since the result can't be represented.
+        if lhs == i64::MIN && rhs == -1 {
+            // In many architectures this causes an arithmetic exception.
+            // Here, we treat it as overflow.
+            self.regs[dest_reg] = lhs.wrapping_div(rhs) as u64;
+            self.flags.overflow = true;
+        } else {
+            self.regs[dest_reg] = (lhs / rhs) as u64;
+            self.flags.overflow = false;
+        }
+
+        let result = self.regs[dest_reg] as i64;
+        self.flags.carry = false;
+        self.flags.zero = result == 0;
+        self.flags.negative = result < 0;
+        Ok(())
+    }
+
+    fn set_chunk(reg: u64, data: u16, chunk: u8) -> u64 {
+        assert!(chunk < 4);
+        let shift = chunk * 16;
+        let mask: u64 = 0xFFFF << shift;
+        (reg & !mask) | ((data as u64) << shift)
+    }
+
+    fn set_byte(reg: u64, data: u8, byte: u8) -> u64 {
+        assert!(byte < 8);
+        let shift = byte * 8;
+        let mask: u64 = 0xFF << shift;
+        (reg & !mask) | ((data as u64) << shift)
+    }
+
+    fn get_byte(reg: u64, byte: u8) -> u8 {
+        assert!(byte < 8);
+        let shift = byte * 8;
+        ((reg & (0xFF << shift)) >> shift) as u8
+    }
+
+    /// Pushes a 64-bit value onto the stack, respecting the configured growth direction
+    fn push(&mut self, value: u64) -> Result<(), Trap> {
+        let sp = self.regs[STACK_PTR];
+
+        if !sp.is_multiple_of(STACK_SLOT) {
+            return Err(Trap::MisalignedAccess { address: sp });
+        }
+
+        let (new_sp, address) = match self.stack.direction {
+            StackDirection::Downward => match sp.checked_sub(STACK_SLOT) {
+                Some(new_sp) if new_sp >= self.stack.limit => (new_sp, new_sp),
+                _ => return Err(Trap::StackOverflow),
+            },
+            StackDirection::Upward => match sp.checked_add(STACK_SLOT) {
+                Some(new_sp) if new_sp <= self.stack.limit => (new_sp, sp),
+                _ => return Err(Trap::StackOverflow),
+            },
+        };
+
+        self.write_u64(address, value)?;
+        self.regs[STACK_PTR] = new_sp;
+        Ok(())
+    }
+
+    /// Pops a 64-bit value from the stack
+    fn pop(&mut self) -> Result<u64, Trap> {
+        let (value, new_sp) = self.peek(self.regs[STACK_PTR])?;
+        self.regs[STACK_PTR] = new_sp;
+        Ok(value)
+    }
+
+    /// The value a pop with the stack pointer at `sp` returns and the stack pointer after it, without changing any state
+    fn peek(&self, sp: u64) -> Result<(u64, u64), Trap> {
+        if !sp.is_multiple_of(STACK_SLOT) {
+            return Err(Trap::MisalignedAccess { address: sp });
+        }
+
+        let (new_sp, address) = match self.stack.direction {
+            StackDirection::Downward => match sp.checked_add(STACK_SLOT) {
+                Some(new_sp) if new_sp <= self.stack.base => (new_sp, sp),
+                _ => return Err(Trap::StackUnderflow),
+            },
+            StackDirection::Upward => match sp.checked_sub(STACK_SLOT) {
+                Some(new_sp) if new_sp >= self.stack.base => (new_sp, new_sp),
+                _ => return Err(Trap::StackUnderflow),
+            },
+        };
+
+        Ok((self.read_u64(address)?, new_sp))
+    }
+
+    fn read_byte(&self, address: u64) -> Result<u8, Trap> {
+        let range = self.memory_range(address, 1)?;
+        Ok(self.memory[range.start])
+    }
+
+    fn write_byte(&mut self, address: u64, value: u8) -> Result<(), Trap> {
+        let range = self.memory_range(address, 1)?;
+        self.memory[range.start] = value;
+        Ok(())
+    }
+
+    /// Reads 8 bytes in big-endian order, the same order instructions are stored in
+    fn read_u64(&self, address: u64) -> Result<u64, Trap> {
+        let range = self.memory_range(address, 8)?;
+        Ok(u64::from_be_bytes(self.memory[range].try_into().unwrap()))
+    }
+
+    /// Writes 8 bytes in big-endian order
+    fn write_u64(&mut self, address: u64, value: u64) -> Result<(), Trap> {
+        let range = self.memory_range(address, 8)?;
+        self.memory[range].copy_from_slice(&value.to_be_bytes());
+        Ok(())
+    }
+
+    fn memory_range(&self, address: u64, len: usize) -> Result<std::ops::Range<usize>, Trap> {
+        usize::try_from(address).ok()
+            .and_then(|start| Some(start..start.checked_add(len)?))
+            .filter(|range| range.end <= self.memory.len())
+            .ok_or(Trap::OutOfBounds { address })
+    }
+
+    #[inline(always)] // for performance
+    fn fetch_instruction(&self, address: u64) -> Result<u32, Trap> {
+        if !address.is_multiple_of(4) {
+            return Err(Trap::MisalignedAccess { address });
+        }
+        let range = self.memory_range(address, 4)?;
+        Ok(u32::from_be_bytes(self.memory[range].try_into().unwrap()))
+    }
+}
+
+trait NthRoot {
+    fn nth_root(self, n: Self) -> Self;
+}
+
+impl NthRoot for f32 {
+    fn nth_root(self, n: f32) -> Self {
+        // If n is zero or negative, return NaN.
+        if n <= 0.0 {
+            return f32::NAN;
+        }
+
+        // Calculate the nth root as value^(1/n).
+        self.powf(1.0 / n)
+    }
+}
+
+impl NthRoot for f64 {
+    fn nth_root(self, n: f64) -> Self {
+        // If n is zero or negative, return NaN.
+        if n <= 0.0 {
+            return f64::NAN;
+        }
+
+        // Calculate the nth root as value^(1/n).
+        self.powf(1.0 / n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn stress_test() {
+        let iterations = 10_000_000;
+
+        let mut cpu = Cpu::default();
+        let fill = iterations.to_string().len();
+
+        for i in 0..iterations {
+            let random_instr = rand::random::<u32>();
+
+            match cpu.exec(random_instr) {
+                Ok(()) => println!("{:0fill$}. {:#010x}", i+1, random_instr),
+                Err(trap) => println!("{:0fill$}. {:#010x} trapped: {}", i+1, random_instr, trap),
+            }
+        }
+
+        println!("Registers after stress test: {:?}", cpu.regs);
+        println!("Flags after stress test: {:?}", cpu.flags);
+    }
+
+    const PUSH_R1: u32 = 0x4100_0006;
+    const POP_R2: u32 = 0x4200_0007;
+
+    #[test]
+    fn test_push_pop() {
+        let mut cpu = Cpu::default();
+        cpu.regs[1] = 0x0123_4567_89AB_CDEF;
+
+        cpu.exec(PUSH_R1).unwrap();
+        assert_eq!(cpu.regs[STACK_PTR], cpu.stack.base - 8);
+        assert_eq!(cpu.memory[cpu.memory.len() - 8..], 0x0123_4567_89AB_CDEF_u64.to_be_bytes());
+
+        cpu.exec(POP_R2).unwrap();
+        assert_eq!(cpu.regs[2], 0x0123_4567_89AB_CDEF);
+        assert_eq!(cpu.regs[STACK_PTR], cpu.stack.base);
+    }
+
+    #[test]
+    fn test_stack_upward() {
+        let mut cpu = Cpu::default();
+        cpu.set_stack(StackConfig { base: 0x100, limit: 0x110, direction: StackDirection::Upward });
+        cpu.regs[1] = 42;
+
+        cpu.exec(PUSH_R1).unwrap();
+        assert_eq!(cpu.regs[STACK_PTR], 0x108);
+        assert_eq!(cpu.memory[0x100..0x108], 42_u64.to_be_bytes());
+
+        cpu.exec(POP_R2).unwrap();
+        assert_eq!(cpu.regs[2], 42);
+        assert_eq!(cpu.regs[STACK_PTR], 0x100);
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let mut cpu = Cpu::default();
+        cpu.set_stack(StackConfig { base: 0x110, limit: 0x100, direction: StackDirection::Downward });
+
+        cpu.exec(PUSH_R1).unwrap();
+        cpu.exec(PUSH_R1).unwrap();
+        assert_eq!(cpu.regs[STACK_PTR], 0x100);
+
+        // The stack is full, the stack pointer must not move any further
+        assert_eq!(cpu.exec(PUSH_R1), Err(Trap::StackOverflow));
+        assert_eq!(cpu.regs[STACK_PTR], 0x100);
+    }
+
+    #[test]
+    fn test_call_ret() {
+        const CALL_PLUS_3: u32 = 0xA000_3001;
+        const CALL_R1: u32 = 0xA100_0000;
+        const RET: u32 = 0xA000_0002;
+
+        let mut cpu = Cpu::default();
+        cpu.set_instruction_ptr(0x40);
+
+        cpu.exec(CALL_PLUS_3).unwrap();
+        assert_eq!(cpu.regs[INSTR_PTR], 0x4C);
+        assert_eq!(cpu.regs[STACK_PTR], cpu.stack.base - 8);
+
+        cpu.regs[1] = -2_i64 as u64;
+        cpu.exec(CALL_R1).unwrap();
+        assert_eq!(cpu.regs[INSTR_PTR], 0x44);
+        assert_eq!(cpu.regs[STACK_PTR], cpu.stack.base - 16);
+
+        cpu.exec(RET).unwrap();
+        assert_eq!(cpu.regs[INSTR_PTR], 0x50);
+        cpu.exec(RET).unwrap();
+        assert_eq!(cpu.regs[INSTR_PTR], 0x44);
+        assert_eq!(cpu.regs[STACK_PTR], cpu.stack.base);
+    }
+
+    #[test]
+    fn test_stack_underflow() {
+        let mut cpu = Cpu::default();
+        cpu.regs[2] = 7;
+
+        assert_eq!(cpu.exec(POP_R2), Err(Trap::StackUnderflow));
+        assert_eq!(cpu.regs[2], 7);
+        assert_eq!(cpu.regs[STACK_PTR], cpu.stack.base);
+    }
+
+    const DIV_R1_R2_R3: u32 = 0x1123_0007;
+    const BRK: u32 = 0xB000_0000;
+    const STV_R1: u32 = 0xB100_0001;
+    const RTI: u32 = 0xB000_0002;
+
+    #[test]
+    fn test_unhandled_traps() {
+        let mut cpu = Cpu::default();
+        cpu.set_instruction_ptr(0x40);
+
+        assert_eq!(cpu.exec(DIV_R1_R2_R3), Err(Trap::DivideByZero));
+        assert_eq!(cpu.exec(0xF000_0000), Err(Trap::IllegalInstruction { instruction: 0xF000_0000 }));
+        assert_eq!(cpu.exec(0x6000_000E), Err(Trap::IllegalInstruction { instruction: 0x6000_000E }));
+        assert_eq!(cpu.exec(BRK), Err(Trap::Breakpoint));
+
+        // Unhandled traps leave the instruction pointer at the faulting instruction
+        assert_eq!(cpu.regs[INSTR_PTR], 0x40);
+    }
+
+    #[test]
+    fn test_memory_traps() {
+        let mut cpu = Cpu::default();
+        cpu.regs[2] = 4096;
+
+        // ldr r1 r2 0
+        assert_eq!(cpu.exec(0x4120_0002), Err(Trap::OutOfBounds { address: 4096 }));
+
+        cpu.set_instruction_ptr(2);
+        assert_eq!(cpu.step(), Err(Trap::MisalignedAccess { address: 2 }));
+        cpu.set_instruction_ptr(4096);
+        assert_eq!(cpu.step(), Err(Trap::OutOfBounds { address: 4096 }));
+    }
+
+    #[test]
+    fn test_trap_handler() {
+        let mut cpu = Cpu::default();
+
+        // Install a vector table at 0x200 with a divide by zero handler at 0x300
+        let entry = 0x200 + Trap::DivideByZero.code() as usize * 8;
+        cpu.memory[entry..entry + 8].copy_from_slice(&0x300_u64.to_be_bytes());
+        cpu.regs[1] = 0x200;
+        cpu.exec(STV_R1).unwrap();
+        assert_eq!(cpu.trap_vector, Some(0x200));
+
+        cpu.privileged = false;
+        cpu.set_instruction_ptr(0x40);
+        cpu.exec(DIV_R1_R2_R3).unwrap();
+        assert_eq!(cpu.regs[INSTR_PTR], 0x300);
+        assert!(cpu.privileged);
+
+        cpu.exec(RTI).unwrap();
+        assert_eq!(cpu.regs[INSTR_PTR], 0x44);
+        assert!(!cpu.privileged);
+        assert_eq!(cpu.regs[STACK_PTR], cpu.stack.base);
+
+        // Traps without a handler entry are still returned
+        assert_eq!(cpu.exec(BRK), Err(Trap::Breakpoint));
+    }
+
+    #[test]
+    fn test_faulting_rti() {
+        let mut cpu = Cpu::default();
+        cpu.set_instruction_ptr(0x40);
+
+        // Only the status word is on the stack, the return address would be past the top of memory
+        let sp = cpu.stack.base - 8;
+        cpu.regs[STACK_PTR] = sp;
+
+        assert_eq!(cpu.exec(RTI), Err(Trap::StackUnderflow));
+        assert_eq!(cpu.regs[STACK_PTR], sp);
+        assert_eq!(cpu.regs[INSTR_PTR], 0x40);
+        assert!(cpu.privileged);
+    }
+
+    #[test]
+    fn test_privilege_violation() {
+        let mut cpu = Cpu { privileged: false, ..Cpu::default() };
+
+        assert_eq!(cpu.exec(STV_R1), Err(Trap::PrivilegeViolation { instruction: STV_R1 }));
+        assert_eq!(cpu.exec(RTI), Err(Trap::PrivilegeViolation { instruction: RTI }));
+        assert_eq!(cpu.trap_vector, None);
+    }
+
+    fn load(cpu: &mut Cpu, program: &[u32]) {
+        for (i, instruction) in program.iter().enumerate() {
+            cpu.memory[i * 4..i * 4 + 4].copy_from_slice(&instruction.to_be_bytes());
+        }
+    }
+
+    const ADD_R1_R1_1: u32 = 0x1110_0011;
+    const HLT: u32 = 0xB000_0003;
+    const B_MINUS_1: u32 = 0x6FFF_F001;
+
+    #[test]
+    fn test_run_until_halt() {
+        let mut cpu = Cpu::default();
+        load(&mut cpu, &[ADD_R1_R1_1, ADD_R1_R1_1, HLT, ADD_R1_R1_1]);
+
+        assert_eq!(cpu.run(100), RunResult { outcome: RunOutcome::Halted, executed: 3 });
+        assert_eq!(cpu.regs[1], 2);
+        assert_eq!(cpu.instruction_ptr(), 12);
+
+        // A halted CPU doesn't execute anything
+        assert_eq!(cpu.run(100), RunResult { outcome: RunOutcome::Halted, executed: 0 });
+    }
+
+    #[test]
+    fn test_run_budget_and_traps() {
+        let mut cpu = Cpu::default();
+        load(&mut cpu, &[ADD_R1_R1_1, B_MINUS_1]);
+
+        assert_eq!(cpu.run(10), RunResult { outcome: RunOutcome::CycleBudgetExhausted, executed: 10 });
+        assert_eq!(cpu.regs[1], 5);
+
+        let mut cpu = Cpu::default();
+        load(&mut cpu, &[ADD_R1_R1_1, DIV_R1_R2_R3]);
+
+        assert_eq!(cpu.run(10), RunResult { outcome: RunOutcome::Trapped(Trap::DivideByZero), executed: 1 });
+        assert_eq!(cpu.instruction_ptr(), 4);
+    }
+
+    #[test]
+    fn test_run_breakpoints() {
+        let mut cpu = Cpu::default();
+        load(&mut cpu, &[ADD_R1_R1_1, BRK, ADD_R1_R1_1, ADD_R1_R1_1, HLT]);
+        cpu.breakpoints.insert(12);
+
+        assert_eq!(cpu.run(100), RunResult { outcome: RunOutcome::Breakpoint { address: 4 }, executed: 2 });
+        assert_eq!(cpu.run(100), RunResult { outcome: RunOutcome::Breakpoint { address: 12 }, executed: 1 });
+        assert_eq!(cpu.run(100), RunResult { outcome: RunOutcome::Halted, executed: 2 });
+        assert_eq!(cpu.regs[1], 3);
+    }
+
+    #[test]
+    fn test_breakpoint_on_first_instruction() {
+        let mut cpu = Cpu::default();
+        load(&mut cpu, &[ADD_R1_R1_1, HLT]);
+        cpu.breakpoints.insert(0);
+
+        assert_eq!(cpu.run(100), RunResult { outcome: RunOutcome::Breakpoint { address: 0 }, executed: 0 });
+        assert_eq!(cpu.run(100), RunResult { outcome: RunOutcome::Halted, executed: 2 });
+        assert_eq!(cpu.regs[1], 1);
+    }
+
+    #[test]
+    fn test_run_traced() {
+        let mut cpu = Cpu::default();
+        load(&mut cpu, &[ADD_R1_R1_1, ADD_R1_R1_1, HLT]);
+
+        let mut addresses = Vec::new();
+        assert_eq!(cpu.run_traced(100, |cpu| addresses.push(cpu.instruction_ptr())).executed, 3);
+        assert_eq!(addresses, [0, 4, 8]);
+    }
+}
@@ -0,0 +1,33 @@
+use super::trap::Trap;
+use std::fmt::Display;
+
+/// The reason `Cpu::run` stopped executing
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RunOutcome {
+    /// A `hlt` instruction was executed
+    Halted,
+    /// The given number of cycles was executed without stopping
+    CycleBudgetExhausted,
+    /// A trap was raised and no handler was installed for it
+    Trapped(Trap),
+    /// A `brk` instruction or a breakpoint set by the host was reached at `address`
+    Breakpoint { address: u64 },
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RunResult {
+    pub outcome: RunOutcome,
+    /// Number of instructions that finished executing, including `hlt` and `brk`
+    pub executed: u64,
+}
+
+impl Display for RunOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunOutcome::Halted => write!(f, "Halted"),
+            RunOutcome::CycleBudgetExhausted => write!(f, "Cycle budget exhausted"),
+            RunOutcome::Trapped(trap) => write!(f, "Trapped: {}", trap),
+            RunOutcome::Breakpoint { address } => write!(f, "Breakpoint at {:#x}", address),
+        }
+    }
+}
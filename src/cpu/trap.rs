@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+/// A fault or exception raised while executing an instruction.
+///
+/// Every trap has a fixed number (see [`Trap::code`]) which selects its entry in the trap vector table.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Trap {
+    /// The instruction doesn't encode any known operation
+    IllegalInstruction { instruction: u32 },
+    /// An address wasn't aligned to the size of the access
+    MisalignedAccess { address: u64 },
+    /// An address lies outside of memory
+    OutOfBounds { address: u64 },
+    /// An integer division had a divisor of zero
+    DivideByZero,
+    /// A privileged instruction was executed in unprivileged mode
+    PrivilegeViolation { instruction: u32 },
+    /// A `brk` instruction was executed
+    Breakpoint,
+    /// A push would grow the stack past its limit
+    StackOverflow,
+    /// A pop was attempted on an empty stack
+    StackUnderflow,
+}
+
+impl Trap {
+    /// Index of this trap in the trap vector table
+    pub fn code(&self) -> u64 {
+        match self {
+            Trap::IllegalInstruction { .. } => 0,
+            Trap::MisalignedAccess { .. } => 1,
+            Trap::OutOfBounds { .. } => 2,
+            Trap::DivideByZero => 3,
+            Trap::PrivilegeViolation { .. } => 4,
+            Trap::Breakpoint => 5,
+            Trap::StackOverflow => 6,
+            Trap::StackUnderflow => 7,
+        }
+    }
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trap::IllegalInstruction { instruction } => write!(f, "Illegal instruction {:#010x}", instruction),
+            Trap::MisalignedAccess { address } => write!(f, "Misaligned access at address {:#x}", address),
+            Trap::OutOfBounds { address } => write!(f, "Memory access out of bounds at address {:#x}", address),
+            Trap::DivideByZero => write!(f, "Division by zero"),
+            Trap::PrivilegeViolation { instruction } => write!(f, "Privileged instruction {:#010x} executed in unprivileged mode", instruction),
+            Trap::Breakpoint => write!(f, "Breakpoint"),
+            Trap::StackOverflow => write!(f, "Stack overflow"),
+            Trap::StackUnderflow => write!(f, "Stack underflow"),
+        }
+    }
+}
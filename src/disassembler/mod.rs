@@ -0,0 +1,197 @@
+use crate::assembler::types::directive::Directive;
+use crate::isa::{self, InstructionForm, Operand, Semantics, Source, FORMS};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+/// A machine word decoded back into a mnemonic and operands
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub form: &'static InstructionForm,
+    pub operands: Vec<Value>,
+}
+
+/// The value of one operand, formatted the way the assembler reads it
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Value {
+    Register(u8),
+    Unsigned(u32),
+    Signed(i64),
+    Label(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Register(reg) => write!(f, "r{}", reg),
+            Value::Unsigned(value) => write!(f, "{}", value),
+            Value::Signed(value) => write!(f, "{}", value),
+            Value::Label(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.form.opcode)?;
+        for operand in &self.operands {
+            write!(f, " {}", operand)?;
+        }
+        Ok(())
+    }
+}
+
+impl Instruction {
+    /// Offset in instructions an immediate branch or call jumps by
+    pub fn branch_offset(&self) -> Option<i64> {
+        match self.form.semantics {
+            Semantics::Branch(_, Source::Immediate) | Semantics::Call(Source::Immediate) => match self.operands.first() {
+                Some(Value::Signed(offset)) => Some(*offset),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Decodes a single instruction word. Branch targets stay numeric offsets.
+pub fn disassemble(word: u32) -> Option<Instruction> {
+    let decoded = isa::decode(word)?;
+
+    let operands = decoded.form.operands.iter().map(|operand| match *operand {
+        Operand::Register(field) => Value::Register(decoded.register(field) as u8),
+        Operand::Unsigned(field) => Value::Unsigned(decoded.field(field)),
+        Operand::Signed(field) | Operand::Label(field) => Value::Signed(decoded.signed_field(field)),
+    }).collect();
+
+    Some(Instruction { form: decoded.form, operands })
+}
+
+/// Disassembles a whole program into assembly source.
+///
+/// Branches and calls landing inside the image are rewritten to jump to generated labels.
+/// Words that aren't instructions are written as `.word` data.
+pub fn disassemble_image(words: &[u32]) -> String {
+    let mut instructions = words.iter().map(|&word| disassemble(word)).collect::<Vec<_>>();
+
+    // Instruction index of every branch target inside the image, numbered in address order
+    let mut labels = BTreeMap::new();
+
+    for (i, instruction) in instructions.iter().enumerate() {
+        if let Some(offset) = instruction.as_ref().and_then(Instruction::branch_offset) {
+            let target = i as i64 + offset;
+            if (0..words.len() as i64).contains(&target) {
+                labels.insert(target as usize, String::new());
+            }
+        }
+    }
+
+    // Directives are spelled like labels, so their names are skipped
+    let mut names = (0..).map(label_name).filter(|name| name.parse::<Directive>().is_err());
+
+    for name in labels.values_mut() {
+        *name = names.next().unwrap();
+    }
+
+    for (i, instruction) in instructions.iter_mut().enumerate() {
+        let Some(instruction) = instruction else { continue };
+
+        let Some(target) = instruction.branch_offset().map(|offset| i as i64 + offset) else { continue };
+
+        let Some(name) = usize::try_from(target).ok().and_then(|target| labels.get(&target)) else { continue };
+
+        // The label spelling of the same encoding
+        if let Some(form) = FORMS.iter().find(|form| form.bits == instruction.form.bits && matches!(form.operands, [Operand::Label(_)])) {
+            instruction.form = form;
+            instruction.operands = vec![Value::Label(name.clone())];
+        }
+    }
+
+    let mut source = String::new();
+
+    for (i, (word, instruction)) in words.iter().zip(&instructions).enumerate() {
+        if let Some(name) = labels.get(&i) {
+            source.push_str(name);
+            source.push('\n');
+        }
+
+        match instruction {
+            Some(instruction) => source.push_str(&format!("    {}\n", instruction)),
+            None => source.push_str(&format!("    .word {:#010x}\n", word)),
+        }
+    }
+
+    source
+}
+
+/// Generated labels only use letters: `.a` to `.z`, then `.aa`, `.ab` and so on
+fn label_name(mut n: usize) -> String {
+    let mut letters = Vec::new();
+
+    loop {
+        letters.push((b'a' + (n % 26) as u8) as char);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+
+    format!(".{}", letters.iter().rev().collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble::assemble;
+
+    #[test]
+    fn test_disassemble() {
+        let program = assemble("add r1 r2 7\nsdiv r3 r4 65535\nldi r5 4660 3\nfsqrt r1 r2\nhlt".to_string()).unwrap();
+        let text = program.iter().map(|&word| disassemble(word).unwrap().to_string()).collect::<Vec<_>>();
+
+        assert_eq!(text, ["add r1 r2 7", "sdiv r3 r4 -1", "ldi r5 4660 3", "fsqrt r1 r2", "hlt"]);
+    }
+
+    #[test]
+    fn test_illegal_word() {
+        assert!(disassemble(0xF000_0000).is_none());
+        assert!(disassemble(0x0000_0001).is_none());
+    }
+
+    #[test]
+    fn test_every_form_round_trips() {
+        for form in &FORMS {
+            let values = (1..=form.operands.len() as u32).collect::<Vec<_>>();
+            let word = form.encode(&values);
+            let source = disassemble(word).unwrap().to_string();
+
+            assert_eq!(assemble(source.clone()).unwrap(), [word], "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_labels() {
+        let source = "call .sub\nhlt\n.sub\ncmp r1 r2\nbe .done\njmp r3\nb .sub\n.done\nret";
+        let program = assemble(source.to_string()).unwrap();
+        let text = disassemble_image(&program);
+
+        assert_eq!(text, "    call .a\n    hlt\n.a\n    cmp r1 r2\n    be .b\n    jmp r3\n    b .a\n.b\n    ret\n");
+        assert_eq!(assemble(text).unwrap(), program);
+    }
+
+    #[test]
+    fn test_targets_outside_image() {
+        let branch = crate::assembler::constructor::Instruction::Branch { offset: either::Right(-4) }.assemble();
+        let text = disassemble_image(&[branch, 0xF000_0000]);
+        assert_eq!(text, "    b -4\n    .word 0xf0000000\n");
+        assert_eq!(assemble(text).unwrap(), [branch, 0xF000_0000]);
+    }
+
+    #[test]
+    fn test_label_names() {
+        assert_eq!(label_name(0), ".a");
+        assert_eq!(label_name(25), ".z");
+        assert_eq!(label_name(26), ".aa");
+        assert_eq!(label_name(27), ".ab");
+        assert_eq!(label_name(26 + 26 * 26), ".aaa");
+    }
+}
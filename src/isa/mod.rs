@@ -0,0 +1,231 @@
+//! The FALCON instruction set, described as data.
+//!
+//! Every instruction form is listed once in [`FORMS`]. The assembler's token patterns, the CPU's decoder and
+//! [`InstructionForm::encode`] are all derived from that table, so encodings can only change in one place.
+
+mod semantics;
+mod table;
+
+pub use semantics::*;
+pub use table::FORMS;
+
+use crate::assembler::types::opcode::Opcode;
+use std::sync::OnceLock;
+
+/// One way to write an instruction in assembly, together with its encoding
+#[derive(Debug)]
+pub struct InstructionForm {
+    pub opcode: Opcode,
+    /// Bits from most to least significant. `0` and `1` are fixed, letters are bits of the named field.
+    pub bits: &'static str,
+    /// Operands in source order, each naming the field it's encoded in
+    pub operands: &'static [Operand],
+    pub semantics: Semantics,
+}
+
+/// An operand of an instruction form and the field holding it
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Operand {
+    Register(char),
+    Unsigned(char),
+    Signed(char),
+    /// Encoded as the offset in instructions from the instruction to the label
+    Label(char),
+}
+
+impl Operand {
+    pub fn field(&self) -> char {
+        match *self {
+            Operand::Register(field) | Operand::Unsigned(field) | Operand::Signed(field) | Operand::Label(field) => field,
+        }
+    }
+}
+
+/// Fixed bits and field masks of a form, computed from its bit string
+#[derive(Debug)]
+struct Layout {
+    mask: u32,
+    value: u32,
+    fields: Vec<(char, u32)>,
+}
+
+impl Layout {
+    fn new(bits: &str) -> Self {
+        let mut layout = Layout { mask: 0, value: 0, fields: Vec::new() };
+
+        for (i, ch) in bits.chars().filter(|ch| *ch != ' ').enumerate() {
+            let bit = 1 << (31 - i);
+
+            match ch {
+                '0' => layout.mask |= bit,
+                '1' => {
+                    layout.mask |= bit;
+                    layout.value |= bit;
+                }
+                field => match layout.fields.iter_mut().find(|(name, _)| *name == field) {
+                    Some((_, mask)) => *mask |= bit,
+                    None => layout.fields.push((field, bit)),
+                },
+            }
+        }
+
+        layout
+    }
+
+    fn field(&self, name: char) -> Option<u32> {
+        self.fields.iter().find(|(field, _)| *field == name).map(|&(_, mask)| mask)
+    }
+}
+
+/// Layouts of all forms, grouped by the opcode in the top four bits
+struct Decoder {
+    layouts: Vec<Layout>,
+    groups: [Vec<usize>; 16],
+}
+
+fn decoder() -> &'static Decoder {
+    static DECODER: OnceLock<Decoder> = OnceLock::new();
+
+    DECODER.get_or_init(|| {
+        let layouts = FORMS.iter().map(|form| Layout::new(form.bits)).collect::<Vec<_>>();
+        let mut groups: [Vec<usize>; 16] = Default::default();
+
+        for (i, layout) in layouts.iter().enumerate() {
+            groups[(layout.value >> 28) as usize].push(i);
+        }
+
+        Decoder { layouts, groups }
+    })
+}
+
+/// An instruction word matched against its form
+#[derive(Debug, Copy, Clone)]
+pub struct Decoded {
+    pub form: &'static InstructionForm,
+    layout: &'static Layout,
+    word: u32,
+}
+
+impl Decoded {
+    /// Raw value of a field, 0 if the form doesn't have it
+    pub fn field(&self, name: char) -> u32 {
+        self.layout.field(name).map_or(0, |mask| (self.word & mask) >> mask.trailing_zeros())
+    }
+
+    /// Value of a field, sign-extended from the field's width
+    pub fn signed_field(&self, name: char) -> i64 {
+        let unused = self.layout.field(name).map_or(32, |mask| 32 - mask.count_ones());
+        ((self.field(name) << unused) as i32 >> unused) as i64
+    }
+
+    pub fn register(&self, name: char) -> usize {
+        self.field(name) as usize
+    }
+}
+
+/// Finds the form an instruction word is encoded with. All fixed bits, including unused zeros, have to match.
+pub fn decode(word: u32) -> Option<Decoded> {
+    let decoder = decoder();
+
+    decoder.groups[(word >> 28) as usize].iter()
+        .map(|&i| (&FORMS[i], &decoder.layouts[i]))
+        .find(|(_, layout)| word & layout.mask == layout.value)
+        .map(|(form, layout)| Decoded { form, layout, word })
+}
+
+impl InstructionForm {
+    /// Encodes the form with one value per operand. Values are truncated to the width of their field.
+    pub fn encode(&self, values: &[u32]) -> u32 {
+        assert_eq!(values.len(), self.operands.len(), "Wrong number of operands for {:?}", self.opcode);
+
+        let layout = Layout::new(self.bits);
+
+        self.operands.iter().zip(values).fold(layout.value, |word, (operand, &value)| {
+            let mask = layout.field(operand.field()).expect("Operand field missing from the bit layout");
+            word | (value << mask.trailing_zeros() & mask)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_layouts() {
+        for form in &FORMS {
+            let layout = Layout::new(form.bits);
+            let fields = layout.fields.iter().map(|&(name, _)| name).collect::<HashSet<_>>();
+            let operands = form.operands.iter().map(Operand::field).collect::<HashSet<_>>();
+
+            assert_eq!(form.bits.replace(' ', "").len(), 32, "{:?}: wrong length", form.bits);
+            assert_eq!(fields, operands, "{:?}: fields don't match the operands", form.bits);
+            assert_eq!(layout.mask >> 28, 0xF, "{:?}: opcode isn't fixed", form.bits);
+
+            for &(name, mask) in &layout.fields {
+                let shifted = mask >> mask.trailing_zeros();
+                assert_eq!(shifted & (shifted + 1), 0, "{:?}: field {} isn't contiguous", form.bits, name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_forms_dont_overlap() {
+        // Two forms overlap when some word matches both. That's only allowed for spellings of the same instruction.
+        for (i, a) in FORMS.iter().enumerate() {
+            let a_layout = Layout::new(a.bits);
+
+            for b in &FORMS[i + 1..] {
+                let b_layout = Layout::new(b.bits);
+                let common = a_layout.mask & b_layout.mask;
+
+                if a_layout.value & common == b_layout.value & common {
+                    assert_eq!(a.bits, b.bits, "{:?} and {:?} overlap", a.opcode, b.opcode);
+                    assert_eq!(a.semantics, b.semantics, "{:?} and {:?} share bits", a.opcode, b.opcode);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_opcode_has_a_form() {
+        for opcode in Opcode::all() {
+            assert!(FORMS.iter().any(|form| form.opcode == opcode), "No form for {:?}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for form in &FORMS {
+            // Every field gets a distinct value so swapped fields are noticed
+            let values = (1..=form.operands.len() as u32).collect::<Vec<_>>();
+            let word = form.encode(&values);
+            let decoded = decode(word).unwrap();
+
+            assert_eq!(decoded.form.semantics, form.semantics);
+
+            for (operand, value) in form.operands.iter().zip(values) {
+                assert_eq!(decoded.field(operand.field()), value, "{:?}: field {}", form.bits, operand.field());
+            }
+        }
+    }
+
+    #[test]
+    fn test_signed_field() {
+        let branch = FORMS.iter().find(|form| form.semantics == Semantics::Branch(Condition::Always, Source::Immediate)).unwrap();
+        let decoded = decode(branch.encode(&[-3_i16 as u16 as u32])).unwrap();
+
+        assert_eq!(decoded.signed_field('I'), -3);
+        assert_eq!(decoded.field('I'), 0xFFFD);
+    }
+
+    #[test]
+    fn test_unused_bits_must_be_zero() {
+        assert!(decode(0x0000_0000).is_some());
+        assert!(decode(0x0000_0001).is_none());
+        assert!(decode(0x1123_0000).is_some());
+        assert!(decode(0x1123_1000).is_none());
+        assert!(decode(0xF000_0000).is_none());
+    }
+}
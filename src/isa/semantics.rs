@@ -0,0 +1,132 @@
+/// What an instruction form does when it's executed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Semantics {
+    Nop,
+    Arithmetic(ArithmeticOperation, Sources),
+    Bitwise(BitwiseOperation),
+    Shift(ShiftOperation, Source),
+    Move,
+    LoadImmediate,
+    Load(Source),
+    Store(Source),
+    Push,
+    Pop,
+    Compare(Sources),
+    CompareSigned(Sources),
+    CompareFloat,
+    CompareDouble,
+    Branch(Condition, Source),
+    Convert(Conversion),
+    Float(FloatOperation),
+    Double(FloatOperation),
+    Call(Source),
+    Return,
+    Breakpoint,
+    SetTrapVector,
+    ReturnFromTrap,
+    Halt,
+}
+
+/// Where the two inputs of an operation come from
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Sources {
+    Registers,
+    RegisterImmediate,
+    ImmediateRegister,
+}
+
+/// Where the single input of an operation (shift amount, address, offset) comes from
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Source {
+    Register,
+    Immediate,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ArithmeticOperation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    DivideSigned,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BitwiseOperation {
+    And,
+    Or,
+    Xor,
+    Nand,
+    Nor,
+    Xnor,
+    Not,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ShiftOperation {
+    RightShift,
+    LeftShift,
+    RightRoll,
+    LeftRoll,
+}
+
+/// Flags that must be set for a branch to be taken
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Condition {
+    Always,
+    Greater,
+    Equal,
+    Smaller,
+    GreaterEqual,
+    NotEqual,
+    SmallerEqual,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Conversion {
+    ImmediateToFloat,
+    ImmediateToDouble,
+    IntegerToFloat,
+    IntegerToDouble,
+    FloatToInteger,
+    FloatToDouble,
+    DoubleToInteger,
+    DoubleToFloat,
+}
+
+/// Operations shared by the float and double groups
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FloatOperation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Negate,
+    Reciprocal,
+    Power,
+    Exponential,
+    Root,
+    SquareRoot,
+    CubeRoot,
+    Square,
+    Cube,
+    Logarithm,
+    NaturalLogarithm,
+    Absolute,
+    Sine,
+    Cosine,
+    Tangent,
+    Arcsine,
+    Arccosine,
+    Arctangent,
+    Floor,
+    Ceil,
+    Round,
+    Minimum,
+    Maximum,
+    Sign,
+    AbsoluteDifference,
+    LoadInfinity,
+    LoadNaN,
+}
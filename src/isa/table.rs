@@ -0,0 +1,193 @@
+use crate::assembler::types::opcode::Opcode as Opc;
+use super::{InstructionForm, Operand::*, semantics::*};
+use ArithmeticOperation as Arith;
+use BitwiseOperation as Bit;
+use ShiftOperation as Shi;
+use Sources::*;
+use Semantics::*;
+
+const fn form(opcode: Opc, bits: &'static str, operands: &'static [super::Operand], semantics: Semantics) -> InstructionForm {
+    InstructionForm { opcode, bits, operands, semantics }
+}
+
+/// Every instruction form of the FALCON ISA.
+///
+/// Forms sharing an encoding are alternative spellings of the same instruction, the first one is the canonical one.
+pub static FORMS: [InstructionForm; 153] = [
+    // Nop
+    form(Opc::Nop, "0000 0000 0000 0000 0000 0000 0000 0000", &[], Nop),
+
+    // Arithmetic
+    form(Opc::Add, "0001 AAAA BBBB CCCC 0000 0000 0000 0000", &[Register('A'), Register('B'), Register('C')], Arithmetic(Arith::Add, Registers)),
+    form(Opc::Add, "0001 AAAA BBBB IIII IIII IIII IIII 0001", &[Register('A'), Register('B'), Unsigned('I')], Arithmetic(Arith::Add, RegisterImmediate)),
+    form(Opc::Subtract, "0001 AAAA BBBB CCCC 0000 0000 0000 0010", &[Register('A'), Register('B'), Register('C')], Arithmetic(Arith::Subtract, Registers)),
+    form(Opc::Subtract, "0001 AAAA BBBB IIII IIII IIII IIII 0011", &[Register('A'), Register('B'), Unsigned('I')], Arithmetic(Arith::Subtract, RegisterImmediate)),
+    form(Opc::Subtract, "0001 AAAA BBBB IIII IIII IIII IIII 0100", &[Register('A'), Unsigned('I'), Register('B')], Arithmetic(Arith::Subtract, ImmediateRegister)),
+    form(Opc::Multiply, "0001 AAAA BBBB CCCC 0000 0000 0000 0101", &[Register('A'), Register('B'), Register('C')], Arithmetic(Arith::Multiply, Registers)),
+    form(Opc::Multiply, "0001 AAAA BBBB IIII IIII IIII IIII 0110", &[Register('A'), Register('B'), Unsigned('I')], Arithmetic(Arith::Multiply, RegisterImmediate)),
+    form(Opc::Divide, "0001 AAAA BBBB CCCC 0000 0000 0000 0111", &[Register('A'), Register('B'), Register('C')], Arithmetic(Arith::Divide, Registers)),
+    form(Opc::Divide, "0001 AAAA BBBB IIII IIII IIII IIII 1000", &[Register('A'), Register('B'), Unsigned('I')], Arithmetic(Arith::Divide, RegisterImmediate)),
+    form(Opc::Divide, "0001 AAAA BBBB IIII IIII IIII IIII 1001", &[Register('A'), Unsigned('I'), Register('B')], Arithmetic(Arith::Divide, ImmediateRegister)),
+    form(Opc::DivideSigned, "0001 AAAA BBBB CCCC 0000 0000 0000 1010", &[Register('A'), Register('B'), Register('C')], Arithmetic(Arith::DivideSigned, Registers)),
+    form(Opc::DivideSigned, "0001 AAAA BBBB IIII IIII IIII IIII 1011", &[Register('A'), Register('B'), Signed('I')], Arithmetic(Arith::DivideSigned, RegisterImmediate)),
+    form(Opc::DivideSigned, "0001 AAAA BBBB IIII IIII IIII IIII 1100", &[Register('A'), Signed('I'), Register('B')], Arithmetic(Arith::DivideSigned, ImmediateRegister)),
+
+    // Bitwise
+    form(Opc::And, "0010 AAAA BBBB CCCC 0000 0000 0000 0000", &[Register('A'), Register('B'), Register('C')], Bitwise(Bit::And)),
+    form(Opc::Or, "0010 AAAA BBBB CCCC 0000 0000 0000 0001", &[Register('A'), Register('B'), Register('C')], Bitwise(Bit::Or)),
+    form(Opc::Xor, "0010 AAAA BBBB CCCC 0000 0000 0000 0010", &[Register('A'), Register('B'), Register('C')], Bitwise(Bit::Xor)),
+    form(Opc::Nand, "0010 AAAA BBBB CCCC 0000 0000 0000 0011", &[Register('A'), Register('B'), Register('C')], Bitwise(Bit::Nand)),
+    form(Opc::Nor, "0010 AAAA BBBB CCCC 0000 0000 0000 0100", &[Register('A'), Register('B'), Register('C')], Bitwise(Bit::Nor)),
+    form(Opc::Xnor, "0010 AAAA BBBB CCCC 0000 0000 0000 0101", &[Register('A'), Register('B'), Register('C')], Bitwise(Bit::Xnor)),
+    form(Opc::Not, "0010 AAAA BBBB 0000 0000 0000 0000 0110", &[Register('A'), Register('B')], Bitwise(Bit::Not)),
+
+    // Shift & rotate
+    form(Opc::RightShift, "0011 AAAA BBBB CCCC 0000 0000 0000 0000", &[Register('A'), Register('B'), Register('C')], Shift(Shi::RightShift, Source::Register)),
+    form(Opc::RightShift, "0011 AAAA BBBB 0000 0000 00II IIII 0001", &[Register('A'), Register('B'), Unsigned('I')], Shift(Shi::RightShift, Source::Immediate)),
+    form(Opc::LeftShift, "0011 AAAA BBBB CCCC 0000 0000 0000 0010", &[Register('A'), Register('B'), Register('C')], Shift(Shi::LeftShift, Source::Register)),
+    form(Opc::LeftShift, "0011 AAAA BBBB 0000 0000 00II IIII 0011", &[Register('A'), Register('B'), Unsigned('I')], Shift(Shi::LeftShift, Source::Immediate)),
+    form(Opc::RightRoll, "0011 AAAA BBBB CCCC 0000 0000 0000 0100", &[Register('A'), Register('B'), Register('C')], Shift(Shi::RightRoll, Source::Register)),
+    form(Opc::RightRoll, "0011 AAAA BBBB 0000 0000 00II IIII 0101", &[Register('A'), Register('B'), Unsigned('I')], Shift(Shi::RightRoll, Source::Immediate)),
+    form(Opc::LeftRoll, "0011 AAAA BBBB CCCC 0000 0000 0000 0110", &[Register('A'), Register('B'), Register('C')], Shift(Shi::LeftRoll, Source::Register)),
+    form(Opc::LeftRoll, "0011 AAAA BBBB 0000 0000 00II IIII 0111", &[Register('A'), Register('B'), Unsigned('I')], Shift(Shi::LeftRoll, Source::Immediate)),
+
+    // Data movement, memory & stack
+    form(Opc::Move, "0100 AAAA BBBB 0000 0000 0000 0000 0000", &[Register('A'), Register('B')], Move),
+    form(Opc::LoadImmediate, "0100 AAAA IIII IIII IIII IIII 00CC 0001", &[Register('A'), Unsigned('I'), Unsigned('C')], LoadImmediate),
+    form(Opc::LoadRegister, "0100 AAAA BBBB 0000 0000 0000 0SSS 0010", &[Register('A'), Register('B'), Unsigned('S')], Load(Source::Register)),
+    form(Opc::LoadRegister, "0100 AAAA IIII IIII IIII IIII 0SSS 0011", &[Register('A'), Unsigned('I'), Unsigned('S')], Load(Source::Immediate)),
+    form(Opc::StoreRegister, "0100 AAAA BBBB 0000 0000 0000 0SSS 0100", &[Register('A'), Register('B'), Unsigned('S')], Store(Source::Register)),
+    form(Opc::StoreRegister, "0100 AAAA IIII IIII IIII IIII 0SSS 0101", &[Register('A'), Unsigned('I'), Unsigned('S')], Store(Source::Immediate)),
+    form(Opc::Push, "0100 AAAA 0000 0000 0000 0000 0000 0110", &[Register('A')], Push),
+    form(Opc::Pop, "0100 AAAA 0000 0000 0000 0000 0000 0111", &[Register('A')], Pop),
+
+    // Comparison
+    form(Opc::Compare, "0101 AAAA BBBB 0000 0000 0000 0000 0000", &[Register('A'), Register('B')], Compare(Registers)),
+    form(Opc::Compare, "0101 AAAA IIII IIII IIII IIII 0000 0001", &[Register('A'), Unsigned('I')], Compare(RegisterImmediate)),
+    form(Opc::Compare, "0101 AAAA IIII IIII IIII IIII 0000 0010", &[Unsigned('I'), Register('A')], Compare(ImmediateRegister)),
+    form(Opc::CompareSigned, "0101 AAAA BBBB 0000 0000 0000 0000 0011", &[Register('A'), Register('B')], CompareSigned(Registers)),
+    form(Opc::CompareSigned, "0101 AAAA IIII IIII IIII IIII 0000 0100", &[Register('A'), Signed('I')], CompareSigned(RegisterImmediate)),
+    form(Opc::CompareSigned, "0101 AAAA IIII IIII IIII IIII 0000 0101", &[Signed('I'), Register('A')], CompareSigned(ImmediateRegister)),
+    form(Opc::CompareFloat, "0101 AAAA BBBB 0000 0000 0000 0000 0110", &[Register('A'), Register('B')], CompareFloat),
+    form(Opc::CompareDouble, "0101 AAAA BBBB 0000 0000 0000 0000 0111", &[Register('A'), Register('B')], CompareDouble),
+
+    // Branching. Jumps take the offset from a register, branches from an immediate. Both accept labels.
+    form(Opc::Jump, "0110 AAAA 0000 0000 0000 0000 0000 0000", &[Register('A')], Branch(Condition::Always, Source::Register)),
+    form(Opc::Branch, "0110 IIII IIII IIII IIII 0000 0000 0001", &[Signed('I')], Branch(Condition::Always, Source::Immediate)),
+    form(Opc::Branch, "0110 IIII IIII IIII IIII 0000 0000 0001", &[Label('I')], Branch(Condition::Always, Source::Immediate)),
+    form(Opc::Jump, "0110 IIII IIII IIII IIII 0000 0000 0001", &[Label('I')], Branch(Condition::Always, Source::Immediate)),
+    form(Opc::JumpGreater, "0110 AAAA 0000 0000 0000 0000 0000 0010", &[Register('A')], Branch(Condition::Greater, Source::Register)),
+    form(Opc::BranchGreater, "0110 IIII IIII IIII IIII 0000 0000 0011", &[Signed('I')], Branch(Condition::Greater, Source::Immediate)),
+    form(Opc::BranchGreater, "0110 IIII IIII IIII IIII 0000 0000 0011", &[Label('I')], Branch(Condition::Greater, Source::Immediate)),
+    form(Opc::JumpGreater, "0110 IIII IIII IIII IIII 0000 0000 0011", &[Label('I')], Branch(Condition::Greater, Source::Immediate)),
+    form(Opc::JumpEqual, "0110 AAAA 0000 0000 0000 0000 0000 0100", &[Register('A')], Branch(Condition::Equal, Source::Register)),
+    form(Opc::BranchEqual, "0110 IIII IIII IIII IIII 0000 0000 0101", &[Signed('I')], Branch(Condition::Equal, Source::Immediate)),
+    form(Opc::BranchEqual, "0110 IIII IIII IIII IIII 0000 0000 0101", &[Label('I')], Branch(Condition::Equal, Source::Immediate)),
+    form(Opc::JumpEqual, "0110 IIII IIII IIII IIII 0000 0000 0101", &[Label('I')], Branch(Condition::Equal, Source::Immediate)),
+    form(Opc::JumpSmaller, "0110 AAAA 0000 0000 0000 0000 0000 0110", &[Register('A')], Branch(Condition::Smaller, Source::Register)),
+    form(Opc::BranchSmaller, "0110 IIII IIII IIII IIII 0000 0000 0111", &[Signed('I')], Branch(Condition::Smaller, Source::Immediate)),
+    form(Opc::BranchSmaller, "0110 IIII IIII IIII IIII 0000 0000 0111", &[Label('I')], Branch(Condition::Smaller, Source::Immediate)),
+    form(Opc::JumpSmaller, "0110 IIII IIII IIII IIII 0000 0000 0111", &[Label('I')], Branch(Condition::Smaller, Source::Immediate)),
+    form(Opc::JumpGreaterEqual, "0110 AAAA 0000 0000 0000 0000 0000 1000", &[Register('A')], Branch(Condition::GreaterEqual, Source::Register)),
+    form(Opc::BranchGreaterEqual, "0110 IIII IIII IIII IIII 0000 0000 1001", &[Signed('I')], Branch(Condition::GreaterEqual, Source::Immediate)),
+    form(Opc::BranchGreaterEqual, "0110 IIII IIII IIII IIII 0000 0000 1001", &[Label('I')], Branch(Condition::GreaterEqual, Source::Immediate)),
+    form(Opc::JumpGreaterEqual, "0110 IIII IIII IIII IIII 0000 0000 1001", &[Label('I')], Branch(Condition::GreaterEqual, Source::Immediate)),
+    form(Opc::JumpNotEqual, "0110 AAAA 0000 0000 0000 0000 0000 1010", &[Register('A')], Branch(Condition::NotEqual, Source::Register)),
+    form(Opc::BranchNotEqual, "0110 IIII IIII IIII IIII 0000 0000 1011", &[Signed('I')], Branch(Condition::NotEqual, Source::Immediate)),
+    form(Opc::BranchNotEqual, "0110 IIII IIII IIII IIII 0000 0000 1011", &[Label('I')], Branch(Condition::NotEqual, Source::Immediate)),
+    form(Opc::JumpNotEqual, "0110 IIII IIII IIII IIII 0000 0000 1011", &[Label('I')], Branch(Condition::NotEqual, Source::Immediate)),
+    form(Opc::JumpSmallerEqual, "0110 AAAA 0000 0000 0000 0000 0000 1100", &[Register('A')], Branch(Condition::SmallerEqual, Source::Register)),
+    form(Opc::BranchSmallerEqual, "0110 IIII IIII IIII IIII 0000 0000 1101", &[Signed('I')], Branch(Condition::SmallerEqual, Source::Immediate)),
+    form(Opc::BranchSmallerEqual, "0110 IIII IIII IIII IIII 0000 0000 1101", &[Label('I')], Branch(Condition::SmallerEqual, Source::Immediate)),
+    form(Opc::JumpSmallerEqual, "0110 IIII IIII IIII IIII 0000 0000 1101", &[Label('I')], Branch(Condition::SmallerEqual, Source::Immediate)),
+
+    // Conversion
+    form(Opc::ImmediateToFloat, "0111 AAAA IIII IIII IIII IIII 0000 0000", &[Register('A'), Signed('I')], Convert(Conversion::ImmediateToFloat)),
+    form(Opc::ImmediateToDouble, "0111 AAAA IIII IIII IIII IIII 0000 0001", &[Register('A'), Signed('I')], Convert(Conversion::ImmediateToDouble)),
+    form(Opc::IntegerToFloat, "0111 AAAA 0000 0000 0000 0000 0000 0010", &[Register('A')], Convert(Conversion::IntegerToFloat)),
+    form(Opc::IntegerToDouble, "0111 AAAA 0000 0000 0000 0000 0000 0011", &[Register('A')], Convert(Conversion::IntegerToDouble)),
+    form(Opc::FloatToInteger, "0111 AAAA 0000 0000 0000 0000 0000 0100", &[Register('A')], Convert(Conversion::FloatToInteger)),
+    form(Opc::FloatToDouble, "0111 AAAA 0000 0000 0000 0000 0000 0101", &[Register('A')], Convert(Conversion::FloatToDouble)),
+    form(Opc::DoubleToInteger, "0111 AAAA 0000 0000 0000 0000 0000 0110", &[Register('A')], Convert(Conversion::DoubleToInteger)),
+    form(Opc::DoubleToFloat, "0111 AAAA 0000 0000 0000 0000 0000 0111", &[Register('A')], Convert(Conversion::DoubleToFloat)),
+
+    // Float arithmetic
+    form(Opc::FloatAdd, "1000 AAAA BBBB CCCC 0000 0000 0000 0000", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Add)),
+    form(Opc::FloatSubtract, "1000 AAAA BBBB CCCC 0000 0000 0000 0001", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Subtract)),
+    form(Opc::FloatMultiply, "1000 AAAA BBBB CCCC 0000 0000 0000 0010", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Multiply)),
+    form(Opc::FloatDivide, "1000 AAAA BBBB CCCC 0000 0000 0000 0011", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Divide)),
+    form(Opc::FloatModulo, "1000 AAAA BBBB CCCC 0000 0000 0000 0100", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Modulo)),
+    form(Opc::FloatNegate, "1000 AAAA BBBB 0000 0000 0000 0000 0101", &[Register('A'), Register('B')], Float(FloatOperation::Negate)),
+    form(Opc::FloatReciprocal, "1000 AAAA BBBB 0000 0000 0000 0000 0110", &[Register('A'), Register('B')], Float(FloatOperation::Reciprocal)),
+    form(Opc::FloatPower, "1000 AAAA BBBB CCCC 0000 0000 0000 0111", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Power)),
+    form(Opc::FloatExponential, "1000 AAAA BBBB 0000 0000 0000 0000 1000", &[Register('A'), Register('B')], Float(FloatOperation::Exponential)),
+    form(Opc::FloatRoot, "1000 AAAA BBBB CCCC 0000 0000 0000 1001", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Root)),
+    form(Opc::FloatSquareRoot, "1000 AAAA BBBB 0000 0000 0000 0000 1010", &[Register('A'), Register('B')], Float(FloatOperation::SquareRoot)),
+    form(Opc::FloatCubeRoot, "1000 AAAA BBBB 0000 0000 0000 0000 1011", &[Register('A'), Register('B')], Float(FloatOperation::CubeRoot)),
+    form(Opc::FloatSquare, "1000 AAAA BBBB 0000 0000 0000 0000 1100", &[Register('A'), Register('B')], Float(FloatOperation::Square)),
+    form(Opc::FloatCube, "1000 AAAA BBBB 0000 0000 0000 0000 1101", &[Register('A'), Register('B')], Float(FloatOperation::Cube)),
+    form(Opc::FloatLogarithm, "1000 AAAA BBBB CCCC 0000 0000 0000 1110", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Logarithm)),
+    form(Opc::FloatNaturalLogarithm, "1000 AAAA BBBB 0000 0000 0000 0000 1111", &[Register('A'), Register('B')], Float(FloatOperation::NaturalLogarithm)),
+    form(Opc::FloatAbsolute, "1000 AAAA BBBB 0000 0000 0000 0001 0000", &[Register('A'), Register('B')], Float(FloatOperation::Absolute)),
+    form(Opc::FloatSine, "1000 AAAA BBBB 0000 0000 0000 0001 0001", &[Register('A'), Register('B')], Float(FloatOperation::Sine)),
+    form(Opc::FloatCosine, "1000 AAAA BBBB 0000 0000 0000 0001 0010", &[Register('A'), Register('B')], Float(FloatOperation::Cosine)),
+    form(Opc::FloatTangent, "1000 AAAA BBBB 0000 0000 0000 0001 0011", &[Register('A'), Register('B')], Float(FloatOperation::Tangent)),
+    form(Opc::FloatArcsine, "1000 AAAA BBBB 0000 0000 0000 0001 0100", &[Register('A'), Register('B')], Float(FloatOperation::Arcsine)),
+    form(Opc::FloatArccosine, "1000 AAAA BBBB 0000 0000 0000 0001 0101", &[Register('A'), Register('B')], Float(FloatOperation::Arccosine)),
+    form(Opc::FloatArctangent, "1000 AAAA BBBB 0000 0000 0000 0001 0110", &[Register('A'), Register('B')], Float(FloatOperation::Arctangent)),
+    form(Opc::FloatFloor, "1000 AAAA BBBB 0000 0000 0000 0001 0111", &[Register('A'), Register('B')], Float(FloatOperation::Floor)),
+    form(Opc::FloatCeil, "1000 AAAA BBBB 0000 0000 0000 0001 1000", &[Register('A'), Register('B')], Float(FloatOperation::Ceil)),
+    form(Opc::FloatRound, "1000 AAAA BBBB 0000 0000 0000 0001 1001", &[Register('A'), Register('B')], Float(FloatOperation::Round)),
+    form(Opc::FloatMinimum, "1000 AAAA BBBB CCCC 0000 0000 0001 1010", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Minimum)),
+    form(Opc::FloatMaximum, "1000 AAAA BBBB CCCC 0000 0000 0001 1011", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::Maximum)),
+    form(Opc::FloatSign, "1000 AAAA BBBB 0000 0000 0000 0001 1100", &[Register('A'), Register('B')], Float(FloatOperation::Sign)),
+    form(Opc::FloatAbsoluteDifference, "1000 AAAA BBBB CCCC 0000 0000 0001 1101", &[Register('A'), Register('B'), Register('C')], Float(FloatOperation::AbsoluteDifference)),
+    form(Opc::FloatLoadInfinity, "1000 AAAA 0000 0000 0000 0000 0001 1110", &[Register('A')], Float(FloatOperation::LoadInfinity)),
+    form(Opc::FloatLoadNaN, "1000 AAAA 0000 0000 0000 0000 0001 1111", &[Register('A')], Float(FloatOperation::LoadNaN)),
+
+    // Double arithmetic
+    form(Opc::DoubleAdd, "1001 AAAA BBBB CCCC 0000 0000 0000 0000", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Add)),
+    form(Opc::DoubleSubtract, "1001 AAAA BBBB CCCC 0000 0000 0000 0001", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Subtract)),
+    form(Opc::DoubleMultiply, "1001 AAAA BBBB CCCC 0000 0000 0000 0010", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Multiply)),
+    form(Opc::DoubleDivide, "1001 AAAA BBBB CCCC 0000 0000 0000 0011", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Divide)),
+    form(Opc::DoubleModulo, "1001 AAAA BBBB CCCC 0000 0000 0000 0100", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Modulo)),
+    form(Opc::DoubleNegate, "1001 AAAA BBBB 0000 0000 0000 0000 0101", &[Register('A'), Register('B')], Double(FloatOperation::Negate)),
+    form(Opc::DoubleReciprocal, "1001 AAAA BBBB 0000 0000 0000 0000 0110", &[Register('A'), Register('B')], Double(FloatOperation::Reciprocal)),
+    form(Opc::DoublePower, "1001 AAAA BBBB CCCC 0000 0000 0000 0111", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Power)),
+    form(Opc::DoubleExponential, "1001 AAAA BBBB 0000 0000 0000 0000 1000", &[Register('A'), Register('B')], Double(FloatOperation::Exponential)),
+    form(Opc::DoubleRoot, "1001 AAAA BBBB CCCC 0000 0000 0000 1001", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Root)),
+    form(Opc::DoubleSquareRoot, "1001 AAAA BBBB 0000 0000 0000 0000 1010", &[Register('A'), Register('B')], Double(FloatOperation::SquareRoot)),
+    form(Opc::DoubleCubeRoot, "1001 AAAA BBBB 0000 0000 0000 0000 1011", &[Register('A'), Register('B')], Double(FloatOperation::CubeRoot)),
+    form(Opc::DoubleSquare, "1001 AAAA BBBB 0000 0000 0000 0000 1100", &[Register('A'), Register('B')], Double(FloatOperation::Square)),
+    form(Opc::DoubleCube, "1001 AAAA BBBB 0000 0000 0000 0000 1101", &[Register('A'), Register('B')], Double(FloatOperation::Cube)),
+    form(Opc::DoubleLogarithm, "1001 AAAA BBBB CCCC 0000 0000 0000 1110", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Logarithm)),
+    form(Opc::DoubleNaturalLogarithm, "1001 AAAA BBBB 0000 0000 0000 0000 1111", &[Register('A'), Register('B')], Double(FloatOperation::NaturalLogarithm)),
+    form(Opc::DoubleAbsolute, "1001 AAAA BBBB 0000 0000 0000 0001 0000", &[Register('A'), Register('B')], Double(FloatOperation::Absolute)),
+    form(Opc::DoubleSine, "1001 AAAA BBBB 0000 0000 0000 0001 0001", &[Register('A'), Register('B')], Double(FloatOperation::Sine)),
+    form(Opc::DoubleCosine, "1001 AAAA BBBB 0000 0000 0000 0001 0010", &[Register('A'), Register('B')], Double(FloatOperation::Cosine)),
+    form(Opc::DoubleTangent, "1001 AAAA BBBB 0000 0000 0000 0001 0011", &[Register('A'), Register('B')], Double(FloatOperation::Tangent)),
+    form(Opc::DoubleArcsine, "1001 AAAA BBBB 0000 0000 0000 0001 0100", &[Register('A'), Register('B')], Double(FloatOperation::Arcsine)),
+    form(Opc::DoubleArccosine, "1001 AAAA BBBB 0000 0000 0000 0001 0101", &[Register('A'), Register('B')], Double(FloatOperation::Arccosine)),
+    form(Opc::DoubleArctangent, "1001 AAAA BBBB 0000 0000 0000 0001 0110", &[Register('A'), Register('B')], Double(FloatOperation::Arctangent)),
+    form(Opc::DoubleFloor, "1001 AAAA BBBB 0000 0000 0000 0001 0111", &[Register('A'), Register('B')], Double(FloatOperation::Floor)),
+    form(Opc::DoubleCeil, "1001 AAAA BBBB 0000 0000 0000 0001 1000", &[Register('A'), Register('B')], Double(FloatOperation::Ceil)),
+    form(Opc::DoubleRound, "1001 AAAA BBBB 0000 0000 0000 0001 1001", &[Register('A'), Register('B')], Double(FloatOperation::Round)),
+    form(Opc::DoubleMinimum, "1001 AAAA BBBB CCCC 0000 0000 0001 1010", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Minimum)),
+    form(Opc::DoubleMaximum, "1001 AAAA BBBB CCCC 0000 0000 0001 1011", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::Maximum)),
+    form(Opc::DoubleSign, "1001 AAAA BBBB 0000 0000 0000 0001 1100", &[Register('A'), Register('B')], Double(FloatOperation::Sign)),
+    form(Opc::DoubleAbsoluteDifference, "1001 AAAA BBBB CCCC 0000 0000 0001 1101", &[Register('A'), Register('B'), Register('C')], Double(FloatOperation::AbsoluteDifference)),
+    form(Opc::DoubleLoadInfinity, "1001 AAAA 0000 0000 0000 0000 0001 1110", &[Register('A')], Double(FloatOperation::LoadInfinity)),
+    form(Opc::DoubleLoadNaN, "1001 AAAA 0000 0000 0000 0000 0001 1111", &[Register('A')], Double(FloatOperation::LoadNaN)),
+
+    // Subroutines
+    form(Opc::Call, "1010 AAAA 0000 0000 0000 0000 0000 0000", &[Register('A')], Call(Source::Register)),
+    form(Opc::Call, "1010 IIII IIII IIII IIII 0000 0000 0001", &[Signed('I')], Call(Source::Immediate)),
+    form(Opc::Call, "1010 IIII IIII IIII IIII 0000 0000 0001", &[Label('I')], Call(Source::Immediate)),
+    form(Opc::Return, "1010 0000 0000 0000 0000 0000 0000 0010", &[], Return),
+
+    // System
+    form(Opc::Breakpoint, "1011 0000 0000 0000 0000 0000 0000 0000", &[], Breakpoint),
+    form(Opc::SetTrapVector, "1011 AAAA 0000 0000 0000 0000 0000 0001", &[Register('A')], SetTrapVector),
+    form(Opc::ReturnFromTrap, "1011 0000 0000 0000 0000 0000 0000 0010", &[], ReturnFromTrap),
+    form(Opc::Halt, "1011 0000 0000 0000 0000 0000 0000 0011", &[], Halt),
+];
@@ -0,0 +1,333 @@
+//! Combines objects into an image. Members of libraries are only added when they export a label that's still missing.
+
+use crate::assembler::relocation::RelocationKind;
+use crate::assembler::types::section::Section;
+use crate::isa::{self, Operand};
+use crate::object::{Library, Object};
+use crate::symbol_file::{self, SymbolFile};
+use std::collections::HashMap;
+use std::fmt::{self, Display, Write};
+
+/// A linked program
+pub struct Linked {
+    /// Memory image starting at address 0
+    pub image: Vec<u8>,
+    /// Where each section of each object and each exported label ended up
+    pub map: String,
+    /// Labels of all objects, for the emulator to show
+    pub symbols: SymbolFile,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LinkError {
+    /// A label exported by two objects
+    DuplicateSymbol { name: String, first: String, second: String },
+    /// A label used by an object that no object or library defines
+    UndefinedSymbol { name: String, object: String },
+    BranchOutOfReach { name: String, object: String, required: i64 },
+    /// A relocation whose field doesn't hold what it should, like a branch relocation on data, or isn't inside its section
+    InvalidRelocation { object: String, section: Section, offset: usize },
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::DuplicateSymbol { name, first, second } => write!(f, "{} is exported by both {} and {}", name, first, second),
+            LinkError::UndefinedSymbol { name, object } => write!(f, "{} used by {} isn't exported by any object or library", name, object),
+            LinkError::BranchOutOfReach { name, object, required } => write!(f, "Branch to {} in {} is out of reach. Offset is {} but limit is {}", name, object, required, i16::MAX),
+            LinkError::InvalidRelocation { object, section, offset } => write!(f, "Relocation at {} {:#x} in {} doesn't fit the section or the instruction there", section, offset, object),
+        }
+    }
+}
+
+/// An object taking part in the link and where its sections are placed
+struct Input<'a> {
+    /// The file, or the library and member like `lib.lib(print.obj)`
+    name: String,
+    object: &'a Object,
+    /// Address of each section
+    bases: [usize; 3],
+}
+
+/// Links objects in the order they're given, text of all of them first, then data, then bss.
+/// Each section of each object starts at a multiple of [`Section::ALIGNMENT`]. All errors are returned together.
+pub fn link(objects: &[(String, Object)], libraries: &[(String, Library)]) -> Result<Linked, Vec<LinkError>> {
+    let mut inputs = objects.iter().map(|(name, object)| Input { name: name.to_owned(), object, bases: [0; 3] }).collect::<Vec<_>>();
+    let mut errors = Vec::new();
+
+    // Object exporting each label, by index into the inputs
+    let mut exports = HashMap::new();
+
+    for i in 0..inputs.len() {
+        export(&inputs, i, &mut exports, &mut errors);
+    }
+
+    // A member can import labels only other members define, so libraries are searched until nothing is added
+    let mut added = true;
+    while added {
+        added = false;
+
+        for (library_name, library) in libraries {
+            for (member_name, member) in &library.members {
+                let name = format!("{}({})", library_name, member_name);
+                let wanted = member.symbols.iter().any(|symbol| symbol.exported && !exports.contains_key(&symbol.name) && imported(&inputs, &symbol.name));
+
+                if wanted && !inputs.iter().any(|input| input.name == name) {
+                    inputs.push(Input { name, object: member, bases: [0; 3] });
+                    export(&inputs, inputs.len() - 1, &mut exports, &mut errors);
+                    added = true;
+                }
+            }
+        }
+    }
+
+    for input in &inputs {
+        for name in &input.object.imports {
+            if !exports.contains_key(name) {
+                errors.push(LinkError::UndefinedSymbol { name: name.to_owned(), object: input.name.to_owned() });
+            }
+        }
+    }
+
+    let mut end: usize = 0;
+    for section in Section::ALL {
+        for input in &mut inputs {
+            let base = end.next_multiple_of(Section::ALIGNMENT);
+            input.bases[section.index()] = base;
+
+            if input.object.size(section) > 0 {
+                end = base + input.object.size(section);
+            }
+        }
+    }
+
+    let mut image = vec![0; end];
+    for input in &inputs {
+        for (section, contents) in [(Section::Text, &input.object.text), (Section::Data, &input.object.data)] {
+            // An empty section may start past the end
+            if contents.is_empty() {
+                continue;
+            }
+            let base = input.bases[section.index()];
+            image[base..base + contents.len()].copy_from_slice(contents);
+        }
+    }
+
+    for input in &inputs {
+        for relocation in &input.object.relocations {
+            let invalid = || LinkError::InvalidRelocation { object: input.name.to_owned(), section: relocation.section, offset: relocation.offset };
+
+            // The whole field has to be inside the section, patching it can't touch another object
+            let width = match relocation.kind {
+                RelocationKind::Address => 16,
+                RelocationKind::Branch | RelocationKind::Word => 4,
+            };
+            if relocation.offset.checked_add(width).is_none_or(|end| end > input.object.size(relocation.section)) {
+                errors.push(invalid());
+                continue;
+            }
+
+            let address = |input: &Input, name: &str| input.object.symbol(name).map(|symbol| input.bases[symbol.section.index()] + symbol.offset);
+            let Some(target) = address(input, &relocation.symbol).or_else(|| exports.get(&relocation.symbol).and_then(|&i| address(&inputs[i], &relocation.symbol))) else {
+                // Imports without an export were reported above
+                if !input.object.imports.contains(&relocation.symbol) {
+                    errors.push(LinkError::UndefinedSymbol { name: relocation.symbol.to_owned(), object: input.name.to_owned() });
+                }
+                continue;
+            };
+
+            let at = input.bases[relocation.section.index()] + relocation.offset;
+            let value = target as i128 + relocation.addend;
+
+            let patched = match relocation.kind {
+                RelocationKind::Branch => {
+                    let offset = (value - at as i128) as i64 / 4;
+                    if i16::try_from(offset).is_err() {
+                        errors.push(LinkError::BranchOutOfReach { name: relocation.symbol.to_owned(), object: input.name.to_owned(), required: offset });
+                        continue;
+                    }
+                    // Decoding finds the form taking the offset as a number, the one taking a label is encoded the same
+                    patch(&mut image, at, Operand::Signed(IMMEDIATE), offset as u32)
+                }
+                RelocationKind::Word => image.get_mut(at..at + 4).map(|word| word.copy_from_slice(&(value as u32).to_be_bytes())),
+                // Each `ldi` loads the next 16-bit chunk
+                RelocationKind::Address => (0..4).try_for_each(|i| patch(&mut image, at + 4 * i, Operand::Unsigned(IMMEDIATE), (value as u64 >> (16 * i)) as u32)),
+            };
+
+            if patched.is_none() {
+                errors.push(invalid());
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(Linked { map: map(&inputs, &exports), symbols: symbols(&inputs, end), image })
+}
+
+/// Adds the labels the input at `i` exports
+fn export(inputs: &[Input], i: usize, exports: &mut HashMap<String, usize>, errors: &mut Vec<LinkError>) {
+    for symbol in inputs[i].object.symbols.iter().filter(|symbol| symbol.exported) {
+        match exports.get(&symbol.name) {
+            Some(&first) => errors.push(LinkError::DuplicateSymbol { name: symbol.name.to_owned(), first: inputs[first].name.to_owned(), second: inputs[i].name.to_owned() }),
+            None => {
+                exports.insert(symbol.name.to_owned(), i);
+            }
+        }
+    }
+}
+
+/// Whether any object taking part uses a label it doesn't define
+fn imported(inputs: &[Input], name: &str) -> bool {
+    inputs.iter().any(|input| input.object.imports.iter().any(|import| import == name))
+}
+
+/// Field holding the offset of branches and calls and the immediate of `ldi`
+const IMMEDIATE: char = 'I';
+
+/// Replaces the value of `operand` in the instruction at `at`. `None` if there's no instruction with that operand.
+fn patch(image: &mut [u8], at: usize, operand: Operand, value: u32) -> Option<()> {
+    let bytes = image.get_mut(at..at + 4)?;
+    let decoded = isa::decode(u32::from_be_bytes(bytes.try_into().unwrap()))?;
+
+    if !decoded.form.operands.contains(&operand) {
+        return None;
+    }
+
+    let values = decoded.form.operands.iter().map(|other| match other.field() {
+        field if field == operand.field() => value,
+        field => decoded.field(field),
+    }).collect::<Vec<_>>();
+
+    bytes.copy_from_slice(&decoded.form.encode(&values).to_be_bytes());
+    Some(())
+}
+
+/// The linker map. Sections are listed with their address, size and object, followed by the exported labels.
+fn map(inputs: &[Input], exports: &HashMap<String, usize>) -> String {
+    let mut text = String::from("Sections:\n");
+
+    for section in Section::ALL {
+        for input in inputs.iter().filter(|input| input.object.size(section) > 0) {
+            writeln!(text, "{:#010x}  {:#8x}  {:<4}  {}", input.bases[section.index()], input.object.size(section), section.to_string(), input.name).unwrap();
+        }
+    }
+
+    let mut symbols = exports.iter().map(|(name, &i)| {
+        let symbol = inputs[i].object.symbol(name).expect("Exported symbol missing from its object");
+        (inputs[i].bases[symbol.section.index()] + symbol.offset, name, &inputs[i].name)
+    }).collect::<Vec<_>>();
+    symbols.sort();
+
+    text.push_str("\nSymbols:\n");
+    for (address, name, object) in symbols {
+        writeln!(text, "{:#010x}  {:<20}  {}", address, name, object).unwrap();
+    }
+
+    text
+}
+
+/// Labels of every object at their linked addresses. Labels in text are code, the others data up to the next label.
+fn symbols(inputs: &[Input], end: usize) -> SymbolFile {
+    let mut labels = inputs.iter().flat_map(|input| input.object.symbols.iter()
+        // Labels local to macro expansions can't be named
+        .filter(|symbol| !symbol.name.contains('@'))
+        .map(move |symbol| (input.bases[symbol.section.index()] + symbol.offset, symbol.section, input, &symbol.name)))
+        .collect::<Vec<_>>();
+    labels.sort_by_key(|&(address, ..)| address);
+
+    let labels = labels.iter().enumerate().map(|(i, &(address, section, input, name))| {
+        let section_end = input.bases[section.index()] + input.object.size(section);
+        let next = labels[i + 1..].iter().map(|&(next, ..)| next).find(|&next| next > address).unwrap_or(end);
+
+        symbol_file::Label {
+            name: name.to_owned(),
+            address,
+            data: (section != Section::Text).then(|| next.min(section_end).saturating_sub(address)),
+        }
+    }).collect();
+
+    SymbolFile { labels, ..SymbolFile::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble::{assemble_source, Options};
+    use crate::cpu::{Cpu, RunOutcome};
+
+    fn object(name: &str, source: &str) -> (String, Object) {
+        object_in(name, source, Options::default().memory_size)
+    }
+
+    /// An object assembled for a memory of `memory_size` bytes
+    fn object_in(name: &str, source: &str, memory_size: usize) -> (String, Object) {
+        let options = Options { relocatable: true, memory_size, ..Options::default() };
+        (name.to_string(), Object::new(&assemble_source(source.to_string(), None, &options).unwrap_or_else(|diagnostics| panic!("{}", diagnostics[0].error))))
+    }
+
+    fn objects() -> Vec<(String, Object)> {
+        vec![
+            object("main.obj", ".extern .double .table\n.start: li r1 .table\n    call .double\n    hlt"),
+            object("table.obj", ".global .table\n.section data\n.table: .word .table 0\n.section bss\n.buffer: .space 8"),
+        ]
+    }
+
+    fn library() -> (String, Library) {
+        ("math.lib".to_string(), Library { members: vec![
+            object("double.obj", ".global .double\n.double: add r0 r1 r1\n    ret"),
+            object("unused.obj", ".global .triple\n.triple: ret"),
+        ] })
+    }
+
+    #[test]
+    fn test_link() {
+        let linked = link(&objects(), &[library()]).unwrap_or_else(|errors| panic!("{:?}", errors));
+
+        let mut cpu = Cpu::default();
+        cpu.memory[..linked.image.len()].copy_from_slice(&linked.image);
+        assert_eq!(cpu.run(100).outcome, RunOutcome::Halted);
+
+        // The text of main.obj and double.obj, then the data of table.obj
+        assert_eq!(cpu.regs[0], 0x30 * 2);
+        assert_eq!(linked.image[0x30..0x38], [0, 0, 0, 0x30, 0, 0, 0, 0]);
+        assert_eq!(linked.image.len(), 0x48);
+
+        assert_eq!(linked.map, "\
+Sections:
+0x00000000      0x18  text  main.obj
+0x00000020       0x8  text  math.lib(double.obj)
+0x00000030       0x8  data  table.obj
+0x00000040       0x8  bss   table.obj
+
+Symbols:
+0x00000020  .double               math.lib(double.obj)
+0x00000030  .table                table.obj
+");
+        assert_eq!(linked.symbols.to_string(), "\
+label .start 0x0 code
+label .double 0x20 code
+label .table 0x30 data 8
+label .buffer 0x40 data 8
+");
+    }
+
+    #[test]
+    fn test_link_errors() {
+        let errors = |objects: &[(String, Object)]| link(objects, &[]).err().unwrap().iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert_eq!(errors(&objects()), [".double used by main.obj isn't exported by any object or library"]);
+        assert_eq!(errors(&[object("a.obj", ".global .a\n.a: ret"), object("b.obj", ".global .a\n.a: hlt")]), [".a is exported by both a.obj and b.obj"]);
+
+        let far = object_in("far.obj", ".extern .a\n    b .a\n    .space 0x20000\n    hlt", 1 << 20);
+        assert_eq!(errors(&[far, object("a.obj", ".global .a\n.a: ret")]), ["Branch to .a in far.obj is out of reach. Offset is 32772 but limit is 32767"]);
+
+        let outside = Object::parse("object\nsection text 0x8\nbytes text 0x0 b0000003b0000003\nsymbol .a text 0x0\nreloc text 0x6 word .a 0\nreloc text 0x0 address .a 0").unwrap();
+        assert_eq!(errors(&[("outside.obj".to_string(), outside)]), [
+            "Relocation at text 0x6 in outside.obj doesn't fit the section or the instruction there",
+            "Relocation at text 0x0 in outside.obj doesn't fit the section or the instruction there",
+        ]);
+    }
+}
@@ -1,21 +1,193 @@
 mod cpu;
 mod assembler;
+mod isa;
+mod disassembler;
+mod symbol_file;
+mod object;
+mod linker;
 
-use assembler::assemble::assemble;
-use cpu::Cpu;
+use assembler::assemble::{assemble_source, Options};
+use assembler::listing::listing;
+use cpu::{Cpu, RunOutcome, StackConfig, StackDirection};
+use object::{Library, Object};
+use symbol_file::SymbolFile;
+
+/// Maximum number of instructions executed before the program is stopped
+const CYCLE_BUDGET: u64 = 1_000_000;
+
+/// What to do with the program, from the command line
+#[derive(Default)]
+struct Args {
+    /// The file to assemble, or the objects and libraries to link or archive
+    files: Vec<String>,
+    /// Binary image run instead of assembling a file
+    image: Option<String>,
+    options: Options,
+    report_relaxed: bool,
+    /// File the image is written to
+    output: Option<String>,
+    /// File the listing is written to
+    listing: Option<String>,
+    /// File the symbols are written to, or read from when running an image
+    symbols: Option<String>,
+    /// File the object is written to instead of running the program
+    object: Option<String>,
+    /// File a library of the objects is written to
+    archive: Option<String>,
+    /// Whether the files are objects and libraries linked into the program
+    link: bool,
+    /// File the linker map is written to
+    map: Option<String>,
+    /// Labels execution stops at
+    breakpoints: Vec<String>,
+    trace: bool,
+    /// Value of the stack pointer while the stack is empty, the end of memory if not given
+    stack_base: Option<u64>,
+    stack_upward: bool,
+}
+
+/// Options taking a value are followed by it, anything else is a file to assemble, link or archive
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match arg.as_str() {
+            "--no-relax" => parsed.options.relax_branches = false,
+            "--report-relaxed" => parsed.report_relaxed = true,
+            "--image" => parsed.image = Some(value()?),
+            "--output" => parsed.output = Some(value()?),
+            "--listing" => parsed.listing = Some(value()?),
+            "--symbols" => parsed.symbols = Some(value()?),
+            "--object" => {
+                parsed.object = Some(value()?);
+                parsed.options.relocatable = true;
+            }
+            "--archive" => parsed.archive = Some(value()?),
+            "--link" => parsed.link = true,
+            "--map" => parsed.map = Some(value()?),
+            "--break" => parsed.breakpoints.push(value()?),
+            "--trace" => parsed.trace = true,
+            "--stack-base" => {
+                let base = value()?;
+                parsed.stack_base = Some(parse_address(&base).ok_or_else(|| format!("Invalid stack base {}", base))?);
+            }
+            "--stack-upward" => parsed.stack_upward = true,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => parsed.files.push(arg),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// A number in decimal or, starting with `0x`, in hex
+fn parse_address(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
-    let file_path = match args.get(1) {
-        None => {
+    if args.get(1).map(String::as_str) == Some("--disassemble") {
+        match args.get(2) {
+            None => println!("No file specified"),
+            Some(path) => disassemble_file(path),
+        }
+        return;
+    }
+
+    let args = match parse_args(args.into_iter().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            println!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(path) = &args.archive {
+        archive(path, &args.files);
+        return;
+    }
+
+    let program = match (&args.image, &args.files[..]) {
+        (Some(path), _) => Some(load_image(path, args.symbols.as_deref())),
+        (None, []) => {
             println!("No file specified");
             return;
         }
-        Some(path) => path,
+        (None, files) if args.link => link_files(files, &args),
+        (None, [path]) => assemble_file(path, &args),
+        (None, _) => {
+            println!("Only one file is assembled at a time, objects are combined with --link");
+            std::process::exit(1);
+        }
     };
 
-    let file_content = match std::fs::read_to_string(file_path) {
+    let Some((image, symbols)) = program else { return };
+
+    let mut cpu = Cpu::default();
+
+    load_program(&mut cpu, &image);
+
+    if args.stack_base.is_some() || args.stack_upward {
+        let direction = if args.stack_upward { StackDirection::Upward } else { StackDirection::Downward };
+        cpu.set_stack(StackConfig::new(args.stack_base.unwrap_or(cpu.stack.base), direction));
+    }
+
+    for name in &args.breakpoints {
+        match symbols.label(name) {
+            Some(address) => cpu.breakpoints.insert(address as u64),
+            None => {
+                println!("No label named {} to break at", name);
+                std::process::exit(1);
+            }
+        };
+    }
+
+    cpu.set_instruction_ptr(0);
+
+    let mut budget = CYCLE_BUDGET;
+
+    // Execution continues after each breakpoint until the program stops for another reason
+    loop {
+        let result = match args.trace {
+            true => cpu.run_traced(budget, |cpu| println!("{}", trace(cpu, &symbols))),
+            false => cpu.run(budget),
+        };
+        budget -= result.executed;
+
+        // The address is shown with its label and line instead
+        let (outcome, address) = match result.outcome {
+            RunOutcome::Breakpoint { address } => ("Breakpoint".to_string(), address),
+            // The instruction pointer is already past `hlt`
+            RunOutcome::Halted => (result.outcome.to_string(), cpu.instruction_ptr() - 4),
+            outcome => (outcome.to_string(), cpu.instruction_ptr()),
+        };
+        println!("{} at {} after {} instructions", outcome, locate(address, &symbols), result.executed);
+
+        match result.outcome {
+            RunOutcome::Breakpoint { .. } => println!("Registers: {:?}", cpu.regs),
+            RunOutcome::Trapped(_) => {
+                println!("Crashed executing {}", instruction(&cpu));
+                break;
+            }
+            _ => break,
+        }
+    }
+
+    println!("Registers after execution: {:?}", cpu.regs);
+}
+
+/// Assembles a file and writes the image, listing and symbols if asked to.
+/// `None` if it has errors or was assembled into an object, which only runs once it's linked.
+fn assemble_file(path: &str, args: &Args) -> Option<(Vec<u8>, SymbolFile)> {
+    let file_content = match std::fs::read_to_string(path) {
         Ok(file_content) => file_content,
         Err(err) => {
             println!("Error: {:?}", err);
@@ -23,52 +195,211 @@ fn main() {
         }
     };
 
-    let instructions = match assemble(file_content) {
-        Ok(instructions) => instructions,
-        Err(err_msg) => {
-            println!("{}", err_msg);
-            return;
+    let assembly = match assemble_source(file_content, Some(std::path::Path::new(path)), &args.options) {
+        Ok(assembly) => assembly,
+        Err(diagnostics) => {
+            diagnostics.iter().for_each(|diagnostic| println!("{}", diagnostic));
+            return None;
         }
     };
 
-    println!("Assembled into {} instructions:", instructions.len());
-    instructions.iter().enumerate().for_each(|(i, instruction)| println!("{}. {:#010x}", i + 1,  instruction));
+    assembly.warnings.iter().for_each(|warning| println!("{}", warning));
+    if args.report_relaxed {
+        assembly.relaxed.iter().for_each(|note| println!("{}", note));
+    }
 
-    let mut cpu = Cpu::default();
+    if let Some(path) = &args.listing {
+        write_file(path, listing(&assembly));
+    }
+
+    if let Some(path) = &args.object {
+        write_file(path, Object::new(&assembly).to_string());
+        println!("Assembled into an object with {} relocations", assembly.linkage.relocations.len());
+        return None;
+    }
 
-    load_program(&mut cpu, &instructions);
+    let symbols = SymbolFile::new(&assembly);
 
-    cpu.set_instruction_ptr(0);
+    if let Some(path) = &args.output {
+        write_file(path, &assembly.image);
+    }
+    if let Some(path) = &args.symbols {
+        write_file(path, symbols.to_string());
+    }
 
-    cpu.run(100);
+    println!("Assembled into {} bytes:", assembly.image.len());
+    assembly.image.chunks(4).enumerate().for_each(|(i, chunk)| {
+        let bytes = chunk.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+        println!("{:#06x}: {}", i * 4, bytes);
+    });
 
-    println!("Registers after execution: {:?}", cpu.regs);
+    Some((assembly.image, symbols))
+}
+
+/// Links objects and libraries and writes the image, map and symbols if asked to. `None` if it has errors.
+fn link_files(paths: &[String], args: &Args) -> Option<(Vec<u8>, SymbolFile)> {
+    let mut objects = Vec::new();
+    let mut libraries = Vec::new();
+
+    for path in paths {
+        let text = read_text(path);
+
+        let parsed = match text.starts_with("library") {
+            true => Library::parse(&text).map(|library| libraries.push((path.to_owned(), library))),
+            false => Object::parse(&text).map(|object| objects.push((path.to_owned(), object))),
+        };
+
+        if let Err(err) = parsed {
+            println!("Invalid object {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+
+    let linked = match linker::link(&objects, &libraries) {
+        Ok(linked) => linked,
+        Err(errors) => {
+            errors.iter().for_each(|error| println!("Error: {}", error));
+            return None;
+        }
+    };
+
+    if let Some(path) = &args.output {
+        write_file(path, &linked.image);
+    }
+    if let Some(path) = &args.map {
+        write_file(path, &linked.map);
+    }
+    if let Some(path) = &args.symbols {
+        write_file(path, linked.symbols.to_string());
+    }
+
+    println!("Linked into {} bytes", linked.image.len());
+
+    Some((linked.image, linked.symbols))
+}
+
+/// Writes a library of objects, each named by its file
+fn archive(path: &str, objects: &[String]) {
+    let mut library = Library::default();
+
+    for object in objects {
+        match Object::parse(&read_text(object)) {
+            Ok(parsed) => library.members.push((object.to_owned(), parsed)),
+            Err(err) => {
+                println!("Invalid object {}: {}", object, err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    write_file(path, library.to_string());
+}
+
+fn read_text(path: &str) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|err| {
+        println!("Error: {:?}", err);
+        std::process::exit(1);
+    })
+}
+
+/// Reads a binary image and the symbol file written when it was assembled, if there is one
+fn load_image(path: &str, symbols: Option<&str>) -> (Vec<u8>, SymbolFile) {
+    let image = std::fs::read(path).unwrap_or_else(|err| {
+        println!("Error: {:?}", err);
+        std::process::exit(1);
+    });
+
+    let symbols = match symbols.map(std::fs::read_to_string) {
+        None => SymbolFile::default(),
+        Some(Ok(text)) => SymbolFile::parse(&text).unwrap_or_else(|err| {
+            println!("Invalid symbol file: {}", err);
+            std::process::exit(1);
+        }),
+        Some(Err(err)) => {
+            println!("Error: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    (image, symbols)
+}
+
+/// An address with the label it's at or after and the line it was assembled from, like `0x14 (.loop+0x4, prog.asm line 7)`
+fn locate(address: u64, symbols: &SymbolFile) -> String {
+    let names = [symbols.symbolize(address as usize), symbols.line(address as usize).map(ToString::to_string)];
+    let names = names.into_iter().flatten().collect::<Vec<_>>();
+
+    match names.is_empty() {
+        true => format!("{:#x}", address),
+        false => format!("{:#x} ({})", address, names.join(", ")),
+    }
+}
+
+/// The instruction about to execute, with where it is
+fn trace(cpu: &Cpu, symbols: &SymbolFile) -> String {
+    format!("{:<40} {}", locate(cpu.instruction_ptr(), symbols), instruction(cpu))
+}
+
+/// The instruction the instruction pointer points at
+fn instruction(cpu: &Cpu) -> String {
+    let address = cpu.instruction_ptr() as usize;
+    let word = address.checked_add(4).and_then(|end| cpu.memory.get(address..end)).map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()));
+
+    match word.map(|word| (word, disassembler::disassemble(word))) {
+        Some((_, Some(instruction))) => instruction.to_string(),
+        Some((word, None)) => format!(".word {:#010x}", word),
+        None => "outside of memory".to_string(),
+    }
 }
 
-fn load_program(cpu: &mut Cpu, program: &[u32]) {
-    let mut mem = 0;
+fn write_file(path: &str, contents: impl AsRef<[u8]>) {
+    if let Err(err) = std::fs::write(path, contents) {
+        println!("Error writing {}: {:?}", path, err);
+        std::process::exit(1);
+    }
+}
+
+/// Prints a binary image of big-endian instruction words as assembly
+fn disassemble_file(path: &str) {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            println!("Error: {:?}", err);
+            std::process::exit(1);
+        }
+    };
 
-    // Ensure the program fits into memory
-    assert!(program.len() * 4 <= cpu.memory.len(), "Program is too large to fit in memory!");
+    if !bytes.len().is_multiple_of(4) {
+        println!("Image size of {} bytes isn't a multiple of the instruction size", bytes.len());
+        std::process::exit(1);
+    }
 
-    for &instruction in program.iter() {
-        let (b1, b2, b3, b4) = encode_instruction(instruction);
+    let words = bytes.chunks_exact(4).map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap())).collect::<Vec<_>>();
 
-        // Write the instruction bytes to memory in order
-        cpu.memory[mem] = b1;
-        cpu.memory[mem + 1] = b2;
-        cpu.memory[mem + 2] = b3;
-        cpu.memory[mem + 3] = b4;
+    print!("{}", disassembler::disassemble_image(&words));
+}
 
-        mem += 4; // Advance by 4 bytes
+/// Copies an image to the start of memory. One that doesn't fit, like a linked or loaded image, ends the program.
+fn load_program(cpu: &mut Cpu, image: &[u8]) {
+    if image.len() > cpu.memory.len() {
+        println!("Program of {} bytes doesn't fit into the {} bytes of memory", image.len(), cpu.memory.len());
+        std::process::exit(1);
     }
 
-    fn encode_instruction(instruction: u32) -> (u8, u8, u8, u8) {
-        (
-            ((instruction & 0xFF000000) >> 24) as u8,
-            ((instruction & 0x00FF0000) >> 16) as u8,
-            ((instruction & 0x0000FF00) >> 8) as u8,
-            (instruction & 0x000000FF) as u8
-        )
+    cpu.memory[..image.len()].copy_from_slice(image);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruction() {
+        let mut cpu = Cpu::default();
+        cpu.memory[..4].copy_from_slice(&0xb0000003_u32.to_be_bytes());
+        assert_eq!(instruction(&cpu), "hlt");
+
+        cpu.set_instruction_ptr(u64::MAX - 3);
+        assert_eq!(instruction(&cpu), "outside of memory");
     }
 }
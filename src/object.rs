@@ -0,0 +1,362 @@
+//! Relocatable programs and libraries of them, for the linker to combine into an image.
+//!
+//! An object is line based like a symbol file. It starts with `object`, every other line is one record:
+//!
+//! ```text
+//! object
+//! section text 0x10
+//! section data 0x4
+//! section bss 0x100
+//! bytes text 0x0 a00000010000000000000000b0000003
+//! bytes data 0x0 00000000
+//! symbol .start text 0x0 export
+//! symbol .count data 0x0
+//! import .print
+//! reloc text 0x0 branch .print 0
+//! reloc data 0x0 word .start 0
+//! ```
+//!
+//! `section` gives the size of a section, `bytes` the contents of text and data from an offset on in hex.
+//! `symbol` places a label in a section, `export` makes it visible to other objects. `import` names a label of another object.
+//! `reloc` is a field the linker fills in with the address of a label plus an addend.
+//!
+//! A library starts with `library` and holds objects, each after a `member` record naming it.
+
+use crate::assembler::assemble::{Assembly, PlacementKind};
+use crate::assembler::relocation::RelocationKind;
+use crate::assembler::types::section::Section;
+use crate::cpu::MEMORY_SIZE;
+use std::fmt::{self, Display};
+
+/// Bytes written per `bytes` record
+const BYTES_PER_RECORD: usize = 32;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Object {
+    pub text: Vec<u8>,
+    pub data: Vec<u8>,
+    /// Bytes the bss section takes, they start out zeroed
+    pub bss: usize,
+    /// Labels in the order of their sections and offsets
+    pub symbols: Vec<Symbol>,
+    pub imports: Vec<String>,
+    pub relocations: Vec<Relocation>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub section: Section,
+    pub offset: usize,
+    /// Whether other objects can refer to it
+    pub exported: bool,
+}
+
+/// A field at `offset` in `section` that holds the address of `symbol` plus `addend`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Relocation {
+    pub section: Section,
+    pub offset: usize,
+    pub kind: RelocationKind,
+    pub symbol: String,
+    pub addend: i128,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Library {
+    /// Objects with their names, in the order they're searched
+    pub members: Vec<(String, Object)>,
+}
+
+impl Object {
+    /// The object of a program assembled as relocatable
+    pub fn new(assembly: &Assembly) -> Self {
+        let linkage = &assembly.linkage;
+        let [text, data, bss] = &linkage.sections;
+
+        // An empty section may start after the end of the image
+        let bytes = |range: &std::ops::Range<usize>| assembly.image.get(range.clone()).unwrap_or_default().to_vec();
+
+        let symbols = assembly.placements.iter().filter_map(|placement| {
+            let PlacementKind::Label(name) = &placement.kind else { return None };
+            let section = linkage.label_sections[name];
+
+            Some(Symbol {
+                name: name.to_owned(),
+                section,
+                offset: placement.address - linkage.sections[section.index()].start,
+                exported: linkage.exports.contains(name),
+            })
+        });
+
+        let relocations = linkage.relocations.iter().map(|relocation| {
+            let section = Section::ALL.into_iter().find(|section| linkage.sections[section.index()].contains(&relocation.address)).expect("Relocation outside of the sections");
+
+            Relocation {
+                section,
+                offset: relocation.address - linkage.sections[section.index()].start,
+                kind: relocation.kind,
+                symbol: relocation.symbol.to_owned(),
+                addend: relocation.addend,
+            }
+        });
+
+        let mut symbols = symbols.collect::<Vec<_>>();
+        symbols.sort_by_key(|symbol| (symbol.section, symbol.offset));
+
+        Self {
+            text: bytes(text),
+            data: bytes(data),
+            bss: bss.len(),
+            symbols,
+            imports: linkage.imports.clone(),
+            relocations: relocations.collect(),
+        }
+    }
+
+    /// Bytes a section takes
+    pub fn size(&self, section: Section) -> usize {
+        match section {
+            Section::Text => self.text.len(),
+            Section::Data => self.data.len(),
+            Section::Bss => self.bss,
+        }
+    }
+
+    /// A label of this object, exported or not
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// Reads an object. Errors name the line they're on.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let records = text.lines().enumerate().map(|(i, record)| (i + 1, record)).collect::<Vec<_>>();
+        parse_object(&records)
+    }
+}
+
+/// Reads the records of an object, the first being its header, each with its line number
+fn parse_object(records: &[(usize, &str)]) -> Result<Object, String> {
+    let mut object = Object::default();
+    // Line of each symbol, checked against the size of its section once all of them are known
+    let mut symbol_lines = Vec::new();
+
+    match records.first() {
+        Some((_, "object")) => {}
+        Some((line, _)) => return Err(format!("Line {}: An object starts with 'object'", line)),
+        None => return Err("Line 1: An object starts with 'object'".to_string()),
+    }
+
+    for &(line, record) in &records[1..] {
+        let error = |reason: &str| format!("Line {}: {}", line, reason);
+        let fields = record.split(' ').collect::<Vec<_>>();
+
+        let section = |name: &str| name.parse::<Section>().map_err(|_| error("Unknown section"));
+        // Nothing larger than memory is loaded, so adding sizes and offsets can't overflow
+        let offset = |text: &str| match number_field(text) {
+            Some(offset) if offset <= MEMORY_SIZE => Ok(offset),
+            Some(_) => Err(error("Offset past the end of memory")),
+            None => Err(error("Invalid offset")),
+        };
+
+        match fields[..] {
+            ["section", name, size] => {
+                let size = number_field(size).ok_or_else(|| error("Invalid size"))?;
+                if size > MEMORY_SIZE {
+                    return Err(error("Section larger than memory"));
+                }
+                match section(name)? {
+                    Section::Text => object.text.resize(size, 0),
+                    Section::Data => object.data.resize(size, 0),
+                    Section::Bss => object.bss = size,
+                }
+            }
+            ["bytes", name, start, hex] => {
+                let start = offset(start)?;
+                let bytes = hex_bytes(hex).ok_or_else(|| error("Invalid bytes"))?;
+                let contents = match section(name)? {
+                    Section::Text => &mut object.text,
+                    Section::Data => &mut object.data,
+                    Section::Bss => return Err(error("The bss section has no bytes")),
+                };
+                let field = start.checked_add(bytes.len()).and_then(|end| contents.get_mut(start..end));
+                field.ok_or_else(|| error("Bytes past the end of the section"))?.copy_from_slice(&bytes);
+            }
+            ["symbol", name, section_name, at, ref export @ ..] => {
+                let exported = match export {
+                    [] => false,
+                    ["export"] => true,
+                    _ => return Err(error("A symbol is followed by nothing but 'export'")),
+                };
+                object.symbols.push(Symbol { name: name.to_owned(), section: section(section_name)?, offset: offset(at)?, exported });
+                symbol_lines.push(line);
+            }
+            ["import", name] => object.imports.push(name.to_owned()),
+            ["reloc", section_name, at, kind, symbol, addend] => object.relocations.push(Relocation {
+                section: section(section_name)?,
+                offset: offset(at)?,
+                kind: kind.parse().map_err(|_| error("Unknown relocation"))?,
+                symbol: symbol.to_owned(),
+                addend: addend.parse().map_err(|_| error("Invalid addend"))?,
+            }),
+            [""] => {}
+            _ => return Err(error("Unknown record")),
+        }
+    }
+
+    // A label may follow the last byte of its section, but not go past it
+    for (symbol, line) in object.symbols.iter().zip(symbol_lines) {
+        if symbol.offset > object.size(symbol.section) {
+            return Err(format!("Line {}: Symbol past the end of its section", line));
+        }
+    }
+
+    Ok(object)
+}
+
+impl Library {
+    /// Reads a library. Errors name the line they're on.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut records = text.lines().enumerate().map(|(i, record)| (i + 1, record));
+
+        if records.next().map(|(_, record)| record) != Some("library") {
+            return Err("Line 1: A library starts with 'library'".to_string());
+        }
+
+        let mut library = Library::default();
+        let mut member: Option<(String, Vec<(usize, &str)>)> = None;
+
+        for (line, record) in records {
+            match (record.strip_prefix("member "), &mut member) {
+                (Some(name), _) => {
+                    if let Some((name, records)) = member.take() {
+                        library.members.push((name, parse_object(&records)?));
+                    }
+                    member = Some((name.to_owned(), Vec::new()));
+                }
+                (None, Some((_, records))) => records.push((line, record)),
+                (None, None) if record.is_empty() => {}
+                (None, None) => return Err(format!("Line {}: Objects of a library follow a 'member' record", line)),
+            }
+        }
+
+        if let Some((name, records)) = member {
+            library.members.push((name, parse_object(&records)?));
+        }
+
+        Ok(library)
+    }
+}
+
+fn number_field(text: &str) -> Option<usize> {
+    usize::from_str_radix(text.strip_prefix("0x")?, 16).ok()
+}
+
+fn hex_bytes(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "object")?;
+
+        for section in Section::ALL {
+            writeln!(f, "section {} {:#x}", section, self.size(section))?;
+        }
+
+        for (section, contents) in [(Section::Text, &self.text), (Section::Data, &self.data)] {
+            for (i, chunk) in contents.chunks(BYTES_PER_RECORD).enumerate() {
+                let hex = chunk.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+                writeln!(f, "bytes {} {:#x} {}", section, i * BYTES_PER_RECORD, hex)?;
+            }
+        }
+
+        for symbol in &self.symbols {
+            let export = if symbol.exported { " export" } else { "" };
+            writeln!(f, "symbol {} {} {:#x}{}", symbol.name, symbol.section, symbol.offset, export)?;
+        }
+
+        for name in &self.imports {
+            writeln!(f, "import {}", name)?;
+        }
+
+        for relocation in &self.relocations {
+            writeln!(f, "reloc {} {:#x} {} {} {}", relocation.section, relocation.offset, relocation.kind, relocation.symbol, relocation.addend)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Library {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "library")?;
+
+        for (name, object) in &self.members {
+            writeln!(f, "member {}", name)?;
+            write!(f, "{}", object)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble::{assemble_source, Options};
+
+    fn object(source: &str) -> Object {
+        let options = Options { relocatable: true, ..Options::default() };
+        Object::new(&assemble_source(source.to_string(), None, &options).ok().unwrap())
+    }
+
+    #[test]
+    fn test_object() {
+        let object = object(".global .start\n.extern .print\n.start: call .print\n    hlt\n.section data\n.pointer: .word .start\n.section bss\n.buffer: .space 0x100");
+
+        assert_eq!(object.to_string(), "\
+object
+section text 0x8
+section data 0x4
+section bss 0x100
+bytes text 0x0 a0000001b0000003
+bytes data 0x0 00000000
+symbol .start text 0x0 export
+symbol .pointer data 0x0
+symbol .buffer bss 0x0
+import .print
+reloc text 0x0 branch .print 0
+reloc data 0x0 word .start 0
+");
+        assert_eq!(Object::parse(&object.to_string()), Ok(object));
+    }
+
+    #[test]
+    fn test_library() {
+        let library = Library { members: vec![
+            ("a.obj".to_string(), object(".global .a\n.a: ret")),
+            ("b.obj".to_string(), object(".global .b\n.b: ret 1")),
+        ] };
+
+        assert_eq!(Library::parse(&library.to_string()), Ok(library));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Object::parse("symbol .a text 0x0"), Err("Line 1: An object starts with 'object'".to_string()));
+        assert_eq!(Object::parse("object\nsection text 0x4\nbytes text 0x2 00000000"), Err("Line 3: Bytes past the end of the section".to_string()));
+        assert_eq!(Object::parse("object\nsection text 0x4\nbytes text 0xffffffffffffffff 00"), Err("Line 3: Offset past the end of memory".to_string()));
+        assert_eq!(Object::parse("object\nreloc text 0xfffffffffffffffe word .a 0"), Err("Line 2: Offset past the end of memory".to_string()));
+        assert_eq!(Object::parse("object\nsection bss 0xffffffffffff"), Err("Line 2: Section larger than memory".to_string()));
+        assert_eq!(Object::parse("object\nsymbol .a data 0x100\nsection data 0x4"), Err("Line 2: Symbol past the end of its section".to_string()));
+        assert_eq!(Object::parse("object\nsymbol .a rodata 0x0"), Err("Line 2: Unknown section".to_string()));
+        assert_eq!(Object::parse("object\nreloc text 0x0 jump .a 0"), Err("Line 2: Unknown relocation".to_string()));
+        assert_eq!(Library::parse("library\nmember a.obj\nobject\nfoo"), Err("Line 4: Unknown record".to_string()));
+        assert_eq!(Library::parse("library\nobject"), Err("Line 2: Objects of a library follow a 'member' record".to_string()));
+    }
+}
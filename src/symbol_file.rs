@@ -0,0 +1,260 @@
+//! Symbols and source lines of an assembled program, for the emulator to show names and lines instead of bare addresses.
+//!
+//! The file is line based. Every line is one record, its fields separated by spaces:
+//!
+//! ```text
+//! label .start 0x0 code
+//! label .table 0x20 data 16
+//! equ SIZE 16
+//! line 0x0 4 3 prog.asm
+//! ```
+//!
+//! `label` gives a label's address and whether code or data follows it, data with its size in bytes.
+//! `equ` gives the value of a constant. `line` maps the bytes from an address on to a line of a file,
+//! the file is the rest of the record and `-` for source that wasn't read from a file. Line numbers start at 1.
+
+use crate::assembler::assemble::{Assembly, PlacementKind};
+use std::fmt::{self, Display};
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SymbolFile {
+    /// Labels in the order of their addresses
+    pub labels: Vec<Label>,
+    /// Constants in the order of their names
+    pub equates: Vec<(String, i128)>,
+    /// Lines in the order of their addresses
+    pub lines: Vec<SourceLine>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Label {
+    pub name: String,
+    pub address: usize,
+    /// Bytes of data up to the next label or instruction, `None` for a label of code
+    pub data: Option<usize>,
+}
+
+/// The line the bytes from `address` to `address + size` were assembled from
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SourceLine {
+    pub address: usize,
+    pub size: usize,
+    /// `None` for source that wasn't read from a file
+    pub file: Option<String>,
+    /// Starting at 1
+    pub line: usize,
+}
+
+impl SymbolFile {
+    /// Labels local to macro expansions and numeric labels can't be named, they're left out
+    pub fn new(assembly: &Assembly) -> Self {
+        let placements = &assembly.placements;
+        let mut labels = Vec::new();
+
+        for (i, placement) in placements.iter().enumerate() {
+            let PlacementKind::Label(name) = &placement.kind else { continue };
+
+            if name.contains('@') {
+                continue;
+            }
+
+            // Data runs up to whatever has a name or is code again
+            let next = placements[i + 1..].iter().find(|next| next.kind != PlacementKind::Data);
+            let follows_code = placements[i + 1..].iter().find(|next| !matches!(next.kind, PlacementKind::Label(_))).is_some_and(|next| next.kind == PlacementKind::Code);
+            let end = next.map_or(assembly.image.len(), |next| next.address);
+
+            labels.push(Label {
+                name: name.to_owned(),
+                address: placement.address,
+                data: (!follows_code).then_some(end - placement.address),
+            });
+        }
+
+        let mut equates = assembly.symbols.constants.iter().map(|(name, value)| (name.to_owned(), *value)).collect::<Vec<_>>();
+        equates.sort();
+
+        let lines = placements.iter()
+            .filter(|placement| placement.length > 0)
+            .map(|placement| SourceLine {
+                address: placement.address,
+                size: placement.length,
+                file: placement.position.file.as_ref().map(|file| file.display().to_string()),
+                line: placement.position.line + 1,
+            })
+            .collect();
+
+        Self { labels, equates, lines }
+    }
+
+    /// Reads a symbol file. Errors name the line they're on.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut file = SymbolFile::default();
+
+        for (i, record) in text.lines().enumerate() {
+            let error = |reason: &str| format!("Line {}: {}", i + 1, reason);
+            let mut fields = record.split(' ');
+
+            match fields.next() {
+                Some("label") => {
+                    let (Some(name), Some(address), Some(kind)) = (fields.next(), fields.next(), fields.next()) else {
+                        return Err(error("A label needs a name, an address and its kind"));
+                    };
+
+                    let data = match (kind, fields.next()) {
+                        ("code", None) => None,
+                        ("data", Some(size)) => Some(size.parse().map_err(|_| error("Invalid data size"))?),
+                        _ => return Err(error("A label is either code or data with a size")),
+                    };
+
+                    file.labels.push(Label { name: name.to_owned(), address: address_field(address).ok_or_else(|| error("Invalid address"))?, data });
+                }
+                Some("equ") => {
+                    let (Some(name), Some(value), None) = (fields.next(), fields.next(), fields.next()) else {
+                        return Err(error("A constant needs a name and a value"));
+                    };
+
+                    file.equates.push((name.to_owned(), value.parse().map_err(|_| error("Invalid value"))?));
+                }
+                Some("line") => {
+                    let (Some(address), Some(size), Some(line)) = (fields.next(), fields.next(), fields.next()) else {
+                        return Err(error("A line needs an address, a size, a line number and a file"));
+                    };
+
+                    let path = fields.collect::<Vec<_>>().join(" ");
+                    let address = address_field(address).ok_or_else(|| error("Invalid address"))?;
+                    let size: usize = size.parse().map_err(|_| error("Invalid size"))?;
+
+                    if address.checked_add(size).is_none() {
+                        return Err(error("A line ends past the end of the address space"));
+                    }
+
+                    file.lines.push(SourceLine {
+                        address,
+                        size,
+                        file: match path.as_str() {
+                            "" => return Err(error("A line needs an address, a size, a line number and a file")),
+                            "-" => None,
+                            _ => Some(path),
+                        },
+                        line: line.parse().map_err(|_| error("Invalid line number"))?,
+                    });
+                }
+                Some("") => {}
+                _ => return Err(error("Unknown record")),
+            }
+        }
+
+        file.labels.sort_by_key(|label| label.address);
+        file.lines.sort_by_key(|line| line.address);
+
+        Ok(file)
+    }
+
+    /// Address of a label
+    pub fn label(&self, name: &str) -> Option<usize> {
+        self.labels.iter().find(|label| label.name == name).map(|label| label.address)
+    }
+
+    /// An address as the closest label before it and the offset from there, like `.loop+0x8`
+    pub fn symbolize(&self, address: usize) -> Option<String> {
+        // Of labels at the same address the last one is closest to the code
+        let label = self.labels.iter().rev().find(|label| label.address <= address)?;
+
+        match address - label.address {
+            0 => Some(label.name.clone()),
+            offset => Some(format!("{}+{:#x}", label.name, offset)),
+        }
+    }
+
+    /// The line the byte at `address` was assembled from
+    pub fn line(&self, address: usize) -> Option<&SourceLine> {
+        let i = self.lines.partition_point(|line| line.address <= address).checked_sub(1)?;
+        let line = &self.lines[i];
+
+        (address < line.address + line.size).then_some(line)
+    }
+}
+
+fn address_field(text: &str) -> Option<usize> {
+    usize::from_str_radix(text.strip_prefix("0x")?, 16).ok()
+}
+
+impl Display for SymbolFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for label in &self.labels {
+            match label.data {
+                None => writeln!(f, "label {} {:#x} code", label.name, label.address)?,
+                Some(size) => writeln!(f, "label {} {:#x} data {}", label.name, label.address, size)?,
+            }
+        }
+
+        for (name, value) in &self.equates {
+            writeln!(f, "equ {} {}", name, value)?;
+        }
+
+        for line in &self.lines {
+            writeln!(f, "line {:#x} {} {} {}", line.address, line.size, line.line, line.file.as_deref().unwrap_or("-"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for SourceLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{} line {}", file, self.line),
+            None => write!(f, "Line {}", self.line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble::{assemble_source, Options};
+
+    fn symbols(source: &str) -> SymbolFile {
+        SymbolFile::new(&assemble_source(source.to_string(), None, &Options::default()).ok().unwrap())
+    }
+
+    #[test]
+    fn test_symbols() {
+        let file = symbols(".equ SIZE 2\n.start: li r1 .table\n1: ldr r2 r1 3\n.end\n    hlt\n.table: .word 1 SIZE\n.buffer\n.space 16");
+
+        assert_eq!(file.to_string(), "\
+label .start 0x0 code
+label .end 0xc code
+label .table 0x10 data 8
+label .buffer 0x18 data 16
+equ SIZE 2
+line 0x0 8 2 -
+line 0x8 4 3 -
+line 0xc 4 5 -
+line 0x10 8 6 -
+");
+        assert_eq!(SymbolFile::parse(&file.to_string()), Ok(file));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let file = symbols(".start\n    nop\n.loop\n    nop\n    b .loop");
+
+        assert_eq!(file.label(".loop"), Some(4));
+        assert_eq!(file.label(".nowhere"), None);
+        assert_eq!(file.symbolize(4).as_deref(), Some(".loop"));
+        assert_eq!(file.symbolize(8).as_deref(), Some(".loop+0x4"));
+        assert_eq!(file.line(9).map(ToString::to_string).as_deref(), Some("Line 5"));
+        assert_eq!(file.line(12), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(SymbolFile::parse("label .a 0x0 code\nlabel .b 12 code"), Err("Line 2: Invalid address".to_string()));
+        assert_eq!(SymbolFile::parse("label .a 0x0 data"), Err("Line 1: A label is either code or data with a size".to_string()));
+        assert_eq!(SymbolFile::parse("line 0x0 4 1"), Err("Line 1: A line needs an address, a size, a line number and a file".to_string()));
+        assert_eq!(SymbolFile::parse("line 0xffffffffffffffff 2 1 a.asm"), Err("Line 1: A line ends past the end of the address space".to_string()));
+        assert_eq!(SymbolFile::parse("symbol .a"), Err("Line 1: Unknown record".to_string()));
+        assert_eq!(SymbolFile::parse("line 0x0 4 1 my programs/a.asm").unwrap().lines[0].file.as_deref(), Some("my programs/a.asm"));
+    }
+}